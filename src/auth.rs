@@ -62,10 +62,9 @@ fn get_service_account_key_path() -> Result<String> {
     if let Ok(entries) = std::fs::read_dir(".") {
         for entry in entries.flatten() {
             if let Some(filename) = entry.file_name().to_str() {
-                if filename.ends_with(".json") && filename != "package.json" && filename != "config.json" {
-                    if Path::new(filename).exists() {
-                        return Ok(filename.to_string());
-                    }
+                if filename.ends_with(".json") && filename != "package.json" && filename != "config.json"
+                    && Path::new(filename).exists() {
+                    return Ok(filename.to_string());
                 }
             }
         }
@@ -101,8 +100,3 @@ async fn load_service_account_key(key_path: &str) -> Result<ServiceAccountKey> {
     
     Ok(service_account_key)
 }
-
-pub async fn get_access_token() -> Result<String> {
-    // TODO: Implement access token retrieval
-    todo!("Implement access token retrieval for service account")
-} 
\ No newline at end of file
@@ -1,7 +1,17 @@
 use anyhow::Result;
-use config::{Config, File};
+use config::{Config, ConfigError, File};
 use serde::{Deserialize, Serialize};
 use crate::args::Args;
+use crate::dates::DateOrder;
+use crate::exercises::ExerciseCatalog;
+use crate::fatigue::FatigueOptions;
+use crate::progression::ProgressionOptions;
+use crate::load::LoadUnit;
+use crate::transform::ParseOptions;
+use chrono::Weekday;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Arc;
 use tracing::{info, debug};
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -10,7 +20,21 @@ pub struct Cfg {
     pub block_range_template: String,
     pub state_path: String,
     pub output_csv: OutputCsvConfig,
+    pub output_sqlite: OutputSqliteConfig,
+    pub output_parquet: OutputParquetConfig,
     pub once: bool,
+    pub schedule: ScheduleConfig,
+    pub layout: LayoutConfig,
+    pub calendar: CalendarConfig,
+    pub dates: DateConfig,
+    pub units: UnitsConfig,
+    pub exercises: ExercisesConfig,
+    pub prs: PrConfig,
+    pub reports: ReportsConfig,
+    pub compliance: ComplianceConfig,
+    pub fatigue: FatigueConfig,
+    pub progression: ProgressionConfig,
+    pub maxes: MaxesConfig,
     
     // Optional: specify particular blocks to process (if None, auto-discover all)
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -31,6 +55,438 @@ pub struct Cfg {
 pub struct OutputCsvConfig {
     pub path: String,
     pub ensure: bool,
+    pub mode: CsvWriteMode,
+    // Upsert only: drop existing rows of a re-emitted block week that are no longer in the sheet
+    pub prune_missing: bool,
+    pub schema: CsvSchema,
+}
+
+/// SQLite output alongside the CSV; needs a build with the `sqlite` feature
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct OutputSqliteConfig {
+    pub enabled: bool,
+    pub path: String,
+    // Drop rows of a re-emitted block week that are no longer in the sheet
+    pub prune_missing: bool,
+}
+
+impl Default for OutputSqliteConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            path: "normalized/sheet_watch.db".to_string(),
+            prune_missing: false,
+        }
+    }
+}
+
+/// Parquet output alongside the CSV, one file per block week; needs a build with the
+/// `parquet` feature
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct OutputParquetConfig {
+    pub enabled: bool,
+    pub dir: String,
+}
+
+impl Default for OutputParquetConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            dir: "normalized/parquet".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CsvWriteMode {
+    /// Append new rows to the end of the file
+    Append,
+    /// Merge rows into the file by record ID, rewriting it atomically
+    Upsert,
+}
+
+impl FromStr for CsvWriteMode {
+    type Err = anyhow::Error;
+    
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "append" => Ok(CsvWriteMode::Append),
+            "upsert" => Ok(CsvWriteMode::Upsert),
+            other => anyhow::bail!("Unknown output_csv.mode '{}', expected 'append' or 'upsert'", other),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum CsvSchema {
+    /// One row per prescribed or actual record, told apart by `record_type`
+    #[default]
+    Long,
+    /// One row per exercise-session with prescribed and actual columns side by side
+    Wide,
+}
+
+impl FromStr for CsvSchema {
+    type Err = anyhow::Error;
+    
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "long" => Ok(CsvSchema::Long),
+            "wide" => Ok(CsvSchema::Wide),
+            other => anyhow::bail!("Unknown output_csv.schema '{}', expected 'long' or 'wide'", other),
+        }
+    }
+}
+
+/// Scheduler settings used when running without `--once`.
+/// A cron expression takes precedence over the fixed interval.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ScheduleConfig {
+    pub interval_secs: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cron: Option<String>,
+    pub run_on_start: bool,
+}
+
+/// Header labels that identify each `WorkoutRecord` field's column within a week.
+/// Labels are matched case-insensitively, ignoring punctuation ("Load /" matches "Load").
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct FieldLabels {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sets: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reps: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub load: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub load_instruction: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rpe: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+    // Date the session was actually performed; overrides the calendar-derived workout_date
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub date_performed: Option<String>,
+}
+
+impl FieldLabels {
+    /// Fill any label not set here from `fallback`
+    fn merged_with(&self, fallback: &FieldLabels) -> FieldLabels {
+        FieldLabels {
+            sets: self.sets.clone().or_else(|| fallback.sets.clone()),
+            reps: self.reps.clone().or_else(|| fallback.reps.clone()),
+            load: self.load.clone().or_else(|| fallback.load.clone()),
+            load_instruction: self.load_instruction.clone().or_else(|| fallback.load_instruction.clone()),
+            rpe: self.rpe.clone().or_else(|| fallback.rpe.clone()),
+            notes: self.notes.clone().or_else(|| fallback.notes.clone()),
+            date_performed: self.date_performed.clone().or_else(|| fallback.date_performed.clone()),
+        }
+    }
+}
+
+/// Column labels for the prescribed and actual groups of a week
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct LayoutProfile {
+    #[serde(default)]
+    pub prescribed: FieldLabels,
+    #[serde(default)]
+    pub actual: FieldLabels,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct LayoutConfig {
+    #[serde(default)]
+    pub prescribed: FieldLabels,
+    #[serde(default)]
+    pub actual: FieldLabels,
+    // Per-block overrides keyed by tab title (matched case-insensitively)
+    #[serde(default)]
+    pub blocks: HashMap<String, LayoutProfile>,
+}
+
+impl LayoutConfig {
+    /// The layout for a block: its override (if any) on top of the global labels
+    pub fn profile_for(&self, block_name: &str) -> LayoutProfile {
+        let global = LayoutProfile {
+            prescribed: self.prescribed.clone(),
+            actual: self.actual.clone(),
+        };
+        
+        match self.blocks.iter().find(|(name, _)| name.eq_ignore_ascii_case(block_name)) {
+            Some((_, block)) => LayoutProfile {
+                prescribed: block.prescribed.merged_with(&global.prescribed),
+                actual: block.actual.merged_with(&global.actual),
+            },
+            None => global,
+        }
+    }
+}
+
+impl Default for LayoutConfig {
+    fn default() -> Self {
+        Self {
+            prescribed: FieldLabels {
+                sets: Some("Sets".to_string()),
+                reps: Some("Reps".to_string()),
+                load: None,
+                load_instruction: Some("Load".to_string()),
+                rpe: Some("RPE".to_string()),
+                notes: None,
+                date_performed: None,
+            },
+            actual: FieldLabels {
+                sets: Some("Sets".to_string()),
+                reps: Some("Reps".to_string()),
+                load: Some("Load".to_string()),
+                load_instruction: None,
+                rpe: Some("RPE".to_string()),
+                notes: Some("Notes".to_string()),
+                date_performed: None,
+            },
+            blocks: HashMap::new(),
+        }
+    }
+}
+
+/// Which weekday each "DAY N" of a week falls on
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct CalendarConfig {
+    // e.g. ["Mon", "Wed", "Fri", "Sat"]: Day 1 is Monday, Day 3 is Friday.
+    // When unset, Day N is N-1 days after the week's start date.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub training_days: Option<Vec<String>>,
+    // Per-block overrides keyed by tab title (matched case-insensitively)
+    #[serde(default)]
+    pub blocks: HashMap<String, BlockCalendar>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct BlockCalendar {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub training_days: Option<Vec<String>>,
+}
+
+impl CalendarConfig {
+    /// Training days for a block: its override if set, otherwise the global list
+    pub fn training_days_for(&self, block_name: &str) -> Option<&[String]> {
+        self.blocks
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(block_name))
+            .and_then(|(_, block)| block.training_days.as_deref())
+            .or(self.training_days.as_deref())
+    }
+    
+    fn all_training_days(&self) -> impl Iterator<Item = &String> {
+        self.training_days
+            .iter()
+            .chain(self.blocks.values().filter_map(|block| block.training_days.as_ref()))
+            .flatten()
+    }
+}
+
+/// How date cells are read
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct DateConfig {
+    // Order of all-numeric dates; spelled-out months ("May 19, 2025") and ISO dates are
+    // read regardless
+    #[serde(default)]
+    pub order: DateOrder,
+    // Per-block overrides keyed by tab title (matched case-insensitively)
+    #[serde(default)]
+    pub blocks: HashMap<String, BlockDates>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct BlockDates {
+    pub order: DateOrder,
+}
+
+impl DateConfig {
+    pub fn order_for(&self, block_name: &str) -> DateOrder {
+        self.blocks
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(block_name))
+            .map_or(self.order, |(_, block)| block.order)
+    }
+}
+
+/// Units that parsed values are normalized to
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct UnitsConfig {
+    // Loads written without a unit are read in this unit, and all loads are converted to it
+    #[serde(default)]
+    pub load: LoadUnit,
+}
+
+/// Where exercise names are canonicalized from
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct ExercisesConfig {
+    // TOML or YAML file mapping canonical exercise IDs to the names used in the sheet
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aliases_path: Option<String>,
+}
+
+/// Personal-record tracking
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct PrConfig {
+    pub enabled: bool,
+    // New PRs are appended here; the running bests are kept in the state file
+    pub path: String,
+}
+
+impl Default for PrConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            path: "normalized/prs.csv".to_string(),
+        }
+    }
+}
+
+/// Volume and tonnage summaries written next to the normalized CSV
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ReportsConfig {
+    pub enabled: bool,
+    // Directory for volume_weekly.csv, volume_blocks.csv and volume.json
+    pub dir: String,
+}
+
+impl Default for ReportsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            dir: "normalized".to_string(),
+        }
+    }
+}
+
+/// Prescribed-vs-actual compliance reports
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ComplianceConfig {
+    pub enabled: bool,
+    // One row per prescription; the rows of every re-normalized week are replaced
+    pub path: String,
+    // One summary row per block week
+    pub weekly_path: String,
+}
+
+impl Default for ComplianceConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            path: "normalized/compliance.csv".to_string(),
+            weekly_path: "normalized/compliance_weekly.csv".to_string(),
+        }
+    }
+}
+
+/// Weekly session load, acute:chronic ratio, monotony and strain
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct FatigueConfig {
+    pub enabled: bool,
+    // Rewritten on every run from the sessions kept in the state file
+    pub path: String,
+    // Weeks averaged for the chronic load
+    pub chronic_weeks: u32,
+    // Weeks whose acute:chronic ratio is above this are flagged
+    pub acwr_spike: f64,
+}
+
+impl Default for FatigueConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            path: "normalized/fatigue.csv".to_string(),
+            chronic_weeks: 4,
+            acwr_spike: 1.5,
+        }
+    }
+}
+
+impl FatigueConfig {
+    pub fn options(&self) -> FatigueOptions {
+        FatigueOptions {
+            chronic_weeks: self.chronic_weeks,
+            acwr_spike: self.acwr_spike,
+        }
+    }
+}
+
+/// Suggested loads for upcoming prescriptions from recent e1RMs and the RPE chart
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ProgressionConfig {
+    pub enabled: bool,
+    // Rewritten on every run with the suggestions for the next `horizon_days`
+    pub path: String,
+    // Only e1RMs from this many days before a session count
+    pub lookback_days: u32,
+    pub horizon_days: u32,
+    // Rounding step for suggested loads; defaults to 5 for lb and 2.5 for kg
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub increment: Option<f64>,
+    // Target RPE for "find" prescriptions that don't give one
+    pub find_rpe: f64,
+    // Fill the suggestions into the sheet's prescribed load column; weeks without one are
+    // skipped rather than overwriting the load instruction
+    pub write_back: bool,
+}
+
+impl Default for ProgressionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            path: "normalized/next_session.csv".to_string(),
+            lookback_days: 42,
+            horizon_days: 7,
+            increment: None,
+            find_rpe: 8.0,
+            write_back: false,
+        }
+    }
+}
+
+impl ProgressionConfig {
+    pub fn options(&self, unit: LoadUnit) -> ProgressionOptions {
+        ProgressionOptions {
+            lookback_days: self.lookback_days as i64,
+            horizon_days: self.horizon_days as i64,
+            increment: self.increment.unwrap_or(unit.increment()),
+            find_rpe: self.find_rpe,
+        }
+    }
+}
+
+/// The maxes table that percentage and "base on max" load instructions are resolved against
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct MaxesConfig {
+    pub enabled: bool,
+    // The table in use, rewritten on every run
+    pub path: String,
+    // Lifts not listed below use their best e1RM
+    pub from_history: bool,
+    // Rounding step for resolved loads; defaults to 5 for lb and 2.5 for kg
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub increment: Option<f64>,
+    // Maxes keyed by exercise name or alias, in the configured unit
+    #[serde(default)]
+    pub lifts: HashMap<String, f64>,
+}
+
+impl Default for MaxesConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            path: "normalized/maxes.csv".to_string(),
+            from_history: true,
+            increment: None,
+            lifts: HashMap::new(),
+        }
+    }
 }
 
 impl Cfg {
@@ -87,6 +543,154 @@ impl Cfg {
                 if let Ok(ensure) = config.get_bool("output_csv.ensure") {
                     cfg.output_csv.ensure = ensure;
                 }
+                if let Ok(mode) = config.get_string("output_csv.mode") {
+                    cfg.output_csv.mode = mode.parse()?;
+                }
+                if let Ok(prune_missing) = config.get_bool("output_csv.prune_missing") {
+                    cfg.output_csv.prune_missing = prune_missing;
+                }
+                if let Ok(schema) = config.get_string("output_csv.schema") {
+                    cfg.output_csv.schema = schema.parse()?;
+                }
+                if let Ok(enabled) = config.get_bool("output_sqlite.enabled") {
+                    cfg.output_sqlite.enabled = enabled;
+                }
+                if let Ok(path) = config.get_string("output_sqlite.path") {
+                    cfg.output_sqlite.path = path;
+                }
+                if let Ok(prune_missing) = config.get_bool("output_sqlite.prune_missing") {
+                    cfg.output_sqlite.prune_missing = prune_missing;
+                }
+                if let Ok(enabled) = config.get_bool("output_parquet.enabled") {
+                    cfg.output_parquet.enabled = enabled;
+                }
+                if let Ok(dir) = config.get_string("output_parquet.dir") {
+                    cfg.output_parquet.dir = dir;
+                }
+                if let Ok(interval_secs) = config.get_int("schedule.interval_secs") {
+                    cfg.schedule.interval_secs = interval_secs.max(0) as u64;
+                }
+                if let Ok(cron) = config.get_string("schedule.cron") {
+                    if !cron.trim().is_empty() {
+                        cfg.schedule.cron = Some(cron);
+                    }
+                }
+                if let Ok(run_on_start) = config.get_bool("schedule.run_on_start") {
+                    cfg.schedule.run_on_start = run_on_start;
+                }
+                // A malformed table is an error rather than a silent fall back to the defaults
+                match config.get::<FieldLabels>("layout.prescribed") {
+                    Ok(prescribed) => cfg.layout.prescribed = prescribed.merged_with(&cfg.layout.prescribed),
+                    Err(ConfigError::NotFound(_)) => {}
+                    Err(e) => anyhow::bail!("Invalid layout.prescribed: {}", e),
+                }
+                match config.get::<FieldLabels>("layout.actual") {
+                    Ok(actual) => cfg.layout.actual = actual.merged_with(&cfg.layout.actual),
+                    Err(ConfigError::NotFound(_)) => {}
+                    Err(e) => anyhow::bail!("Invalid layout.actual: {}", e),
+                }
+                match config.get::<HashMap<String, LayoutProfile>>("layout.blocks") {
+                    Ok(blocks) => cfg.layout.blocks = blocks,
+                    Err(ConfigError::NotFound(_)) => {}
+                    Err(e) => anyhow::bail!("Invalid layout.blocks: {}", e),
+                }
+                match config.get::<Vec<String>>("calendar.training_days") {
+                    Ok(training_days) => cfg.calendar.training_days = Some(training_days),
+                    Err(ConfigError::NotFound(_)) => {}
+                    Err(e) => anyhow::bail!("Invalid calendar.training_days: {}", e),
+                }
+                match config.get::<HashMap<String, BlockCalendar>>("calendar.blocks") {
+                    Ok(blocks) => cfg.calendar.blocks = blocks,
+                    Err(ConfigError::NotFound(_)) => {}
+                    Err(e) => anyhow::bail!("Invalid calendar.blocks: {}", e),
+                }
+                if let Ok(order) = config.get_string("dates.order") {
+                    cfg.dates.order = order.parse()?;
+                }
+                match config.get::<HashMap<String, BlockDates>>("dates.blocks") {
+                    Ok(blocks) => cfg.dates.blocks = blocks,
+                    Err(ConfigError::NotFound(_)) => {}
+                    Err(e) => anyhow::bail!("Invalid dates.blocks: {}", e),
+                }
+                if let Ok(unit) = config.get_string("units.load") {
+                    cfg.units.load = unit.parse()?;
+                }
+                if let Ok(aliases_path) = config.get_string("exercises.aliases_path") {
+                    if !aliases_path.trim().is_empty() {
+                        cfg.exercises.aliases_path = Some(aliases_path);
+                    }
+                }
+                if let Ok(enabled) = config.get_bool("prs.enabled") {
+                    cfg.prs.enabled = enabled;
+                }
+                if let Ok(path) = config.get_string("prs.path") {
+                    cfg.prs.path = path;
+                }
+                if let Ok(enabled) = config.get_bool("reports.enabled") {
+                    cfg.reports.enabled = enabled;
+                }
+                if let Ok(dir) = config.get_string("reports.dir") {
+                    cfg.reports.dir = dir;
+                }
+                if let Ok(enabled) = config.get_bool("compliance.enabled") {
+                    cfg.compliance.enabled = enabled;
+                }
+                if let Ok(path) = config.get_string("compliance.path") {
+                    cfg.compliance.path = path;
+                }
+                if let Ok(weekly_path) = config.get_string("compliance.weekly_path") {
+                    cfg.compliance.weekly_path = weekly_path;
+                }
+                if let Ok(enabled) = config.get_bool("fatigue.enabled") {
+                    cfg.fatigue.enabled = enabled;
+                }
+                if let Ok(path) = config.get_string("fatigue.path") {
+                    cfg.fatigue.path = path;
+                }
+                if let Ok(chronic_weeks) = config.get_int("fatigue.chronic_weeks") {
+                    cfg.fatigue.chronic_weeks = chronic_weeks.max(0) as u32;
+                }
+                if let Ok(acwr_spike) = config.get_float("fatigue.acwr_spike") {
+                    cfg.fatigue.acwr_spike = acwr_spike;
+                }
+                if let Ok(enabled) = config.get_bool("progression.enabled") {
+                    cfg.progression.enabled = enabled;
+                }
+                if let Ok(path) = config.get_string("progression.path") {
+                    cfg.progression.path = path;
+                }
+                if let Ok(lookback_days) = config.get_int("progression.lookback_days") {
+                    cfg.progression.lookback_days = lookback_days.max(0) as u32;
+                }
+                if let Ok(horizon_days) = config.get_int("progression.horizon_days") {
+                    cfg.progression.horizon_days = horizon_days.max(0) as u32;
+                }
+                if let Ok(increment) = config.get_float("progression.increment") {
+                    cfg.progression.increment = Some(increment);
+                }
+                if let Ok(find_rpe) = config.get_float("progression.find_rpe") {
+                    cfg.progression.find_rpe = find_rpe;
+                }
+                if let Ok(write_back) = config.get_bool("progression.write_back") {
+                    cfg.progression.write_back = write_back;
+                }
+                if let Ok(enabled) = config.get_bool("maxes.enabled") {
+                    cfg.maxes.enabled = enabled;
+                }
+                if let Ok(path) = config.get_string("maxes.path") {
+                    cfg.maxes.path = path;
+                }
+                if let Ok(from_history) = config.get_bool("maxes.from_history") {
+                    cfg.maxes.from_history = from_history;
+                }
+                if let Ok(increment) = config.get_float("maxes.increment") {
+                    cfg.maxes.increment = Some(increment);
+                }
+                match config.get::<HashMap<String, f64>>("maxes.lifts") {
+                    Ok(lifts) => cfg.maxes.lifts = lifts,
+                    Err(ConfigError::NotFound(_)) => {}
+                    Err(e) => anyhow::bail!("Invalid maxes.lifts: {}", e),
+                }
                 debug!("Loaded configuration from file");
             } else {
                 debug!("Could not parse config file, using defaults");
@@ -140,6 +744,11 @@ impl Cfg {
                 anyhow::bail!("block_range_template cannot be empty");
             }
             
+            // Edited weeks are re-emitted in full, which would duplicate them in append mode
+            if self.output_csv.mode == CsvWriteMode::Append {
+                anyhow::bail!("output_csv.mode = \"append\" is only supported with raw_range; block mode re-parses edited weeks and needs \"upsert\"");
+            }
+            
             // Check configuration mode
             if let Some(ref specific_blocks) = self.specific_blocks {
                 if specific_blocks.is_empty() {
@@ -177,28 +786,120 @@ impl Cfg {
             anyhow::bail!("output_csv.path cannot be empty");
         }
         
+        if self.prs.enabled && self.prs.path.is_empty() {
+            anyhow::bail!("prs.path cannot be empty when prs.enabled = true");
+        }
+        
+        if self.reports.enabled && self.reports.dir.is_empty() {
+            anyhow::bail!("reports.dir cannot be empty when reports.enabled = true");
+        }
+        
+        if self.compliance.enabled && (self.compliance.path.is_empty() || self.compliance.weekly_path.is_empty()) {
+            anyhow::bail!("compliance.path and compliance.weekly_path cannot be empty when compliance.enabled = true");
+        }
+        
+        if self.fatigue.enabled {
+            if self.fatigue.path.is_empty() {
+                anyhow::bail!("fatigue.path cannot be empty when fatigue.enabled = true");
+            }
+            if self.fatigue.chronic_weeks == 0 {
+                anyhow::bail!("fatigue.chronic_weeks must be at least 1");
+            }
+            if self.fatigue.acwr_spike <= 0.0 {
+                anyhow::bail!("fatigue.acwr_spike must be positive");
+            }
+        }
+        
+        if self.progression.enabled {
+            if self.progression.path.is_empty() {
+                anyhow::bail!("progression.path cannot be empty when progression.enabled = true");
+            }
+            if self.progression.increment.is_some_and(|increment| increment <= 0.0) {
+                anyhow::bail!("progression.increment must be positive");
+            }
+            if !(6.0..=10.0).contains(&self.progression.find_rpe) {
+                anyhow::bail!("progression.find_rpe must be between 6 and 10");
+            }
+        }
+        
+        if self.maxes.enabled {
+            if self.maxes.path.is_empty() {
+                anyhow::bail!("maxes.path cannot be empty when maxes.enabled = true");
+            }
+            if self.maxes.increment.is_some_and(|increment| increment <= 0.0) {
+                anyhow::bail!("maxes.increment must be positive");
+            }
+            if let Some((name, _)) = self.maxes.lifts.iter().find(|(_, &max)| max <= 0.0) {
+                anyhow::bail!("maxes.lifts.{} must be positive", name);
+            }
+        }
+        
+        for day in self.calendar.all_training_days() {
+            Weekday::from_str(day.trim())
+                .map_err(|_| anyhow::anyhow!("Invalid weekday '{}' in calendar.training_days", day))?;
+        }
+        
+        if self.output_csv.prune_missing && self.output_csv.mode != CsvWriteMode::Upsert {
+            anyhow::bail!("output_csv.prune_missing requires output_csv.mode = \"upsert\"");
+        }
+        
+        if self.output_sqlite.enabled {
+            if !cfg!(feature = "sqlite") {
+                anyhow::bail!("output_sqlite.enabled requires a build with the sqlite feature (cargo build --features sqlite)");
+            }
+            if self.output_sqlite.path.is_empty() {
+                anyhow::bail!("output_sqlite.path cannot be empty when output_sqlite.enabled = true");
+            }
+        }
+        
+        if self.output_parquet.enabled {
+            if !cfg!(feature = "parquet") {
+                anyhow::bail!("output_parquet.enabled requires a build with the parquet feature (cargo build --features parquet)");
+            }
+            if self.output_parquet.dir.is_empty() {
+                anyhow::bail!("output_parquet.dir cannot be empty when output_parquet.enabled = true");
+            }
+        }
+        
+        if !self.once {
+            if let Some(ref cron) = self.schedule.cron {
+                cron::Schedule::from_str(cron)
+                    .map_err(|e| anyhow::anyhow!("Invalid schedule.cron expression '{}': {}", cron, e))?;
+            } else if self.schedule.interval_secs == 0 {
+                anyhow::bail!("schedule.interval_secs must be greater than 0");
+            }
+        }
+        
         info!("Configuration validation passed");
         Ok(())
     }
     
-    /// Get block ranges if using legacy min/max mode (deprecated - use auto-discovery instead)
-    pub fn get_legacy_block_ranges(&self) -> Option<Vec<String>> {
-        if let Some(ref raw_range) = self.raw_range {
-            // Legacy mode: return single range
-            Some(vec![raw_range.clone()])
-        } else if let Some(ref specific_blocks) = self.specific_blocks {
+    /// Parser settings for a block, with any per-block overrides applied
+    pub fn parse_options_for(&self, block_name: &str, exercises: &Arc<ExerciseCatalog>) -> ParseOptions {
+        let training_days = self.calendar.training_days_for(block_name).map(|days| {
+            days.iter().filter_map(|day| Weekday::from_str(day.trim()).ok()).collect()
+        });
+        
+        ParseOptions {
+            layout: self.layout.profile_for(block_name),
+            training_days,
+            date_order: self.dates.order_for(block_name),
+            load_unit: self.units.load,
+            exercises: Arc::clone(exercises),
+        }
+    }
+    
+    /// Whether a discovered block tab should be processed under the configured block selection
+    pub fn includes_block(&self, block_number: u32) -> bool {
+        if let Some(ref specific_blocks) = self.specific_blocks {
             // Specific blocks mode
-            Some(specific_blocks.iter()
-                 .map(|&block_num| self.block_range_template.replace("{}", &block_num.to_string()))
-                 .collect())
+            specific_blocks.contains(&block_number)
         } else if let (Some(min_block), Some(max_block)) = (self.min_block, self.max_block) {
             // Legacy min/max mode
-            Some((min_block..=max_block)
-                 .map(|block_num| self.block_range_template.replace("{}", &block_num.to_string()))
-                 .collect())
+            (min_block..=max_block).contains(&block_number)
         } else {
-            // Auto-discovery mode - ranges will be determined dynamically
-            None
+            // Auto-discovery mode - every block tab is processed
+            true
         }
     }
 }
@@ -212,12 +913,94 @@ impl Default for Cfg {
             output_csv: OutputCsvConfig {
                 path: "normalized/normalized.csv".to_string(),
                 ensure: true,
+                mode: CsvWriteMode::Upsert,
+                prune_missing: false,
+                schema: CsvSchema::Long,
             },
+            output_sqlite: OutputSqliteConfig::default(),
+            output_parquet: OutputParquetConfig::default(),
             once: false,
+            schedule: ScheduleConfig {
+                interval_secs: 3600,
+                cron: None,
+                run_on_start: true,
+            },
+            layout: LayoutConfig::default(),
+            calendar: CalendarConfig::default(),
+            dates: DateConfig::default(),
+            units: UnitsConfig::default(),
+            exercises: ExercisesConfig::default(),
+            prs: PrConfig::default(),
+            reports: ReportsConfig::default(),
+            compliance: ComplianceConfig::default(),
+            fatigue: FatigueConfig::default(),
+            progression: ProgressionConfig::default(),
+            maxes: MaxesConfig::default(),
             specific_blocks: None, // Auto-discover all blocks
             min_block: None,       // Legacy support
             max_block: None,       // Legacy support
             raw_range: None,       // Legacy support
         }
     }
-} 
\ No newline at end of file
+} 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load(toml: &str) -> Result<Cfg> {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        std::fs::write(&path, toml).unwrap();
+        Cfg::load(Args {
+            sheet_id: None,
+            raw_range: None,
+            csv_path: None,
+            once: true,
+            log_level: "info".to_string(),
+            config: path.to_str().unwrap().to_string(),
+        })
+    }
+
+    #[test]
+    fn reads_layout_and_calendar_tables() {
+        let cfg = load(r#"
+            [layout.actual]
+            load = "Weight"
+
+            [layout.blocks."Block 3".prescribed]
+            load_instruction = "Intensity"
+
+            [calendar]
+            training_days = ["Mon", "Wed", "Fri"]
+
+            [calendar.blocks."Block 3"]
+            training_days = ["Tue", "Thu"]
+        "#).unwrap();
+        assert_eq!(cfg.layout.actual.load.as_deref(), Some("Weight"));
+        assert_eq!(cfg.layout.actual.sets.as_deref(), Some("Sets"));
+        let block = cfg.layout.profile_for("Block 3");
+        assert_eq!(block.prescribed.load_instruction.as_deref(), Some("Intensity"));
+        assert_eq!(cfg.calendar.training_days.as_ref().map(Vec::len), Some(3));
+        assert_eq!(cfg.calendar.blocks.len(), 1);
+    }
+
+    #[test]
+    fn loads_the_example_config() {
+        load(include_str!("../config/config.example.toml")).unwrap();
+    }
+
+    #[test]
+    fn rejects_malformed_layout_and_calendar_tables() {
+        let errors = [
+            "[layout.prescribed]\nsetz = \"Sets\"",
+            "[layout.actual]\nload = [\"Weight\"]",
+            "[layout.blocks.\"Block 3\"]\nprescribd = { sets = \"Sets\" }",
+            "[calendar]\ntraining_days = \"Mon\"",
+            "[calendar.blocks.\"Block 3\"]\ntraining_day = [\"Mon\"]",
+        ];
+        for toml in errors {
+            assert!(load(toml).is_err(), "accepted {}", toml);
+        }
+    }
+}
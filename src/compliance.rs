@@ -0,0 +1,483 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use crate::strength::round_to;
+use crate::transform::{group_by_row, RowRecords, WorkoutRecord};
+
+/// An actual RPE within this of the target counts as on target
+pub const RPE_TOLERANCE: f64 = 0.5;
+
+/// What happened to a prescription
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PairStatus {
+    /// Actuals were logged for it
+    Completed,
+    /// The session was logged but this exercise wasn't
+    Skipped,
+    /// Nothing was logged for the session and its date has passed
+    Missed,
+    /// Nothing was logged yet and the session is today or later
+    Pending,
+    /// Actuals with no prescription on their row
+    Unplanned,
+}
+
+impl PairStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PairStatus::Completed => "completed",
+            PairStatus::Skipped => "skipped",
+            PairStatus::Missed => "missed",
+            PairStatus::Pending => "pending",
+            PairStatus::Unplanned => "unplanned",
+        }
+    }
+
+    /// Status of a prescription with nothing logged for it
+    fn unlogged(session_logged: bool, workout_date: NaiveDate, today: NaiveDate) -> PairStatus {
+        if session_logged {
+            PairStatus::Skipped
+        } else if workout_date < today {
+            PairStatus::Missed
+        } else {
+            PairStatus::Pending
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RpeDeviation {
+    Over,
+    Under,
+    OnTarget,
+}
+
+impl RpeDeviation {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RpeDeviation::Over => "over",
+            RpeDeviation::Under => "under",
+            RpeDeviation::OnTarget => "on_target",
+        }
+    }
+}
+
+/// A prescription and the actuals logged on the same sheet row
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompliancePair {
+    /// The sheet row's record ID without the record type
+    pub id: String,
+    pub block_name: String,
+    pub week_start_date: NaiveDate,
+    pub week_number: u32,
+    pub day_number: u32,
+    pub workout_date: NaiveDate,
+    pub exercise_id: String,
+    pub exercise: String,
+    pub status: PairStatus,
+    pub sets_prescribed: Option<u32>,
+    /// None when nothing was logged
+    pub sets_done: Option<u32>,
+    pub reps_prescribed: Option<String>,
+    /// Reps of each logged set
+    pub reps_done: Vec<u32>,
+    /// Every logged set within the prescribed range (at or above it for AMRAP sets)
+    pub reps_in_range: Option<bool>,
+    /// Highest prescribed RPE
+    pub rpe_target: Option<f64>,
+    /// Highest logged RPE
+    pub rpe_actual: Option<f64>,
+}
+
+impl CompliancePair {
+    pub fn sets_delta(&self) -> Option<i64> {
+        Some(self.sets_done? as i64 - self.sets_prescribed? as i64)
+    }
+
+    pub fn rpe_delta(&self) -> Option<f64> {
+        Some(round_to(self.rpe_actual? - self.rpe_target?, 0.1))
+    }
+
+    pub fn rpe_deviation(&self) -> Option<RpeDeviation> {
+        self.rpe_delta().map(|delta| {
+            if delta > RPE_TOLERANCE {
+                RpeDeviation::Over
+            } else if delta < -RPE_TOLERANCE {
+                RpeDeviation::Under
+            } else {
+                RpeDeviation::OnTarget
+            }
+        })
+    }
+
+    pub fn to_csv_headers() -> Vec<String> {
+        [
+            "id", "block_name", "week_start_date", "week_number", "day_number", "workout_date",
+            "exercise_id", "exercise", "status", "sets_prescribed", "sets_done", "sets_delta",
+            "reps_prescribed", "reps_done", "reps_in_range", "rpe_target", "rpe_actual", "rpe_delta",
+            "rpe_deviation",
+        ]
+        .into_iter()
+        .map(str::to_string)
+        .collect()
+    }
+
+    pub fn to_csv_row(&self) -> Vec<String> {
+        vec![
+            self.id.clone(),
+            self.block_name.clone(),
+            self.week_start_date.to_string(),
+            self.week_number.to_string(),
+            self.day_number.to_string(),
+            self.workout_date.to_string(),
+            self.exercise_id.clone(),
+            self.exercise.clone(),
+            self.status.as_str().to_string(),
+            self.sets_prescribed.map(|n| n.to_string()).unwrap_or_default(),
+            self.sets_done.map(|n| n.to_string()).unwrap_or_default(),
+            self.sets_delta().map(|n| n.to_string()).unwrap_or_default(),
+            self.reps_prescribed.clone().unwrap_or_default(),
+            self.reps_done.iter().map(|r| r.to_string()).collect::<Vec<_>>().join(","),
+            self.reps_in_range.map(|b| b.to_string()).unwrap_or_default(),
+            self.rpe_target.map(|v| v.to_string()).unwrap_or_default(),
+            self.rpe_actual.map(|v| v.to_string()).unwrap_or_default(),
+            self.rpe_delta().map(|v| v.to_string()).unwrap_or_default(),
+            self.rpe_deviation().map(|d| d.as_str().to_string()).unwrap_or_default(),
+        ]
+    }
+}
+
+/// Compliance totals of one block week
+#[derive(Debug, Clone, Default)]
+pub struct WeekCompliance {
+    pub block_name: String,
+    pub week_start_date: NaiveDate,
+    pub week_number: u32,
+    pub sessions_prescribed: u32,
+    pub sessions_completed: u32,
+    pub sessions_missed: u32,
+    pub exercises_prescribed: u32,
+    pub exercises_completed: u32,
+    pub sets_prescribed: u32,
+    /// Sets done against a prescription; unplanned work isn't counted
+    pub sets_done: u32,
+    /// Prescriptions whose logged reps were checked against the range, and how many were in it
+    pub reps_checked: u32,
+    pub reps_in_range: u32,
+    pub rpe_over: u32,
+    pub rpe_under: u32,
+    pub rpe_on_target: u32,
+}
+
+impl WeekCompliance {
+    pub fn to_csv_headers() -> Vec<String> {
+        [
+            "id", "block_name", "week_start_date", "week_number", "sessions_prescribed",
+            "sessions_completed", "sessions_missed", "exercises_prescribed", "exercises_completed",
+            "sets_prescribed", "sets_done", "set_completion", "reps_checked", "reps_in_range",
+            "rpe_over", "rpe_under", "rpe_on_target",
+        ]
+        .into_iter()
+        .map(str::to_string)
+        .collect()
+    }
+
+    pub fn to_csv_row(&self) -> Vec<String> {
+        let week_start_date = self.week_start_date.to_string();
+        // Share of prescribed sets done, as a percentage
+        let set_completion = (self.sets_prescribed > 0)
+            .then(|| round_to(self.sets_done as f64 * 100.0 / self.sets_prescribed as f64, 0.1));
+        vec![
+            format!("{}_{}", self.block_name, week_start_date),
+            self.block_name.clone(),
+            week_start_date,
+            self.week_number.to_string(),
+            self.sessions_prescribed.to_string(),
+            self.sessions_completed.to_string(),
+            self.sessions_missed.to_string(),
+            self.exercises_prescribed.to_string(),
+            self.exercises_completed.to_string(),
+            self.sets_prescribed.to_string(),
+            self.sets_done.to_string(),
+            set_completion.map(|v| v.to_string()).unwrap_or_default(),
+            self.reps_checked.to_string(),
+            self.reps_in_range.to_string(),
+            self.rpe_over.to_string(),
+            self.rpe_under.to_string(),
+            self.rpe_on_target.to_string(),
+        ]
+    }
+}
+
+/// Reps of each set an actual record logged; empty for timed and to-RPE sets
+fn logged_reps(record: &WorkoutRecord) -> Vec<u32> {
+    match record.reps.as_ref().filter(|r| r.seconds.is_none() && !r.to_rpe) {
+        Some(reps) if !reps.per_set.is_empty() => reps.per_set.clone(),
+        Some(reps) => reps
+            .min
+            .map(|min| vec![min; record.set_count().unwrap_or(1) as usize])
+            .unwrap_or_default(),
+        None => Vec::new(),
+    }
+}
+
+fn highest_rpe<'a>(records: impl Iterator<Item = &'a WorkoutRecord>) -> Option<f64> {
+    records
+        .filter_map(|record| record.rpe.as_ref())
+        .flat_map(|rpe| rpe.per_set.iter().copied())
+        .reduce(f64::max)
+}
+
+/// Link each actual record to the prescription on its sheet row, in sheet order.
+///
+/// A prescription and its actuals share a block, week, day and exercise because they come
+/// from the same row. Sessions with nothing logged are missed once `today` is past their date.
+pub fn pair_records(records: &[WorkoutRecord], today: NaiveDate) -> Vec<CompliancePair> {
+    // Sessions with at least one actual
+    let logged_sessions: HashSet<(&str, NaiveDate, u32)> = records
+        .iter()
+        .filter(|record| record.record_type == "actual")
+        .map(|record| (record.block_name.as_str(), record.week_start_date, record.day_number))
+        .collect();
+
+    group_by_row(records)
+        .into_iter()
+        .map(|row| {
+            let first = row.first();
+            let RowRecords { key, prescribed, actuals } = row;
+            let session = (first.block_name.as_str(), first.week_start_date, first.day_number);
+            let status = match (prescribed, actuals.is_empty()) {
+                (None, _) => PairStatus::Unplanned,
+                (Some(_), false) => PairStatus::Completed,
+                (Some(p), true) => PairStatus::unlogged(logged_sessions.contains(&session), p.workout_date, today),
+            };
+
+            let reps_done: Vec<u32> = actuals.iter().flat_map(|a| logged_reps(a)).collect();
+            let target = prescribed
+                .and_then(|p| p.reps.as_ref())
+                .filter(|r| r.seconds.is_none() && !r.to_rpe);
+            let reps_in_range = target.and_then(|target| {
+                let low = target.min?;
+                let high = if target.amrap { u32::MAX } else { target.max.unwrap_or(low) };
+                (!reps_done.is_empty()).then(|| reps_done.iter().all(|reps| (low..=high).contains(reps)))
+            });
+
+            CompliancePair {
+                id: key.to_string(),
+                block_name: first.block_name.clone(),
+                week_start_date: first.week_start_date,
+                week_number: first.week_number,
+                day_number: first.day_number,
+                workout_date: first.workout_date,
+                exercise_id: first.exercise_id.clone(),
+                exercise: first.exercise_canonical.clone(),
+                status,
+                sets_prescribed: prescribed.and_then(WorkoutRecord::set_count),
+                sets_done: (!actuals.is_empty()).then(|| actuals.iter().map(|a| a.set_count().unwrap_or(1)).sum()),
+                reps_prescribed: prescribed.and_then(|p| p.reps.as_ref()).map(|r| r.raw.clone()),
+                reps_done,
+                reps_in_range,
+                rpe_target: highest_rpe(prescribed.into_iter()),
+                rpe_actual: highest_rpe(actuals.into_iter()),
+            }
+        })
+        .collect()
+}
+
+/// Compliance pairs of every block week, keyed by block name then week start date
+pub type ComplianceHistory = BTreeMap<String, BTreeMap<NaiveDate, Vec<CompliancePair>>>;
+
+/// Fold newly paired records into the stored pairs, then re-check every stored prescription
+/// with nothing logged against `today`, so a pending session turns into a missed one even when
+/// its week is never re-normalized again.
+///
+/// With `whole_weeks` (block mode) a re-normalized week's pairs replace the stored ones;
+/// otherwise (legacy raw_range mode, where rows arrive a few at a time) they are merged in by ID.
+pub fn record_pairs(history: &mut ComplianceHistory, pairs: Vec<CompliancePair>, whole_weeks: bool, today: NaiveDate) {
+    let mut weeks: BTreeMap<(String, NaiveDate), Vec<CompliancePair>> = BTreeMap::new();
+    for pair in pairs {
+        weeks.entry((pair.block_name.clone(), pair.week_start_date)).or_default().push(pair);
+    }
+
+    for ((block_name, week_start), pairs) in weeks {
+        let stored = history.entry(block_name).or_default().entry(week_start).or_default();
+        if whole_weeks {
+            *stored = pairs;
+            continue;
+        }
+        for pair in pairs {
+            match stored.iter_mut().find(|existing| existing.id == pair.id) {
+                Some(existing) => *existing = pair,
+                None => stored.push(pair),
+            }
+        }
+    }
+
+    for pairs in history.values_mut().flat_map(|weeks| weeks.values_mut()) {
+        refresh_statuses(pairs, today);
+    }
+}
+
+/// Re-derive the status of a week's prescriptions with nothing logged
+fn refresh_statuses(pairs: &mut [CompliancePair], today: NaiveDate) {
+    // A session is logged when any of its rows has actuals
+    let mut logged: HashMap<u32, bool> = HashMap::new();
+    for pair in pairs.iter() {
+        *logged.entry(pair.day_number).or_default() |= matches!(pair.status, PairStatus::Completed | PairStatus::Unplanned);
+    }
+
+    for pair in pairs.iter_mut() {
+        if matches!(pair.status, PairStatus::Skipped | PairStatus::Missed | PairStatus::Pending) {
+            pair.status = PairStatus::unlogged(logged[&pair.day_number], pair.workout_date, today);
+        }
+    }
+}
+
+/// What a week's pairs showed about one session
+#[derive(Default)]
+struct SessionSeen {
+    prescribed: bool,
+    logged: bool,
+    missed: bool,
+}
+
+/// Roll pairs up into one summary per block week, in block and week order
+pub fn summarize_weeks(pairs: &[CompliancePair]) -> Vec<WeekCompliance> {
+    let mut weeks: BTreeMap<(&str, NaiveDate), WeekCompliance> = BTreeMap::new();
+    let mut sessions: BTreeMap<(&str, NaiveDate, u32), SessionSeen> = BTreeMap::new();
+
+    for pair in pairs {
+        let week = weeks.entry((&pair.block_name, pair.week_start_date)).or_insert_with(|| WeekCompliance {
+            block_name: pair.block_name.clone(),
+            week_start_date: pair.week_start_date,
+            week_number: pair.week_number,
+            ..WeekCompliance::default()
+        });
+        let session = sessions.entry((&pair.block_name, pair.week_start_date, pair.day_number)).or_default();
+
+        if pair.status != PairStatus::Unplanned {
+            session.prescribed = true;
+            week.exercises_prescribed += 1;
+            week.sets_prescribed += pair.sets_prescribed.unwrap_or(0);
+            week.sets_done += pair.sets_done.unwrap_or(0);
+        }
+        if matches!(pair.status, PairStatus::Completed | PairStatus::Unplanned) {
+            session.logged = true;
+        }
+        if pair.status == PairStatus::Completed {
+            week.exercises_completed += 1;
+        }
+        if pair.status == PairStatus::Missed {
+            session.missed = true;
+        }
+
+        if let Some(in_range) = pair.reps_in_range {
+            week.reps_checked += 1;
+            week.reps_in_range += in_range as u32;
+        }
+        match pair.rpe_deviation() {
+            Some(RpeDeviation::Over) => week.rpe_over += 1,
+            Some(RpeDeviation::Under) => week.rpe_under += 1,
+            Some(RpeDeviation::OnTarget) => week.rpe_on_target += 1,
+            None => {}
+        }
+    }
+
+    for ((block_name, week_start, _), session) in sessions {
+        let week = weeks.get_mut(&(block_name, week_start)).expect("session belongs to a week");
+        if session.prescribed {
+            week.sessions_prescribed += 1;
+            week.sessions_completed += session.logged as u32;
+            week.sessions_missed += session.missed as u32;
+        }
+    }
+
+    weeks.into_values().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sheets::SheetRow;
+    use crate::transform::{normalize_block_data, ParseOptions};
+
+    fn row(row_number: usize, cells: &[&str]) -> SheetRow {
+        SheetRow { row_number, cells: cells.iter().map(|cell| cell.to_string()).collect() }
+    }
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 5, day).unwrap()
+    }
+
+    /// Day 1 has a squat logged short of its reps, a skipped bench and an unplanned curl;
+    /// nothing is logged for Day 2
+    fn records() -> Vec<WorkoutRecord> {
+        let rows = vec![
+            row(1, &["", "", "5/19/2025"]),
+            row(2, &["", "", "Week 1"]),
+            row(6, &["", "DAY 1"]),
+            row(7, &["", "Squat", "", "3", "5", "300", "8", "", "300", "3", "5,5,4", "9"]),
+            row(8, &["", "Bench", "", "3", "8", "200", "7"]),
+            row(9, &["", "Curl", "", "", "", "", "", "", "30", "3", "12", ""]),
+            row(10, &["", "DAY 2"]),
+            row(11, &["", "Deadlift", "", "3", "5", "400", "8"]),
+        ];
+        normalize_block_data(rows, "Block 1", &ParseOptions::default()).unwrap().records
+    }
+
+    fn status_of(pairs: &[CompliancePair], exercise: &str) -> PairStatus {
+        pairs.iter().find(|pair| pair.exercise == exercise).unwrap().status
+    }
+
+    #[test]
+    fn pairs_prescriptions_with_their_row_actuals() {
+        let pairs = pair_records(&records(), date(20));
+
+        assert_eq!(pairs.len(), 4);
+        let squat = pairs.iter().find(|pair| pair.exercise == "Squat").unwrap();
+        assert_eq!(squat.status, PairStatus::Completed);
+        assert_eq!((squat.sets_prescribed, squat.sets_done, squat.sets_delta()), (Some(3), Some(3), Some(0)));
+        assert_eq!(squat.reps_done, vec![5, 5, 4]);
+        assert_eq!(squat.reps_in_range, Some(false));
+        assert_eq!(squat.rpe_deviation(), Some(RpeDeviation::Over));
+
+        assert_eq!(status_of(&pairs, "Bench"), PairStatus::Skipped);
+        assert_eq!(status_of(&pairs, "Curl"), PairStatus::Unplanned);
+        assert_eq!(status_of(&pairs, "Deadlift"), PairStatus::Pending);
+    }
+
+    #[test]
+    fn pending_sessions_turn_missed_once_their_date_passes() {
+        let mut history = ComplianceHistory::new();
+        record_pairs(&mut history, pair_records(&records(), date(20)), true, date(20));
+        record_pairs(&mut history, Vec::new(), true, date(21));
+
+        let pairs = &history["Block 1"][&date(19)];
+        assert_eq!(status_of(pairs, "Deadlift"), PairStatus::Missed);
+        assert_eq!(status_of(pairs, "Bench"), PairStatus::Skipped);
+
+        let weeks = summarize_weeks(pairs);
+        assert_eq!(weeks.len(), 1);
+        let week = &weeks[0];
+        assert_eq!((week.sessions_prescribed, week.sessions_completed, week.sessions_missed), (2, 1, 1));
+        assert_eq!((week.exercises_prescribed, week.exercises_completed), (3, 1));
+        assert_eq!((week.sets_prescribed, week.sets_done), (9, 3));
+        assert_eq!((week.reps_checked, week.reps_in_range, week.rpe_over), (1, 0, 1));
+    }
+
+    #[test]
+    fn whole_weeks_replace_stored_pairs() {
+        let mut history = ComplianceHistory::new();
+        record_pairs(&mut history, pair_records(&records(), date(20)), true, date(20));
+        let squat_only: Vec<CompliancePair> = pair_records(&records(), date(20))
+            .into_iter()
+            .filter(|pair| pair.exercise == "Squat")
+            .collect();
+
+        record_pairs(&mut history, squat_only.clone(), false, date(20));
+        assert_eq!(history["Block 1"][&date(19)].len(), 4);
+        record_pairs(&mut history, squat_only, true, date(20));
+        assert_eq!(history["Block 1"][&date(19)].len(), 1);
+    }
+}
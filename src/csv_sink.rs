@@ -1,69 +1,564 @@
 use anyhow::Result;
-use csv::Writer;
-use std::fs::OpenOptions;
+use chrono::NaiveDate;
+use csv::{Reader, Writer};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File, OpenOptions};
 use std::path::{Path, PathBuf};
-use tracing::{info, debug};
-use crate::transform::WorkoutRecord;
+use tracing::{info, debug, warn};
+use crate::cfg::CsvSchema;
+use crate::transform::{record_id_family, WorkoutRecord};
+use crate::wide::{join_records, WideRecord};
+
+/// Outcome of an upsert, for logging
+#[derive(Debug, Default, Clone, Copy)]
+pub struct UpsertStats {
+    pub inserted: usize,
+    pub updated: usize,
+    pub unchanged: usize,
+    pub pruned: usize,
+    pub total: usize,
+}
+
+/// Header and rows of `records` in the given schema
+fn encode(records: &[WorkoutRecord], schema: CsvSchema) -> (Vec<String>, Vec<Vec<String>>) {
+    match schema {
+        CsvSchema::Long => (WorkoutRecord::to_csv_headers(), records.iter().map(WorkoutRecord::to_csv_row).collect()),
+        CsvSchema::Wide => (WideRecord::to_csv_headers(), join_records(records).iter().map(WideRecord::to_csv_row).collect()),
+    }
+}
+
+pub fn append(csv_path: &str, rows: &[WorkoutRecord], schema: CsvSchema, ensure_directories: bool) -> Result<()> {
+    let (headers, rows) = encode(rows, schema);
+    append_rows(csv_path, &headers, &rows, ensure_directories)
+}
+
+/// Append rows to a CSV, writing `headers` first when the file is new. When the existing file
+/// was written with a different header, its rows are mapped onto `headers` by name and the file
+/// is rewritten, so the appended rows never end up under a stale header.
+pub fn append_rows(csv_path: &str, headers: &[String], rows: &[Vec<String>], ensure_directories: bool) -> Result<()> {
+    let path = validate_csv_path(csv_path, ensure_directories)?;
 
-pub fn append(csv_path: &str, rows: &[WorkoutRecord], ensure_directories: bool) -> Result<()> {
-    let path = Path::new(csv_path);
-    
     info!("Appending {} rows to CSV file: {}", rows.len(), csv_path);
-    
+
     if rows.is_empty() {
         debug!("No rows to append, skipping");
         return Ok(());
     }
-    
-    // Ensure directory exists if requested
-    if ensure_directories {
-        if let Some(parent) = path.parent() {
-            std::fs::create_dir_all(parent)?;
-            debug!("Created directory: {:?}", parent);
-        }
-    }
-    
+
     let file_exists = path.exists();
     let needs_header = !file_exists;
-    
+
+    if file_exists && !has_headers(&path, headers)? {
+        info!("CSV header of {} changed, rewriting existing rows under the new header", csv_path);
+        let mut merged = project_rows(&path, headers)?;
+        merged.extend(rows.iter().cloned());
+        write_atomically(&path, headers, &merged)?;
+        info!("Successfully appended {} rows to {}", rows.len(), csv_path);
+        return Ok(());
+    }
+
     // Open file for appending
     let file = OpenOptions::new()
         .create(true)
         .append(true)
-        .open(path)?;
-    
+        .open(&path)?;
+
     let mut writer = Writer::from_writer(file);
-    
+
     // Write header if this is a new file
     if needs_header {
         info!("Writing CSV header to new file");
-        writer.write_record(&WorkoutRecord::to_csv_headers())?;
+        writer.write_record(headers)?;
     }
-    
+
     // Write all rows
     for row in rows {
-        writer.write_record(&row.to_csv_row())?;
+        writer.write_record(row)?;
     }
-    
+
     writer.flush()?;
     info!("Successfully appended {} rows to {}", rows.len(), csv_path);
-    
+
+    Ok(())
+}
+
+/// Merge rows into the CSV keyed by record ID.
+///
+/// Existing rows with a matching ID are replaced when their content changed, new IDs are
+/// appended, and rows from older column layouts are mapped onto the current header by name.
+/// Rows sharing an incoming row's ID family (the ID without a per-set suffix) but not its ID
+/// are dropped, so a row split into per-set rows doesn't leave its single row behind.
+/// With `prune_missing`, existing rows are dropped when they belong to a (block, week) present
+/// in `rows` but their ID is not. The file is rewritten via a temp file and rename, so readers
+/// never see a partially written CSV.
+pub fn upsert(
+    csv_path: &str,
+    rows: &[WorkoutRecord],
+    schema: CsvSchema,
+    ensure_directories: bool,
+    prune_missing: bool,
+) -> Result<UpsertStats> {
+    let path = validate_csv_path(csv_path, ensure_directories)?;
+
+    info!("Upserting {} rows into CSV file: {}", rows.len(), csv_path);
+
+    let (headers, rows) = encode(rows, schema);
+    let id_col = column_index(&headers, "id")?;
+    let processed_at_col = column_index(&headers, "processed_at")?;
+    let block_col = column_index(&headers, "block_name")?;
+    let week_col = column_index(&headers, "week_number")?;
+
+    // Rows written in the other schema can't be mapped onto this one, so the file is rebuilt
+    // from this run's rows; block mode re-emits every block after a schema change
+    let mut stats = UpsertStats::default();
+    let mut existing = match file_schema(csv_path)? {
+        Some(file_schema) if file_schema != schema => {
+            let dropped = get_row_count(csv_path)?;
+            info!("{} was written in the {:?} schema, rebuilding it as {:?} ({} old rows dropped)",
+                  csv_path, file_schema, schema, dropped);
+            stats.pruned = dropped;
+            Vec::new()
+        }
+        _ => read_rows(&path, &headers)?,
+    };
+    let mut positions: HashMap<String, usize> = existing
+        .iter()
+        .enumerate()
+        .map(|(index, row)| (row[id_col].clone(), index))
+        .collect();
+
+    let mut incoming_ids = HashSet::new();
+    let mut incoming_scopes = HashSet::new();
+
+    for new_row in rows {
+        incoming_ids.insert(new_row[id_col].clone());
+        incoming_scopes.insert((new_row[block_col].clone(), new_row[week_col].clone()));
+
+        match positions.get(&new_row[id_col]) {
+            Some(&index) => {
+                if same_content(&existing[index], &new_row, processed_at_col) {
+                    stats.unchanged += 1;
+                } else {
+                    existing[index] = new_row;
+                    stats.updated += 1;
+                }
+            }
+            None => {
+                positions.insert(new_row[id_col].clone(), existing.len());
+                existing.push(new_row);
+                stats.inserted += 1;
+            }
+        }
+    }
+
+    // A row split into per-set rows (or joined back) changes IDs, so the old shape of an
+    // incoming row is always dropped: "…_actual" when "…_actual_s1".."_s3" arrive
+    let incoming_families: HashSet<&str> = incoming_ids.iter().map(|id| record_id_family(id)).collect();
+    let before = existing.len();
+    existing.retain(|row| {
+        incoming_ids.contains(&row[id_col]) || !incoming_families.contains(record_id_family(&row[id_col]))
+    });
+    stats.pruned += before - existing.len();
+
+    if prune_missing {
+        let before = existing.len();
+        existing.retain(|row| {
+            incoming_ids.contains(&row[id_col])
+                || !incoming_scopes.contains(&(row[block_col].clone(), row[week_col].clone()))
+        });
+        stats.pruned += before - existing.len();
+    }
+
+    stats.total = existing.len();
+
+    if stats.inserted == 0 && stats.updated == 0 && stats.pruned == 0 && path.exists() && has_headers(&path, &headers)? {
+        info!("CSV already up to date ({} unchanged rows), skipping rewrite", stats.unchanged);
+        return Ok(stats);
+    }
+
+    write_atomically(&path, &headers, &existing)?;
+
+    info!("Upserted into {}: {} inserted, {} updated, {} unchanged, {} pruned ({} rows total)",
+          csv_path, stats.inserted, stats.updated, stats.unchanged, stats.pruned, stats.total);
+
+    Ok(stats)
+}
+
+/// Replace a CSV's contents with `rows`, atomically
+pub fn replace_rows(csv_path: &str, headers: &[String], rows: &[Vec<String>], ensure_directories: bool) -> Result<()> {
+    let path = validate_csv_path(csv_path, ensure_directories)?;
+    write_atomically(&path, headers, rows)?;
+    info!("Wrote {} rows to {}", rows.len(), csv_path);
+    Ok(())
+}
+
+/// Replace every existing row of the (block_name, week_start_date) pairs present in `rows`
+/// with `rows`, and drop the rows of the `cleared` weeks, keeping the rows of other weeks.
+/// Rows are kept grouped by block and week, in their original order within a week.
+pub fn replace_weeks(
+    csv_path: &str,
+    headers: &[String],
+    rows: &[Vec<String>],
+    cleared: &[(String, NaiveDate)],
+    ensure_directories: bool,
+) -> Result<()> {
+    let path = validate_csv_path(csv_path, ensure_directories)?;
+    let block_col = column_index(headers, "block_name")?;
+    let week_col = column_index(headers, "week_start_date")?;
+
+    let cleared: Vec<(String, String)> = cleared.iter().map(|(block, week)| (block.clone(), week.to_string())).collect();
+    let weeks: HashSet<(&str, &str)> = rows
+        .iter()
+        .map(|row| (row[block_col].as_str(), row[week_col].as_str()))
+        .chain(cleared.iter().map(|(block, week)| (block.as_str(), week.as_str())))
+        .collect();
+    let mut merged: Vec<Vec<String>> = project_rows(&path, headers)?
+        .into_iter()
+        .filter(|row| !weeks.contains(&(row[block_col].as_str(), row[week_col].as_str())))
+        .collect();
+    let kept = merged.len();
+    merged.extend(rows.iter().cloned());
+    merged.sort_by(|a, b| (&a[block_col], &a[week_col]).cmp(&(&b[block_col], &b[week_col])));
+
+    write_atomically(&path, headers, &merged)?;
+    info!("Replaced {} weeks in {}: {} new rows, {} kept", weeks.len(), csv_path, rows.len(), kept);
+    Ok(())
+}
+
+/// The schema an existing CSV was written in, told apart by the long schema's `record_type`
+/// column. None when the file doesn't exist yet.
+pub fn file_schema(csv_path: &str) -> Result<Option<CsvSchema>> {
+    let path = Path::new(csv_path);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let headers = Reader::from_path(path)?.headers()?.clone();
+    if headers.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(if headers.iter().any(|h| h == "record_type") { CsvSchema::Long } else { CsvSchema::Wide }))
+}
+
+/// Read an existing CSV and project every row onto `headers` by column name.
+/// Later rows win when the file already contains duplicate IDs.
+fn read_rows(path: &Path, headers: &[String]) -> Result<Vec<Vec<String>>> {
+    let id_col = column_index(headers, "id")?;
+    let mut rows: Vec<Vec<String>> = Vec::new();
+    let mut positions: HashMap<String, usize> = HashMap::new();
+    let mut duplicates = 0;
+
+    for row in project_rows(path, headers)? {
+        match positions.get(&row[id_col]) {
+            Some(&index) => {
+                rows[index] = row;
+                duplicates += 1;
+            }
+            None => {
+                positions.insert(row[id_col].clone(), rows.len());
+                rows.push(row);
+            }
+        }
+    }
+
+    if duplicates > 0 {
+        warn!("Collapsed {} duplicate IDs while reading {:?}", duplicates, path);
+    }
+
+    debug!("Read {} existing rows from {:?}", rows.len(), path);
+    Ok(rows)
+}
+
+/// Read an existing CSV and map every row onto `headers` by column name, leaving columns
+/// the file doesn't have empty
+fn project_rows(path: &Path, headers: &[String]) -> Result<Vec<Vec<String>>> {
+    if !path.exists() {
+        debug!("CSV file does not exist yet: {:?}", path);
+        return Ok(Vec::new());
+    }
+
+    let mut reader = Reader::from_path(path)?;
+    let file_headers = reader.headers()?.clone();
+
+    let mapping: Vec<Option<usize>> = headers
+        .iter()
+        .map(|header| file_headers.iter().position(|h| h == header))
+        .collect();
+
+    let missing: Vec<&String> = headers.iter().zip(&mapping)
+        .filter(|(_, index)| index.is_none())
+        .map(|(header, _)| header)
+        .collect();
+    if !missing.is_empty() {
+        debug!("Existing CSV is missing columns {:?}, they will be left empty", missing);
+    }
+
+    let mut rows = Vec::new();
+    for result in reader.records() {
+        let record = result?;
+        rows.push(
+            mapping
+                .iter()
+                .map(|index| index.and_then(|i| record.get(i)).unwrap_or_default().to_string())
+                .collect(),
+        );
+    }
+    Ok(rows)
+}
+
+/// True when the existing file at `path` was written with exactly `headers`
+fn has_headers(path: &Path, headers: &[String]) -> Result<bool> {
+    let file_headers = Reader::from_path(path)?.headers()?.clone();
+    Ok(file_headers.iter().eq(headers.iter().map(String::as_str)))
+}
+
+fn write_atomically(path: &Path, headers: &[String], rows: &[Vec<String>]) -> Result<()> {
+    let file_name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| anyhow::anyhow!("Invalid CSV path: {:?}", path))?;
+    let temp_path = path.with_file_name(format!(".{}.tmp", file_name));
+
+    {
+        let file = File::create(&temp_path)?;
+        let mut writer = Writer::from_writer(file);
+        writer.write_record(headers)?;
+        for row in rows {
+            writer.write_record(row)?;
+        }
+        writer.flush()?;
+        writer
+            .into_inner()
+            .map_err(|e| anyhow::anyhow!("Failed to flush {:?}: {}", temp_path, e))?
+            .sync_all()?;
+    }
+
+    fs::rename(&temp_path, path)?;
+    debug!("Replaced {:?} with {} rows", path, rows.len());
+
     Ok(())
 }
 
-pub fn validate_csv_path(path: &str) -> Result<PathBuf> {
+fn same_content(existing: &[String], new_row: &[String], processed_at_col: usize) -> bool {
+    existing.len() == new_row.len()
+        && existing.iter().zip(new_row).enumerate()
+            .all(|(index, (old, new))| index == processed_at_col || old == new)
+}
+
+fn column_index(headers: &[String], name: &str) -> Result<usize> {
+    headers
+        .iter()
+        .position(|h| h == name)
+        .ok_or_else(|| anyhow::anyhow!("CSV header is missing column '{}'", name))
+}
+
+pub fn validate_csv_path(path: &str, ensure_directories: bool) -> Result<PathBuf> {
     let path_buf = PathBuf::from(path);
-    
-    // TODO: Add validation logic for CSV path
-    // - Check if parent directory is writable
-    // - Validate file extension
-    // - Check disk space if needed
-    
+
+    if path_buf.extension().and_then(|ext| ext.to_str()) != Some("csv") {
+        warn!("Output path does not have a .csv extension: {}", path);
+    }
+
+    if let Some(parent) = path_buf.parent().filter(|p| !p.as_os_str().is_empty()) {
+        if ensure_directories {
+            fs::create_dir_all(parent)?;
+            debug!("Ensured directory exists: {:?}", parent);
+        } else if !parent.exists() {
+            anyhow::bail!("Output directory does not exist: {:?} (set output_csv.ensure = true to create it)", parent);
+        }
+    }
+
     Ok(path_buf)
 }
 
-pub fn get_row_count(_csv_path: &str) -> Result<usize> {
-    // TODO: Implement function to count existing rows in CSV
-    // This can be useful for verification
-    todo!("Implement row counting for existing CSV files")
-} 
\ No newline at end of file
+pub fn get_row_count(csv_path: &str) -> Result<usize> {
+    let path = Path::new(csv_path);
+    if !path.exists() {
+        return Ok(0);
+    }
+
+    let mut reader = Reader::from_path(path)?;
+    let mut count = 0;
+    for record in reader.records() {
+        record?;
+        count += 1;
+    }
+
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sheets::SheetRow;
+    use crate::transform::{normalize_block_data, ParseOptions};
+
+    fn row(row_number: usize, cells: &[&str]) -> SheetRow {
+        SheetRow { row_number, cells: cells.iter().map(|cell| cell.to_string()).collect() }
+    }
+
+    /// One week of `block` with a squat and a bench row, each prescribed and logged, in the
+    /// default column offsets. The squat's actual reps are `squat_reps`.
+    fn block_records(block: &str, squat_reps: &str) -> Vec<WorkoutRecord> {
+        let rows = vec![
+            row(1, &["", "", "5/19/2025"]),
+            row(2, &["", "", "Week 1"]),
+            row(6, &["", "DAY 1"]),
+            row(7, &["", "Squat", "", "3", "5", "300", "8", "", "300", "3", squat_reps, "8"]),
+            row(8, &["", "Bench", "", "3", "8", "200", "7", "", "200", "3", "8", "7"]),
+        ];
+        normalize_block_data(rows, block, &ParseOptions::default()).unwrap().records
+    }
+
+    fn records() -> Vec<WorkoutRecord> {
+        block_records("Block 1", "5")
+    }
+
+    fn temp_csv() -> (tempfile::TempDir, String) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("normalized.csv").to_str().unwrap().to_string();
+        (dir, path)
+    }
+
+    fn ids(path: &str) -> Vec<String> {
+        read(path).1.into_iter().map(|row| row[0].clone()).collect()
+    }
+
+    fn read(path: &str) -> (Vec<String>, Vec<Vec<String>>) {
+        let mut reader = Reader::from_path(path).unwrap();
+        let headers = reader.headers().unwrap().iter().map(str::to_string).collect();
+        let rows = reader.records().map(|r| r.unwrap().iter().map(str::to_string).collect()).collect();
+        (headers, rows)
+    }
+
+    #[test]
+    fn upsert_inserts_updates_and_skips_unchanged_rows() {
+        let (_dir, path) = temp_csv();
+
+        let stats = upsert(&path, &records(), CsvSchema::Long, false, false).unwrap();
+        assert_eq!((stats.inserted, stats.updated, stats.unchanged, stats.total), (4, 0, 0, 4));
+
+        let stats = upsert(&path, &records(), CsvSchema::Long, false, false).unwrap();
+        assert_eq!((stats.inserted, stats.updated, stats.unchanged), (0, 0, 4));
+
+        let mut edited = records();
+        edited[1].notes = Some("felt heavy".to_string());
+        let stats = upsert(&path, &edited, CsvSchema::Long, false, false).unwrap();
+        assert_eq!((stats.inserted, stats.updated, stats.unchanged, stats.total), (0, 1, 3, 4));
+        let (headers, rows) = read(&path);
+        let notes = column_index(&headers, "notes").unwrap();
+        assert_eq!(rows[1][notes], "felt heavy");
+    }
+
+    #[test]
+    fn upsert_replaces_a_row_split_into_sets() {
+        let (_dir, path) = temp_csv();
+        upsert(&path, &records(), CsvSchema::Long, false, false).unwrap();
+        let squat_actual = ids(&path).into_iter().find(|id| id.ends_with("_r7_actual")).unwrap();
+
+        let stats = upsert(&path, &block_records("Block 1", "5,5,4"), CsvSchema::Long, false, false).unwrap();
+        assert_eq!((stats.inserted, stats.pruned), (3, 1));
+        let ids = ids(&path);
+        assert!(!ids.contains(&squat_actual));
+        for set in 1..=3 {
+            assert!(ids.contains(&format!("{}_s{}", squat_actual, set)));
+        }
+
+        // Joining the sets back into one row drops the per-set rows
+        let stats = upsert(&path, &records(), CsvSchema::Long, false, false).unwrap();
+        assert_eq!((stats.inserted, stats.pruned, stats.total), (1, 3, 4));
+    }
+
+    #[test]
+    fn prune_missing_drops_deleted_rows_of_emitted_weeks_only() {
+        let (_dir, path) = temp_csv();
+        let mut both = records();
+        both.extend(block_records("Block 2", "5"));
+        upsert(&path, &both, CsvSchema::Long, false, false).unwrap();
+
+        // The bench row was deleted from Block 1; Block 2 wasn't re-emitted
+        let squat_only: Vec<WorkoutRecord> = records().into_iter().filter(|r| r.exercise_canonical == "Squat").collect();
+        let stats = upsert(&path, &squat_only, CsvSchema::Long, false, false).unwrap();
+        assert_eq!((stats.pruned, stats.total), (0, 8));
+
+        let stats = upsert(&path, &squat_only, CsvSchema::Long, false, true).unwrap();
+        assert_eq!((stats.pruned, stats.total), (2, 6));
+        let ids = ids(&path);
+        assert_eq!(ids.iter().filter(|id| id.starts_with("block2_")).count(), 4);
+        assert!(!ids.iter().any(|id| id.starts_with("block1_") && id.contains("_r8_")));
+    }
+
+    #[test]
+    fn writes_through_a_temp_file() {
+        let (dir, path) = temp_csv();
+        upsert(&path, &records(), CsvSchema::Long, false, false).unwrap();
+        let files: Vec<String> = fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(files, vec!["normalized.csv"]);
+        assert_eq!(get_row_count(&path).unwrap(), 4);
+    }
+
+    #[test]
+    fn upsert_maps_rows_from_an_older_header() {
+        let (_dir, path) = temp_csv();
+        upsert(&path, &records(), CsvSchema::Long, false, false).unwrap();
+
+        // Write the same rows without the `cue` column, as an older version would have
+        let (headers, rows) = read(&path);
+        let cue = column_index(&headers, "cue").unwrap();
+        let drop_cue = |row: &Vec<String>| -> Vec<String> {
+            row.iter().enumerate().filter(|(index, _)| *index != cue).map(|(_, cell)| cell.clone()).collect()
+        };
+        let old_rows: Vec<Vec<String>> = rows.iter().map(drop_cue).collect();
+        write_atomically(Path::new(&path), &drop_cue(&headers), &old_rows).unwrap();
+
+        let stats = upsert(&path, &records(), CsvSchema::Long, false, false).unwrap();
+        assert_eq!((stats.inserted, stats.unchanged, stats.total), (0, 4, 4));
+        assert_eq!(read(&path).0, headers);
+    }
+
+    #[test]
+    fn append_rewrites_rows_under_a_changed_header() {
+        let (_dir, path) = temp_csv();
+        let header = |names: &[&str]| -> Vec<String> { names.iter().map(|name| name.to_string()).collect() };
+
+        append_rows(&path, &header(&["id", "a"]), &[header(&["1", "x"])], false).unwrap();
+        append_rows(&path, &header(&["id", "a"]), &[header(&["2", "y"])], false).unwrap();
+        append_rows(&path, &header(&["id", "b", "a"]), &[header(&["3", "z", "w"])], false).unwrap();
+
+        let (headers, rows) = read(&path);
+        assert_eq!(headers, header(&["id", "b", "a"]));
+        assert_eq!(rows, vec![header(&["1", "", "x"]), header(&["2", "", "y"]), header(&["3", "z", "w"])]);
+    }
+
+    #[test]
+    fn replace_weeks_keeps_other_weeks() {
+        let (_dir, path) = temp_csv();
+        let headers: Vec<String> = ["block_name", "week_start_date", "sets"].iter().map(|h| h.to_string()).collect();
+        let row = |block: &str, week: &str, sets: &str| vec![block.to_string(), week.to_string(), sets.to_string()];
+
+        let rows = [row("Block 1", "2025-05-26", "10"), row("Block 1", "2025-05-19", "12"), row("Block 2", "2025-06-02", "9")];
+        replace_weeks(&path, &headers, &rows, &[], false).unwrap();
+        let cleared = [("Block 2".to_string(), NaiveDate::from_ymd_opt(2025, 6, 2).unwrap())];
+        replace_weeks(&path, &headers, &[row("Block 1", "2025-05-26", "14")], &cleared, false).unwrap();
+
+        assert_eq!(read(&path).1, vec![row("Block 1", "2025-05-19", "12"), row("Block 1", "2025-05-26", "14")]);
+    }
+
+    #[test]
+    fn switching_schema_rebuilds_the_file() {
+        let (_dir, path) = temp_csv();
+
+        upsert(&path, &records(), CsvSchema::Long, false, false).unwrap();
+        assert_eq!(file_schema(&path).unwrap(), Some(CsvSchema::Long));
+
+        let stats = upsert(&path, &records(), CsvSchema::Wide, false, false).unwrap();
+        assert_eq!(stats.pruned, 4);
+        let (headers, rows) = read(&path);
+        assert_eq!(headers, WideRecord::to_csv_headers());
+        assert_eq!(rows.len(), 2);
+        assert!(rows.iter().all(|row| !row[0].ends_with("_prescribed") && !row[0].ends_with("_actual")));
+        assert_eq!(file_schema(&path).unwrap(), Some(CsvSchema::Wide));
+    }
+}
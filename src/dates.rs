@@ -0,0 +1,267 @@
+use anyhow::Result;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// How to read an all-numeric date such as "5/6/2025"
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DateOrder {
+    /// Month first: "5/19/2025"
+    #[default]
+    Mdy,
+    /// Day first: "19/5/2025"
+    Dmy,
+}
+
+impl FromStr for DateOrder {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "mdy" | "m/d/y" | "month_first" => Ok(DateOrder::Mdy),
+            "dmy" | "d/m/y" | "day_first" => Ok(DateOrder::Dmy),
+            other => anyhow::bail!("Invalid date order '{}': expected \"mdy\" or \"dmy\"", other),
+        }
+    }
+}
+
+impl DateOrder {
+    fn describe(self) -> &'static str {
+        match self {
+            DateOrder::Mdy => "month/day/year",
+            DateOrder::Dmy => "day/month/year",
+        }
+    }
+
+    fn other(self) -> Self {
+        match self {
+            DateOrder::Mdy => DateOrder::Dmy,
+            DateOrder::Dmy => DateOrder::Mdy,
+        }
+    }
+}
+
+/// The pieces of a date-looking cell, before the day/month order is applied
+#[derive(Debug, Clone, Copy)]
+enum DateShape {
+    /// "5/19/2025", "19.5.25", "5/19"; `full_year` is false for two-digit years
+    Numeric { first: u32, second: u32, year: Option<i32>, full_year: bool },
+    /// "2025-05-19"
+    Iso { year: i32, month: u32, day: u32 },
+    /// "May 19, 2025", "19 May 2025", "Mon 19th May"
+    Named { month: u32, day: u32, year: Option<i32> },
+}
+
+const MONTHS: [&str; 12] = [
+    "january", "february", "march", "april", "may", "june",
+    "july", "august", "september", "october", "november", "december",
+];
+
+const WEEKDAYS: [&str; 7] = ["monday", "tuesday", "wednesday", "thursday", "friday", "saturday", "sunday"];
+
+/// Whether a header cell is a week start date: any supported format that includes a year.
+/// Numeric dates need a four-digit year, so set/rep cells like "10/10/10" aren't taken for one.
+pub fn is_date_header(cell: &str) -> bool {
+    match date_shape(cell) {
+        Some(DateShape::Numeric { full_year, .. }) => full_year,
+        Some(DateShape::Named { year, .. }) => year.is_some(),
+        Some(DateShape::Iso { .. }) => true,
+        None => false,
+    }
+}
+
+/// Parse a date cell that must include its year
+pub fn parse_date(cell: &str, order: DateOrder) -> Result<NaiveDate> {
+    parse(cell, order, None)
+}
+
+/// Parse a date cell, using `default_year` when the cell has none ("5/19", "May 19")
+pub fn parse_date_in_year(cell: &str, order: DateOrder, default_year: i32) -> Result<NaiveDate> {
+    parse(cell, order, Some(default_year))
+}
+
+fn parse(cell: &str, order: DateOrder, default_year: Option<i32>) -> Result<NaiveDate> {
+    let Some(shape) = date_shape(cell) else {
+        anyhow::bail!("not a recognized date format");
+    };
+
+    let (year, month, day) = match shape {
+        DateShape::Iso { year, month, day } => (Some(year), month, day),
+        DateShape::Named { month, day, year } => (year, month, day),
+        DateShape::Numeric { first, second, year, .. } => {
+            let (month, day) = apply_order(first, second, order);
+            (year, month, day)
+        }
+    };
+
+    let Some(year) = year.or(default_year) else {
+        anyhow::bail!("date has no year");
+    };
+
+    if let Some(date) = NaiveDate::from_ymd_opt(year, month, day) {
+        return Ok(date);
+    }
+
+    // Point at the config when the other order would have worked
+    if let DateShape::Numeric { first, second, .. } = shape {
+        let (month, day) = apply_order(first, second, order.other());
+        if NaiveDate::from_ymd_opt(year, month, day).is_some() {
+            anyhow::bail!(
+                "not a valid {} date (set dates.order = \"{}\" for {} dates)",
+                order.describe(),
+                if order == DateOrder::Mdy { "dmy" } else { "mdy" },
+                order.other().describe()
+            );
+        }
+    }
+
+    anyhow::bail!("not a valid calendar date")
+}
+
+fn apply_order(first: u32, second: u32, order: DateOrder) -> (u32, u32) {
+    match order {
+        DateOrder::Mdy => (first, second),
+        DateOrder::Dmy => (second, first),
+    }
+}
+
+fn date_shape(cell: &str) -> Option<DateShape> {
+    let tokens: Vec<String> = cell
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|token| !token.is_empty())
+        .map(|token| token.to_ascii_lowercase())
+        .collect();
+
+    if tokens.is_empty() || tokens.len() > 4 {
+        return None;
+    }
+
+    if tokens.iter().all(|token| token.chars().all(|c| c.is_ascii_digit())) {
+        return numeric_shape(&tokens);
+    }
+
+    named_shape(&tokens)
+}
+
+fn numeric_shape(tokens: &[String]) -> Option<DateShape> {
+    let numbers: Vec<u32> = tokens.iter().map(|token| token.parse().ok()).collect::<Option<_>>()?;
+
+    match (tokens, numbers.as_slice()) {
+        ([y, _, _], &[year, month, day]) if y.len() == 4 => Some(DateShape::Iso { year: year as i32, month, day }),
+        ([a, b, y], &[first, second, year]) if a.len() <= 2 && b.len() <= 2 => {
+            Some(DateShape::Numeric { first, second, year: Some(expand_year(y, year)?), full_year: y.len() == 4 })
+        }
+        ([a, b], &[first, second]) if a.len() <= 2 && b.len() <= 2 => {
+            Some(DateShape::Numeric { first, second, year: None, full_year: false })
+        }
+        _ => None,
+    }
+}
+
+fn named_shape(tokens: &[String]) -> Option<DateShape> {
+    let mut month = None;
+    let mut day = None;
+    let mut year = None;
+
+    for token in tokens {
+        if token.chars().all(|c| c.is_ascii_alphabetic()) {
+            if let Some(index) = month_index(token) {
+                if month.replace(index + 1).is_some() {
+                    return None;
+                }
+            } else if !is_weekday(token) {
+                return None;
+            }
+            continue;
+        }
+
+        // Numbers, allowing an ordinal suffix: "19th", "1st"
+        let digits = token.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+        let suffix = &token[digits.len()..];
+        if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit())
+            || !matches!(suffix, "" | "st" | "nd" | "rd" | "th") {
+            return None;
+        }
+
+        let value: u32 = digits.parse().ok()?;
+        if digits.len() == 4 && suffix.is_empty() && year.is_none() {
+            year = Some(value as i32);
+        } else if digits.len() <= 2 && day.is_none() {
+            day = Some(value);
+        } else {
+            return None;
+        }
+    }
+
+    Some(DateShape::Named { month: month?, day: day?, year })
+}
+
+/// "May", "Sept", "september" -> index into MONTHS
+fn month_index(token: &str) -> Option<u32> {
+    if token.len() < 3 {
+        return None;
+    }
+    MONTHS.iter().position(|name| name.starts_with(token)).map(|index| index as u32)
+}
+
+fn is_weekday(token: &str) -> bool {
+    token.len() >= 3 && WEEKDAYS.iter().any(|name| name.starts_with(token))
+}
+
+/// Two-digit years are taken as 20xx
+fn expand_year(token: &str, year: u32) -> Option<i32> {
+    match token.len() {
+        2 => Some(2000 + year as i32),
+        4 => Some(year as i32),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn parses_supported_formats() {
+        assert_eq!(parse_date("5/19/2025", DateOrder::Mdy).unwrap(), date(2025, 5, 19));
+        assert_eq!(parse_date("19.5.25", DateOrder::Dmy).unwrap(), date(2025, 5, 19));
+        assert_eq!(parse_date("2025-05-19", DateOrder::Dmy).unwrap(), date(2025, 5, 19));
+        assert_eq!(parse_date("May 19, 2025", DateOrder::Mdy).unwrap(), date(2025, 5, 19));
+        assert_eq!(parse_date("Mon 19th May 2025", DateOrder::Mdy).unwrap(), date(2025, 5, 19));
+    }
+
+    #[test]
+    fn fills_in_a_missing_year() {
+        assert!(parse_date("5/19", DateOrder::Mdy).is_err());
+        assert_eq!(parse_date_in_year("5/19", DateOrder::Mdy, 2025).unwrap(), date(2025, 5, 19));
+        assert_eq!(parse_date_in_year("Sept 2", DateOrder::Mdy, 2025).unwrap(), date(2025, 9, 2));
+    }
+
+    #[test]
+    fn points_at_the_other_order() {
+        let error = parse_date("19/5/2025", DateOrder::Mdy).unwrap_err().to_string();
+        assert!(error.contains("dmy"), "{}", error);
+        assert!(parse_date("2/30/2025", DateOrder::Mdy).is_err());
+    }
+
+    #[test]
+    fn detects_week_headers() {
+        assert!(is_date_header("5/19/2025"));
+        assert!(is_date_header("2025-05-19"));
+        assert!(is_date_header("May 19, 2025"));
+        assert!(!is_date_header("May 19"));
+        assert!(!is_date_header("Week 3"));
+    }
+
+    #[test]
+    fn ignores_two_digit_years_in_headers() {
+        assert!(!is_date_header("10/10/10"));
+        assert!(!is_date_header("5/19/25"));
+        assert_eq!(parse_date("10/10/10", DateOrder::Mdy).unwrap(), date(2010, 10, 10));
+    }
+}
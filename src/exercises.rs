@@ -0,0 +1,233 @@
+use anyhow::Result;
+use config::{Config, File};
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use tracing::{debug, info, warn};
+use crate::hashing::stable_hash;
+use crate::taxonomy::{classify, Taxonomy, TaxonomyOverride};
+
+/// One entry of the alias file, keyed by canonical exercise ID
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ExerciseEntry {
+    // Display name; defaults to the first alias, or the ID
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    // Names used in the sheet for this exercise. Modifiers and cues are ignored when matching.
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    // Overrides of the built-in movement pattern, muscle groups, equipment and lift category
+    #[serde(flatten)]
+    pub taxonomy: TaxonomyOverride,
+}
+
+/// An exercise cell split into its canonical exercise, modifiers and coach cue
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CanonicalExercise {
+    pub id: String,
+    pub name: String,
+    /// "w/belt", "paused", "1s pause", "T&G", ...
+    pub modifiers: Vec<String>,
+    /// Coach instruction after a `*`: "drop 10lb each set"
+    pub cue: Option<String>,
+    pub taxonomy: Taxonomy,
+}
+
+/// Maps the exercise names used in the sheet to canonical exercise IDs
+#[derive(Debug, Clone, Default)]
+pub struct ExerciseCatalog {
+    entries: HashMap<String, ExerciseEntry>,
+    // Match key of every alias, name and ID -> exercise ID
+    lookup: HashMap<String, String>,
+    // Hash of the alias file's contents; empty when there is none
+    fingerprint: String,
+}
+
+impl ExerciseCatalog {
+    /// Load the alias file (TOML or YAML, by extension). No path means no aliases.
+    pub fn load(path: Option<&str>) -> Result<Self> {
+        let Some(path) = path else {
+            return Ok(Self::default());
+        };
+        if !std::path::Path::new(path).exists() {
+            anyhow::bail!("Exercise alias file not found: {}", path);
+        }
+
+        let entries: HashMap<String, ExerciseEntry> = Config::builder()
+            .add_source(File::with_name(path))
+            .build()?
+            .try_deserialize()
+            .map_err(|e| anyhow::anyhow!("Invalid exercise alias file {}: {}", path, e))?;
+
+        info!("Loaded {} exercises from {}", entries.len(), path);
+        let fingerprint = stable_hash(&[std::fs::read_to_string(path)?]);
+        Ok(Self { fingerprint, ..Self::from_entries(entries) })
+    }
+
+    fn from_entries(entries: HashMap<String, ExerciseEntry>) -> Self {
+        let mut lookup = HashMap::new();
+        for (id, entry) in &entries {
+            let names = std::iter::once(id.as_str())
+                .chain(entry.name.as_deref())
+                .chain(entry.aliases.iter().map(String::as_str));
+            for name in names {
+                let key = match_key(&split_name(name).0);
+                if let Some(previous) = lookup.insert(key.clone(), id.clone()) {
+                    if previous != *id {
+                        warn!("Exercise alias '{}' is listed under both '{}' and '{}'", name, previous, id);
+                    }
+                }
+            }
+        }
+        Self { entries, lookup, fingerprint: String::new() }
+    }
+
+    /// Hash of the alias file, so blocks can be re-normalized when the aliases change
+    pub fn fingerprint(&self) -> &str {
+        &self.fingerprint
+    }
+
+    /// Canonicalize an exercise cell. Names not in the alias file get an ID derived from
+    /// the name itself, so spacing, case and plural differences still line up.
+    pub fn canonicalize(&self, cell: &str) -> CanonicalExercise {
+        let (base, modifiers, cue) = split_name(cell);
+        let key = match_key(&base);
+
+        let (id, name, taxonomy) = match self.lookup.get(&key) {
+            Some(id) => {
+                let entry = &self.entries[id];
+                let name = entry.name.clone()
+                    .or_else(|| entry.aliases.first().map(|alias| split_name(alias).0))
+                    .unwrap_or_else(|| id.clone());
+                let taxonomy = classify(&name).with_override(&entry.taxonomy);
+                (id.clone(), name, taxonomy)
+            }
+            None => {
+                debug!("Exercise '{}' has no alias entry, using '{}'", base, key);
+                let taxonomy = classify(&base);
+                (key, base, taxonomy)
+            }
+        };
+
+        CanonicalExercise { id, name, modifiers, cue, taxonomy }
+    }
+}
+
+/// Builds the recorded modifier text from a pattern match
+type ModifierLabel = fn(&Captures) -> String;
+
+/// Modifier patterns and the normalized text recorded for each match
+fn modifier_patterns() -> &'static [(Regex, ModifierLabel)] {
+    static PATTERNS: OnceLock<Vec<(Regex, ModifierLabel)>> = OnceLock::new();
+    PATTERNS.get_or_init(|| {
+        let patterns: [(&str, ModifierLabel); 9] = [
+            // "1s pause", "2 sec pause", "3-count pause"; the duration is kept
+            (r"(?i)\b(\d+)\s*(?:s|sec|secs|second|seconds|ct|count)?\s*-?\s*paused?\b", |c| format!("{}s pause", &c[1])),
+            (r"(?i)\bpaused?\b", |_| "paused".to_string()),
+            (r"(?i)\bt\s*&\s*g\b|\btouch\s*(?:and|&|n)\s*go\b", |_| "T&G".to_string()),
+            (r"(?i)\b(?:beltless|no\s+belt)\b", |_| "beltless".to_string()),
+            // "w/belt", "w/ sleeves", "with belt"; keeps the equipment word
+            (r"(?i)(?:\bw/\s*|\bwith\s+)(belt|sleeves|wraps|straps|chains|bands?)\b", |c| format!("w/{}", c[1].to_lowercase())),
+            (r"(?i)\btempo(?:\s*\d(?:-?\d){2,3})?\b", |_| "tempo".to_string()),
+            (r"(?i)\bdeficit\b", |_| "deficit".to_string()),
+            (r"(?i)\bdead\s*stop\b", |_| "dead stop".to_string()),
+            (r"(?i)\bpin\b", |_| "pin".to_string()),
+        ];
+        patterns
+            .into_iter()
+            .map(|(pattern, label)| (Regex::new(pattern).expect("valid modifier regex"), label))
+            .collect()
+    })
+}
+
+/// Split an exercise cell into the bare name, its modifiers and the cue after `*`
+fn split_name(cell: &str) -> (String, Vec<String>, Option<String>) {
+    let (name, cue) = match cell.split_once('*') {
+        Some((name, cue)) => {
+            let cue = cue.replace('*', " ").split_whitespace().collect::<Vec<_>>().join(" ");
+            (name, (!cue.is_empty()).then_some(cue))
+        }
+        None => (cell, None),
+    };
+
+    let mut modifiers = Vec::new();
+    let mut name = name.to_string();
+    for (pattern, label) in modifier_patterns() {
+        for captures in pattern.captures_iter(&name) {
+            let modifier = label(&captures);
+            if !modifiers.contains(&modifier) {
+                modifiers.push(modifier);
+            }
+        }
+        name = pattern.replace_all(&name, " ").into_owned();
+    }
+
+    // Drop brackets and separators the modifiers left behind: "Squat (paused)" -> "Squat"
+    let name = name
+        .replace(['(', ')', '[', ']'], " ")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .trim_matches(|c: char| c == '-' || c == ',' || c == '+' || c.is_whitespace())
+        .to_string();
+
+    (name, modifiers, cue)
+}
+
+/// Case-, spacing- and plural-insensitive key: "Low Bar Squats" and "lowbar squat" both
+/// become "lowbarsquat"
+fn match_key(name: &str) -> String {
+    name.split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let word = word.to_ascii_lowercase();
+            match word.strip_suffix('s') {
+                Some(stem) if word.len() > 3 && !stem.ends_with('s') => stem.to_string(),
+                _ => word,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_modifiers_and_cues() {
+        let (name, modifiers, cue) = split_name("Low Bar Squat (1s pause) w/belt *drop 10lb each set");
+        assert_eq!(name, "Low Bar Squat");
+        assert_eq!(modifiers, vec!["1s pause", "w/belt"]);
+        assert_eq!(cue.as_deref(), Some("drop 10lb each set"));
+
+        let (name, modifiers, cue) = split_name("Bench T&G");
+        assert_eq!((name.as_str(), modifiers, cue), ("Bench", vec!["T&G".to_string()], None));
+    }
+
+    #[test]
+    fn match_keys_ignore_case_spacing_and_plurals() {
+        assert_eq!(match_key("Low Bar Squats"), "lowbarsquat");
+        assert_eq!(match_key("lowbar squat"), "lowbarsquat");
+        assert_eq!(match_key("Press"), "press");
+        assert_eq!(match_key("Abs"), "abs");
+    }
+
+    #[test]
+    fn canonicalizes_through_aliases() {
+        let entry = ExerciseEntry {
+            name: Some("Competition Squat".to_string()),
+            aliases: vec!["Comp Squat".to_string(), "SBD Squat".to_string()],
+            ..ExerciseEntry::default()
+        };
+        let catalog = ExerciseCatalog::from_entries(HashMap::from([("squat".to_string(), entry)]));
+
+        let exercise = catalog.canonicalize("comp squats paused");
+        assert_eq!(exercise.id, "squat");
+        assert_eq!(exercise.name, "Competition Squat");
+        assert_eq!(exercise.modifiers, vec!["paused"]);
+
+        let unknown = catalog.canonicalize("Leg Press");
+        assert_eq!((unknown.id.as_str(), unknown.name.as_str()), ("legpress", "Leg Press"));
+    }
+}
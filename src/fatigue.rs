@@ -0,0 +1,273 @@
+use chrono::{Duration, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use crate::strength::round_to;
+use crate::transform::WorkoutRecord;
+
+/// RPE and set counts of one training day
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SessionLoad {
+    pub sets: u32,
+    pub rated_sets: u32,
+    pub rpe_total: f64,
+}
+
+impl SessionLoad {
+    pub fn add(&mut self, other: &SessionLoad) {
+        self.sets += other.sets;
+        self.rated_sets += other.rated_sets;
+        self.rpe_total += other.rpe_total;
+    }
+
+    /// Session RPE load: the day's average RPE x its sets. The sheet has no session
+    /// durations, so sets stand in for the minutes of the usual sRPE x duration.
+    /// Days with no RPE recorded count as zero.
+    pub fn load(&self) -> f64 {
+        if self.rated_sets == 0 {
+            return 0.0;
+        }
+        self.rpe_total / self.rated_sets as f64 * self.sets as f64
+    }
+}
+
+/// A block week's sessions by workout date
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WeekSessions {
+    pub week_number: u32,
+    pub sessions: BTreeMap<NaiveDate, SessionLoad>,
+}
+
+/// Sessions of every block week, keyed by block name then week start date
+pub type SessionHistory = BTreeMap<String, BTreeMap<NaiveDate, WeekSessions>>;
+
+/// Fold the records' sessions into the stored weeks. With `whole_weeks` (block mode) a
+/// re-normalized week replaces what was stored for it; otherwise (legacy raw_range mode, where
+/// each record is seen once) its sessions are added to the stored week.
+pub fn record_sessions(history: &mut SessionHistory, records: &[WorkoutRecord], whole_weeks: bool) {
+    let mut weeks: HashMap<(&str, NaiveDate), WeekSessions> = HashMap::new();
+
+    for record in records {
+        let week = weeks.entry((&record.block_name, record.week_start_date)).or_insert_with(|| WeekSessions {
+            week_number: record.week_number,
+            ..WeekSessions::default()
+        });
+        if record.record_type != "actual" || (record.reps.is_none() && record.load.is_none()) {
+            continue;
+        }
+
+        let sets = record.set_count().unwrap_or(1);
+        let session = week.sessions.entry(record.workout_date).or_default();
+        session.sets += sets;
+        if let Some(rpe) = record.rpe.as_ref().filter(|r| !r.per_set.is_empty()) {
+            // One RPE covers every set of the record; a list rates one set per value
+            match rpe.per_set.as_slice() {
+                [single] => {
+                    session.rpe_total += single * sets as f64;
+                    session.rated_sets += sets;
+                }
+                values => {
+                    session.rpe_total += values.iter().sum::<f64>();
+                    session.rated_sets += values.len() as u32;
+                }
+            }
+        }
+    }
+
+    for ((block_name, week_start), week) in weeks {
+        let stored_weeks = history.entry(block_name.to_string()).or_default();
+        match stored_weeks.get_mut(&week_start) {
+            Some(stored) if !whole_weeks => {
+                for (date, session) in week.sessions {
+                    stored.sessions.entry(date).or_default().add(&session);
+                }
+            }
+            _ => {
+                stored_weeks.insert(week_start, week);
+            }
+        }
+    }
+}
+
+/// Settings for the weekly metrics
+#[derive(Debug, Clone, Copy)]
+pub struct FatigueOptions {
+    /// Weeks averaged for the chronic load
+    pub chronic_weeks: u32,
+    /// Acute:chronic ratio above which a week is flagged
+    pub acwr_spike: f64,
+}
+
+/// Load, ratio, monotony and strain of one training week
+#[derive(Debug, Clone)]
+pub struct WeekFatigue {
+    pub week_start_date: NaiveDate,
+    pub block_name: String,
+    pub week_number: u32,
+    pub sessions: u32,
+    /// Sum of the week's session loads (the acute load)
+    pub weekly_load: f64,
+    /// Average weekly load of the `chronic_weeks` before this one; None until that much
+    /// history exists
+    pub chronic_load: Option<f64>,
+    pub acwr: Option<f64>,
+    /// Mean daily load over the week's 7 days divided by its standard deviation
+    pub monotony: Option<f64>,
+    /// Weekly load x monotony
+    pub strain: Option<f64>,
+    pub acwr_spike: bool,
+}
+
+impl WeekFatigue {
+    pub fn to_csv_headers() -> Vec<String> {
+        [
+            "week_start_date", "block_name", "week_number", "sessions", "weekly_load", "chronic_load",
+            "acwr", "monotony", "strain", "acwr_spike",
+        ]
+        .into_iter()
+        .map(str::to_string)
+        .collect()
+    }
+
+    pub fn to_csv_row(&self) -> Vec<String> {
+        let optional = |value: Option<f64>| value.map(|v| v.to_string()).unwrap_or_default();
+        vec![
+            self.week_start_date.to_string(),
+            self.block_name.clone(),
+            self.week_number.to_string(),
+            self.sessions.to_string(),
+            self.weekly_load.to_string(),
+            optional(self.chronic_load),
+            optional(self.acwr),
+            optional(self.monotony),
+            optional(self.strain),
+            self.acwr_spike.to_string(),
+        ]
+    }
+}
+
+/// Weekly metrics over every stored block week, in date order.
+///
+/// Sessions are placed by workout date, so blocks form one continuous timeline; calendar
+/// weeks with no sessions between recorded weeks count as zero load in the chronic average.
+pub fn weekly_fatigue(history: &SessionHistory, options: FatigueOptions) -> Vec<WeekFatigue> {
+    let mut daily: BTreeMap<NaiveDate, SessionLoad> = BTreeMap::new();
+    let mut weeks: BTreeMap<NaiveDate, (&str, u32)> = BTreeMap::new();
+    for (block_name, block_weeks) in history {
+        for (week_start, week) in block_weeks {
+            weeks.entry(*week_start).or_insert((block_name, week.week_number));
+            for (date, session) in &week.sessions {
+                daily.entry(*date).or_default().add(session);
+            }
+        }
+    }
+    let Some(&first_week) = weeks.keys().next() else {
+        return Vec::new();
+    };
+
+    // The session of each of the 7 days from `week_start`, None on rest days
+    let week_days = |week_start: NaiveDate| -> Vec<Option<&SessionLoad>> {
+        (0..7).map(|offset| daily.get(&(week_start + Duration::days(offset)))).collect()
+    };
+    let day_loads = |week_start: NaiveDate| -> Vec<f64> {
+        week_days(week_start).into_iter().map(|day| day.map_or(0.0, SessionLoad::load)).collect()
+    };
+
+    weeks
+        .iter()
+        .map(|(&week_start, &(block_name, week_number))| {
+            let days = day_loads(week_start);
+            let weekly_load: f64 = days.iter().sum();
+
+            let chronic_start = week_start - Duration::weeks(options.chronic_weeks as i64);
+            let chronic_load = (options.chronic_weeks > 0 && chronic_start >= first_week).then(|| {
+                let total: f64 = (1..=options.chronic_weeks as i64)
+                    .map(|back| day_loads(week_start - Duration::weeks(back)).iter().sum::<f64>())
+                    .sum();
+                total / options.chronic_weeks as f64
+            });
+            let acwr = chronic_load.filter(|&chronic| chronic > 0.0).map(|chronic| round_to(weekly_load / chronic, 0.01));
+
+            let mean = weekly_load / 7.0;
+            let deviation = (days.iter().map(|load| (load - mean).powi(2)).sum::<f64>() / 7.0).sqrt();
+            let monotony = (deviation > 0.0).then(|| mean / deviation);
+
+            WeekFatigue {
+                week_start_date: week_start,
+                block_name: block_name.to_string(),
+                week_number,
+                sessions: week_days(week_start).into_iter().flatten().filter(|session| session.sets > 0).count() as u32,
+                weekly_load: round_to(weekly_load, 0.01),
+                chronic_load: chronic_load.map(|load| round_to(load, 0.01)),
+                acwr,
+                monotony: monotony.map(|monotony| round_to(monotony, 0.01)),
+                strain: monotony.map(|monotony| round_to(weekly_load * monotony, 0.01)),
+                acwr_spike: acwr.is_some_and(|acwr| acwr > options.acwr_spike),
+            }
+        })
+        .collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sheets::SheetRow;
+    use crate::transform::{normalize_block_data, ParseOptions};
+
+    fn row(row_number: usize, cells: &[&str]) -> SheetRow {
+        SheetRow { row_number, cells: cells.iter().map(|cell| cell.to_string()).collect() }
+    }
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 5, day).unwrap()
+    }
+
+    /// A week with one session of `sets` sets, all at `rpe`
+    fn week(week_number: u32, session: NaiveDate, sets: u32, rpe: f64) -> WeekSessions {
+        let load = SessionLoad { sets, rated_sets: sets, rpe_total: rpe * sets as f64 };
+        WeekSessions { week_number, sessions: BTreeMap::from([(session, load)]) }
+    }
+
+    #[test]
+    fn sessions_average_rpe_over_sets() {
+        let rows = vec![
+            row(1, &["", "", "5/19/2025"]),
+            row(2, &["", "", "Week 1"]),
+            row(6, &["", "DAY 1"]),
+            row(7, &["", "Squat", "", "3", "5", "300", "8", "", "300", "3", "5", "8"]),
+            row(8, &["", "Bench", "", "3", "8", "200", "6", "", "200", "3", "8", "6"]),
+        ];
+        let records = normalize_block_data(rows, "Block 1", &ParseOptions::default()).unwrap().records;
+
+        let mut history = SessionHistory::new();
+        record_sessions(&mut history, &records, true);
+        record_sessions(&mut history, &records, true);
+        let session = &history["Block 1"][&date(19)].sessions[&date(19)];
+        assert_eq!((session.sets, session.rated_sets), (6, 6));
+        assert_eq!(session.load(), 42.0);
+
+        record_sessions(&mut history, &records, false);
+        assert_eq!(history["Block 1"][&date(19)].sessions[&date(19)].sets, 12);
+    }
+
+    #[test]
+    fn flags_a_load_spike_against_the_chronic_weeks() {
+        let history = SessionHistory::from([(
+            "Block 1".to_string(),
+            BTreeMap::from([
+                (date(5), week(1, date(5), 2, 5.0)),
+                (date(12), week(2, date(12), 2, 5.0)),
+                (date(19), week(3, date(19), 5, 8.0)),
+            ]),
+        )]);
+        let weeks = weekly_fatigue(&history, FatigueOptions { chronic_weeks: 2, acwr_spike: 1.5 });
+
+        assert_eq!(weeks.len(), 3);
+        assert!(weeks[..2].iter().all(|week| week.chronic_load.is_none() && !week.acwr_spike));
+        let spike = &weeks[2];
+        assert_eq!((spike.weekly_load, spike.chronic_load, spike.acwr), (40.0, Some(10.0), Some(4.0)));
+        assert!(spike.acwr_spike);
+        // One session in seven days: mean 40/7 over a standard deviation of about 14
+        assert_eq!((spike.monotony, spike.strain), (Some(0.41), Some(16.33)));
+    }
+}
@@ -0,0 +1,46 @@
+use sha2::{Digest, Sha256};
+use crate::sheets::SheetRow;
+
+/// Number of hex characters kept from the SHA-256 digest
+const SHORT_HASH_LEN: usize = 16;
+
+/// Hash a sequence of string parts into a short, stable hex digest.
+///
+/// Parts are length-prefixed so `["ab", "c"]` and `["a", "bc"]` hash differently.
+/// Unlike `std::hash`, the output is identical across runs, machines and Rust versions.
+pub fn stable_hash<S: AsRef<str>>(parts: &[S]) -> String {
+    let mut hasher = Sha256::new();
+    for part in parts {
+        let bytes = part.as_ref().as_bytes();
+        hasher.update((bytes.len() as u64).to_le_bytes());
+        hasher.update(bytes);
+    }
+    
+    hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<String>()[..SHORT_HASH_LEN]
+        .to_string()
+}
+
+/// Hash sheet rows, including their absolute row numbers. Trailing empty cells are
+/// ignored, since the Sheets API omits them inconsistently between requests.
+pub fn hash_rows(rows: &[SheetRow]) -> String {
+    let mut parts = Vec::new();
+    for row in rows {
+        let used = row.cells.iter().rposition(|cell| !cell.trim().is_empty()).map_or(0, |i| i + 1);
+        parts.push(format!("{}:{}", row.row_number, used));
+        parts.extend(row.cells[..used].iter().map(|cell| cell.trim().to_string()));
+    }
+    stable_hash(&parts)
+}
+
+/// Lowercase a label and strip everything except ASCII letters and digits (e.g. "Block 24" -> "block24")
+pub fn slug(label: &str) -> String {
+    label
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
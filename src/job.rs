@@ -1,33 +1,59 @@
 use anyhow::Result;
+use chrono::NaiveDate;
 use google_sheets4::{Sheets, hyper_rustls, hyper};
+use std::sync::Arc;
 use tracing::{info, warn};
 use crate::{
-    cfg::Cfg,
-    state::{load_state, save_state},
-    sheets::{fetch_rows, discover_block_tabs, detect_block_extent},
-    transform::normalize_block_data,
-    csv_sink::append,
+    cfg::{Cfg, CsvWriteMode},
+    exercises::ExerciseCatalog,
+    state::{load_state, save_state, backup_state},
+    sheets::{fetch_rows_with_header, discover_block_tabs, detect_block_extent, cell_address, write_cells, BlockInfo, SheetRow},
+    transform::{normalize_block_data, week_content_hashes, NormalizedBlock, ParseOptions, WorkoutRecord},
+    hashing::{hash_rows, stable_hash},
+    csv_sink::{append, append_rows, file_schema, replace_rows, replace_weeks, upsert, get_row_count},
+    compliance::{pair_records, record_pairs, summarize_weeks, CompliancePair, WeekCompliance},
+    fatigue::{record_sessions, weekly_fatigue, WeekFatigue},
+    maxes::{resolve_loads, MaxTable},
+    progression::{prune_upcoming, record_estimates, record_upcoming, suggest, Suggestion},
+    prs::{detect_prs, PersonalRecord},
+    volume::{record_volume, summarize, write_report},
 };
 
+/// A range to read, with the block tab it belongs to (None in legacy raw_range mode)
+struct RangeTarget {
+    range: String,
+    block: Option<BlockInfo>,
+}
+
+/// A block tab fetched and parsed in full, before deciding which of its weeks changed
+struct ParsedBlock<'a> {
+    block: &'a BlockInfo,
+    range: &'a str,
+    name: &'a str,
+    raw_rows: Vec<SheetRow>,
+    options: ParseOptions,
+    normalized: NormalizedBlock,
+}
+
 pub async fn run_job(
-    cfg: Cfg,
-    hub: Sheets<hyper_rustls::HttpsConnector<hyper::client::HttpConnector>>,
+    cfg: &Cfg,
+    hub: &Sheets<hyper_rustls::HttpsConnector<hyper::client::HttpConnector>>,
 ) -> Result<()> {
     info!("Starting job execution");
     
-    // Validate configuration
-    cfg.validate()?;
+    // Load the exercise alias dictionary once for every block
+    let exercises = Arc::new(ExerciseCatalog::load(cfg.exercises.aliases_path.as_deref())?);
     
     // Load state
     let mut state = load_state(&cfg.state_path)?;
     
-    // Get all ranges to process - either from legacy config or auto-discovery
-    let ranges = if let Some(legacy_ranges) = cfg.get_legacy_block_ranges() {
-        info!("Using configured ranges");
-        legacy_ranges
+    // Get all ranges to process - either the legacy single range or discovered block tabs
+    let targets = if let Some(ref raw_range) = cfg.raw_range {
+        info!("Using configured range");
+        vec![RangeTarget { range: raw_range.clone(), block: None }]
     } else {
         info!("Auto-discovering block tabs from spreadsheet");
-        let discovered_blocks = discover_block_tabs(&hub, &cfg.sheet_id).await?;
+        let discovered_blocks = discover_block_tabs(hub, &cfg.sheet_id).await?;
         
         if discovered_blocks.is_empty() {
             anyhow::bail!("No block tabs found in the spreadsheet. Expected sheets with names like 'Block 1', 'Block 2', etc.");
@@ -35,110 +61,341 @@ pub async fn run_job(
         
         info!("Discovered {} block tabs", discovered_blocks.len());
         
-        // For each discovered block, detect its optimal range dynamically
-        let mut optimized_ranges = Vec::new();
-        for block in discovered_blocks.iter() {
-            match detect_block_extent(&hub, &cfg.sheet_id, &block.name).await {
+        // Older state files are keyed by range string; move them onto sheetIds before use
+        let migrated = state.migrate_range_keys(&discovered_blocks);
+        if migrated > 0 {
+            backup_state(&cfg.state_path)?;
+            info!("Migrated {} block state entries from range keys to sheetIds", migrated);
+        }
+        
+        let selected_blocks: Vec<BlockInfo> = discovered_blocks
+            .into_iter()
+            .filter(|block| cfg.includes_block(block.block_number))
+            .collect();
+        
+        if let Some(ref specific_blocks) = cfg.specific_blocks {
+            for block_number in specific_blocks {
+                if !selected_blocks.iter().any(|b| b.block_number == *block_number) {
+                    warn!("Configured block {} was not found in the spreadsheet", block_number);
+                }
+            }
+        }
+        
+        // For each selected block, detect its optimal range dynamically
+        let mut block_targets = Vec::new();
+        for block in selected_blocks {
+            let range = match detect_block_extent(hub, &cfg.sheet_id, &block.name).await {
                 Ok(optimized_range) => {
                     info!("Block {}: Using optimized range {}", block.name, optimized_range);
-                    optimized_ranges.push(optimized_range);
+                    optimized_range
                 }
                 Err(e) => {
                     warn!("Failed to detect extent for {}, using fallback template: {}", block.name, e);
                     let fallback_range = cfg.block_range_template.replace("{}", &block.block_number.to_string());
                     info!("Block {}: Using fallback range {}", block.name, fallback_range);
-                    optimized_ranges.push(fallback_range);
+                    fallback_range
                 }
-            }
+            };
+            block_targets.push(RangeTarget { range, block: Some(block) });
         }
-        optimized_ranges
+        block_targets
     };
     
-    info!("Processing {} range(s)", ranges.len());
+    info!("Processing {} range(s)", targets.len());
+    
+    // Block mode re-emits every changed week in full; legacy mode emits only rows added since
+    // the last run, so per-week state has to be added to rather than replaced
+    let whole_weeks = cfg.raw_range.is_none();
+    
+    // A CSV written in the other schema is rebuilt from re-emitted blocks, which legacy mode can't do
+    if !whole_weeks {
+        if let Some(schema) = file_schema(&cfg.output_csv.path)?.filter(|&schema| schema != cfg.output_csv.schema) {
+            anyhow::bail!("{} was written in the {:?} schema but output_csv.schema is {:?}; move it aside or point output_csv.path at a new file",
+                          cfg.output_csv.path, schema, cfg.output_csv.schema);
+        }
+    }
     
     let mut total_new_rows = 0;
     let mut all_normalized_rows = Vec::new();
+    let mut parsed_blocks = Vec::new();
+    let mut emptied_weeks: Vec<(String, NaiveDate)> = Vec::new();
     
     // Process each range (block or single legacy range)
-    for (range_index, range) in ranges.iter().enumerate() {
-        info!("Processing range {}/{}: {}", range_index + 1, ranges.len(), range);
-        
-        // Get the starting row for this specific range/block
-        let start_row = if ranges.len() == 1 {
-            // Legacy mode: use global state
-            state.last_processed_row + 1
-        } else {
-            // Block mode: use per-block state
-            state.get_next_row_for_block(range)
-        };
+    for (range_index, target) in targets.iter().enumerate() {
+        let range = &target.range;
+        info!("Processing range {}/{}: {}", range_index + 1, targets.len(), range);
         
-        info!("Starting from row {} for range: {}", start_row, range);
+        // Extract block name from range (e.g., "Block 1!A2:Z" -> "Block 1")
+        let block_name = range.split('!').next().unwrap_or(range);
+        
+        let Some(ref block) = target.block else {
+            // Legacy mode: fetch the header band plus the data rows below the last row seen,
+            // so the parser always sees the week headers
+            info!("Fetching rows after sheet row {} for range: {}", state.last_row_seen, range);
+            
+            let fetched = fetch_rows_with_header(hub, &cfg.sheet_id, range, state.last_row_seen).await?;
+            let Some(last_data_row) = fetched.last_data_row() else {
+                info!("No new rows found in range: {}", range);
+                continue;
+            };
+            let new_row_count = fetched.data_rows.len();
+            
+            info!("Found {} new rows in range: {} (through sheet row {})", new_row_count, range, last_data_row);
+            let options = cfg.parse_options_for(block_name, &exercises);
+            let Some(normalized) = normalize_or_warn(fetched.into_rows(), block_name, &options) else {
+                continue;
+            };
+            log_warnings(&normalized);
+            all_normalized_rows.extend(normalized.records);
+            total_new_rows += new_row_count;
+            state.update_processed(last_data_row, new_row_count);
+            
+            info!("Completed processing range: {} ({} rows)", range, new_row_count);
+            continue;
+        };
         
-        // Fetch rows from this specific range
-        let raw_rows = fetch_rows(&hub, &cfg.sheet_id, range, start_row).await?;
+        // Block mode: athletes edit earlier weeks in place, so always fetch and parse the whole
+        // block. Which weeks are emitted is decided by content hashes once the maxes are known.
+        let raw_rows = fetch_rows_with_header(hub, &cfg.sheet_id, range, 0).await?.into_rows();
         
         if raw_rows.is_empty() {
-            info!("No new rows found in range: {}", range);
+            info!("No rows found in range: {}", range);
             continue;
         }
         
-        info!("Found {} new rows in range: {}", raw_rows.len(), range);
-        
-        // Extract block name from range (e.g., "Block 1!A2:Z" -> "Block 1")
-        let block_name = range.split('!').next().unwrap_or(range);
+        // A block that fails to parse keeps its old hashes, so it is retried next run
+        let options = cfg.parse_options_for(block_name, &exercises);
+        let Some(normalized) = normalize_or_warn(raw_rows.clone(), block_name, &options) else {
+            continue;
+        };
+        parsed_blocks.push(ParsedBlock { block, range, name: block_name, raw_rows, options, normalized });
+    }
+    
+    // Maxes come from the config, the PR history and every parsed record, so this run's
+    // estimates are in place before the maxes are hashed below
+    let mut maxes = MaxTable::from_config(&cfg.maxes.lifts, &exercises, cfg.units.load);
+    if cfg.maxes.from_history {
+        maxes.add_history(&state.prs);
+        maxes.add_estimates(&all_normalized_rows);
+        for parsed in &parsed_blocks {
+            maxes.add_estimates(&parsed.normalized.records);
+        }
+    }
+    
+    for ParsedBlock { block, range, name: block_name, raw_rows, options, normalized } in parsed_blocks {
+        // Parse options, the CSV schema and the maxes a week's instructions use are part of the
+        // hashes, so config changes re-normalize the affected weeks too
+        let config_fingerprint = stable_hash(&[options.fingerprint(), format!("{:?}", cfg.output_csv.schema)]);
+        let maxes_fingerprint = |records: &[WorkoutRecord]| {
+            if cfg.maxes.enabled { maxes.fingerprint(records) } else { String::new() }
+        };
+        let block_hash = stable_hash(&[hash_rows(&raw_rows), config_fingerprint.clone(), maxes_fingerprint(&normalized.records)]);
+        if state.is_block_unchanged(block.tab_id, &block_hash) {
+            info!("Block {} unchanged since last run, skipping", block_name);
+            continue;
+        }
         
-        // Use the new block-aware processing
-        let range_normalized_rows = match normalize_block_data(raw_rows.clone(), block_name) {
-            Ok(records) => {
-                info!("Successfully parsed {} workout records from {}", records.len(), block_name);
-                records
-            }
+        let week_hashes: Vec<(String, String)> = match week_content_hashes(&raw_rows) {
+            Ok(hashes) => hashes
+                .into_iter()
+                .map(|(week, hash)| {
+                    let maxes = maxes_fingerprint(normalized.week_records(&week));
+                    let hash = stable_hash(&[hash, config_fingerprint.clone(), maxes]);
+                    (week, hash)
+                })
+                .collect(),
             Err(e) => {
-                warn!("Failed to parse block data for {}: {}", block_name, e);
-                // Fallback to empty vec
-                Vec::new()
+                warn!("Failed to hash weeks for {}: {}", block_name, e);
+                continue;
             }
         };
+        let changed_weeks = state.changed_weeks(block.tab_id, &week_hashes);
+        info!("Block {} changed: {} of {} weeks need re-normalizing", 
+              block_name, changed_weeks.len(), week_hashes.len());
+        log_warnings(&normalized);
+        
+        // A changed week without records had its rows deleted, so its stored aggregates go too
+        let mut records = Vec::new();
+        for week in normalized.weeks.iter().filter(|week| changed_weeks.contains(&week.key)) {
+            let week_records = &normalized.records[week.records.clone()];
+            if week_records.is_empty() {
+                emptied_weeks.push((block_name.to_string(), week.start_date));
+            }
+            records.extend_from_slice(week_records);
+        }
         
-        let _range_processed_count = range_normalized_rows.len();
+        let last_row_seen = raw_rows.last().map_or(0, |row| row.row_number);
+        state.update_block_content(block, range, last_row_seen, records.len(), block_hash, week_hashes);
         
         // Add to global collection
-        all_normalized_rows.extend(range_normalized_rows);
+        all_normalized_rows.extend(records);
         total_new_rows += raw_rows.len();
         
-        // Update state for this range
-        if ranges.len() == 1 {
-            // Legacy mode: update global state
-            state.update_processed(raw_rows.len());
-        } else {
-            // Block mode: update per-block state
-            state.update_block_state(range, raw_rows.len());
-        }
-        
         info!("Completed processing range: {} ({} rows)", range, raw_rows.len());
     }
     
+    if !emptied_weeks.is_empty() {
+        info!("Clearing {} weeks that no longer have any rows", emptied_weeks.len());
+        state.clear_weeks(&emptied_weeks);
+    }
+    
+    // Work percentage and "base on max" instructions out against the maxes table
+    if cfg.maxes.enabled {
+        let increment = cfg.maxes.increment.unwrap_or(cfg.units.load.increment());
+        let resolved = resolve_loads(&mut all_normalized_rows, &maxes, increment);
+        if resolved > 0 {
+            info!("Resolved {} load instructions against the maxes table", resolved);
+        }
+        replace_rows(&cfg.maxes.path, &MaxTable::to_csv_headers(), &maxes.to_csv_rows(), cfg.output_csv.ensure)?;
+    }
+    
     // Write all normalized rows to CSV
     if !all_normalized_rows.is_empty() {
-        append(&cfg.output_csv.path, &all_normalized_rows, cfg.output_csv.ensure)?;
-        info!("Appended {} normalized rows to CSV from all ranges", all_normalized_rows.len());
+        match cfg.output_csv.mode {
+            CsvWriteMode::Append => {
+                append(&cfg.output_csv.path, &all_normalized_rows, cfg.output_csv.schema, cfg.output_csv.ensure)?;
+                info!("Appended {} normalized rows to CSV from all ranges", all_normalized_rows.len());
+            }
+            CsvWriteMode::Upsert => {
+                upsert(&cfg.output_csv.path, &all_normalized_rows, cfg.output_csv.schema, cfg.output_csv.ensure, cfg.output_csv.prune_missing)?;
+            }
+        }
+        info!("CSV {} now holds {} rows", cfg.output_csv.path, get_row_count(&cfg.output_csv.path)?);
+        
+        #[cfg(feature = "sqlite")]
+        if cfg.output_sqlite.enabled {
+            crate::sqlite_sink::upsert(&cfg.output_sqlite.path, &all_normalized_rows, cfg.output_csv.ensure, cfg.output_sqlite.prune_missing)?;
+        }
+        
+        #[cfg(feature = "parquet")]
+        if cfg.output_parquet.enabled {
+            crate::parquet_sink::write(&cfg.output_parquet.dir, &all_normalized_rows, whole_weeks, cfg.output_csv.ensure)?;
+        }
     } else {
         info!("No rows were successfully normalized from any range");
     }
     
+    // Check the new rows for PRs against the history kept in state
+    if cfg.prs.enabled {
+        let prs = detect_prs(&mut state.prs, &all_normalized_rows);
+        if !prs.is_empty() {
+            let rows: Vec<Vec<String>> = prs.iter().map(PersonalRecord::to_csv_row).collect();
+            append_rows(&cfg.prs.path, &PersonalRecord::to_csv_headers(), &rows, cfg.output_csv.ensure)?;
+            info!("Recorded {} new PRs in {}", prs.len(), cfg.prs.path);
+        }
+    }
+    
+    // Fold the re-normalized weeks into the stored volume and rewrite the reports
+    let weeks_changed = !all_normalized_rows.is_empty() || !emptied_weeks.is_empty();
+    if cfg.reports.enabled && weeks_changed {
+        record_volume(&mut state.volume, &all_normalized_rows, whole_weeks);
+        write_report(&summarize(&state.volume), &cfg.reports.dir, cfg.output_csv.ensure)?;
+    }
+    
+    // Pair the re-normalized weeks' actuals with their prescriptions, and re-check every stored
+    // week against today so pending sessions turn into missed ones once their date passes
+    if cfg.compliance.enabled {
+        let today = chrono::Local::now().date_naive();
+        record_pairs(&mut state.compliance, pair_records(&all_normalized_rows, today), whole_weeks, today);
+        let pairs: Vec<CompliancePair> = state.compliance.values().flat_map(|weeks| weeks.values().flatten()).cloned().collect();
+        if !pairs.is_empty() || !emptied_weeks.is_empty() {
+            let rows: Vec<Vec<String>> = pairs.iter().map(CompliancePair::to_csv_row).collect();
+            replace_weeks(&cfg.compliance.path, &CompliancePair::to_csv_headers(), &rows, &emptied_weeks, cfg.output_csv.ensure)?;
+            let rows: Vec<Vec<String>> = summarize_weeks(&pairs).iter().map(WeekCompliance::to_csv_row).collect();
+            replace_weeks(&cfg.compliance.weekly_path, &WeekCompliance::to_csv_headers(), &rows, &emptied_weeks, cfg.output_csv.ensure)?;
+        }
+    }
+    
+    // Recompute the weekly fatigue metrics and warn when the latest week's load spikes
+    if cfg.fatigue.enabled && weeks_changed {
+        record_sessions(&mut state.sessions, &all_normalized_rows, whole_weeks);
+        let weeks = weekly_fatigue(&state.sessions, cfg.fatigue.options());
+        let rows: Vec<Vec<String>> = weeks.iter().map(WeekFatigue::to_csv_row).collect();
+        replace_rows(&cfg.fatigue.path, &WeekFatigue::to_csv_headers(), &rows, cfg.output_csv.ensure)?;
+        if let Some(week) = weeks.last().filter(|week| week.acwr_spike) {
+            warn!("Acute:chronic workload ratio spiked to {} in {} week {} (starting {})",
+                  week.acwr.unwrap_or_default(), week.block_name, week.week_number, week.week_start_date);
+        }
+    }
+    
+    // Suggest loads for the coming sessions from recent e1RMs, optionally filling them in
+    if cfg.progression.enabled {
+        let options = cfg.progression.options(cfg.units.load);
+        let today = chrono::Local::now().date_naive();
+        record_estimates(&mut state.e1rms, &all_normalized_rows, options.lookback_days);
+        record_upcoming(&mut state.upcoming, &all_normalized_rows, whole_weeks);
+        prune_upcoming(&mut state.upcoming, today);
+        
+        let suggestions = suggest(&state.upcoming, &state.e1rms, today, &cfg.units.load.to_string(), options);
+        let rows: Vec<Vec<String>> = suggestions.iter().map(Suggestion::to_csv_row).collect();
+        replace_rows(&cfg.progression.path, &Suggestion::to_csv_headers(), &rows, cfg.output_csv.ensure)?;
+        info!("Suggested loads for {} upcoming prescriptions", suggestions.len());
+        
+        if cfg.progression.write_back && !suggestions.is_empty() {
+            write_back_suggestions(hub, cfg, &targets, &suggestions).await?;
+        }
+    }
+    
     // Save updated state
     save_state(&cfg.state_path, &state)?;
     
     // Log completion
     info!("Job completed successfully. Processed {} total rows across {} ranges. Total ever processed: {}", 
-          total_new_rows, ranges.len(), state.total_processed);
+          total_new_rows, targets.len(), state.total_processed);
     
     Ok(())
 }
 
+/// Write each suggested load into its prescription's load cell in the sheet
+async fn write_back_suggestions(
+    hub: &Sheets<hyper_rustls::HttpsConnector<hyper::client::HttpConnector>>,
+    cfg: &Cfg,
+    targets: &[RangeTarget],
+    suggestions: &[Suggestion],
+) -> Result<()> {
+    let mut cells = Vec::new();
+    for suggestion in suggestions {
+        let target = targets.iter().find(|t| t.block.is_some() && t.range.split('!').next() == Some(suggestion.block_name.as_str()));
+        match (target, suggestion.load_column) {
+            (Some(target), Some(column)) if suggestion.sheet_row > 0 => {
+                cells.push((cell_address(&target.range, column, suggestion.sheet_row)?, suggestion.load.to_string()));
+            }
+            // Without a dedicated load column the only candidate is the instruction cell, which
+            // holds the coach's text and is never overwritten
+            _ => warn!("No load column to write the suggestion for {} on {} to, skipping", suggestion.exercise, suggestion.workout_date),
+        }
+    }
+    
+    write_cells(hub, &cfg.sheet_id, &cells).await?;
+    Ok(())
+}
+
+/// Normalize a block. Returns None if its structure can't be parsed.
+fn normalize_or_warn(raw_rows: Vec<SheetRow>, block_name: &str, options: &ParseOptions) -> Option<NormalizedBlock> {
+    match normalize_block_data(raw_rows, block_name, options) {
+        Ok(normalized) => {
+            info!("Successfully parsed {} workout records from {} ({} warnings)",
+                  normalized.records.len(), block_name, normalized.warnings.len());
+            Some(normalized)
+        }
+        Err(e) => {
+            warn!("Failed to parse block data for {}: {}", block_name, e);
+            None
+        }
+    }
+}
+
+/// Log the cells of a block that couldn't be interpreted
+fn log_warnings(normalized: &NormalizedBlock) {
+    for warning in &normalized.warnings {
+        warn!(block = %warning.block_name, row = warning.row_number, field = warning.field,
+              value = %warning.value, "{}", warning.message);
+    }
+}
+
 pub async fn run_with_error_handling(
-    cfg: Cfg,
-    hub: Sheets<hyper_rustls::HttpsConnector<hyper::client::HttpConnector>>,
+    cfg: &Cfg,
+    hub: &Sheets<hyper_rustls::HttpsConnector<hyper::client::HttpConnector>>,
 ) -> Result<()> {
     match run_job(cfg, hub).await {
         Ok(()) => {
@@ -0,0 +1,290 @@
+use std::collections::{HashMap, HashSet};
+use tracing::debug;
+use crate::cfg::{FieldLabels, LayoutProfile};
+use crate::sheets::SheetRow;
+
+/// A `WorkoutRecord` field that is read from its own sheet column
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RecordField {
+    Sets,
+    Reps,
+    Load,
+    LoadInstruction,
+    Rpe,
+    Notes,
+    DatePerformed,
+}
+
+/// Absolute column index of each field within one week's group
+#[derive(Debug, Clone, Default)]
+pub struct ColumnMap {
+    columns: HashMap<RecordField, usize>,
+}
+
+impl ColumnMap {
+    fn from_offsets(start_col: usize, offsets: &[(RecordField, usize)]) -> Self {
+        Self {
+            columns: offsets.iter().map(|&(field, offset)| (field, start_col + offset)).collect(),
+        }
+    }
+
+    pub fn get(&self, field: RecordField) -> Option<usize> {
+        self.columns.get(&field).copied()
+    }
+
+    fn contains(&self, field: RecordField) -> bool {
+        self.columns.contains_key(&field)
+    }
+
+    fn insert(&mut self, field: RecordField, col: usize) {
+        self.columns.insert(field, col);
+    }
+
+    fn is_empty(&self) -> bool {
+        self.columns.is_empty()
+    }
+
+    /// The trimmed cell for `field` in `row`, if the field is mapped and the cell isn't blank
+    pub fn cell<'a>(&self, row: &'a [String], field: RecordField) -> Option<&'a str> {
+        self.get(field)
+            .and_then(|col| row.get(col))
+            .map(|cell| cell.trim())
+            .filter(|cell| !cell.is_empty())
+    }
+}
+
+/// Columns of the prescribed and actual groups of one week
+#[derive(Debug, Clone, Default)]
+pub struct WeekColumns {
+    pub prescribed: ColumnMap,
+    pub actual: ColumnMap,
+}
+
+/// Fixed offsets from the week's date column, used when no header labels are found
+const DEFAULT_PRESCRIBED_OFFSETS: &[(RecordField, usize)] = &[
+    (RecordField::Sets, 1),
+    (RecordField::Reps, 2),
+    (RecordField::LoadInstruction, 3),
+    (RecordField::Rpe, 4),
+];
+const DEFAULT_ACTUAL_OFFSETS: &[(RecordField, usize)] = &[
+    (RecordField::Load, 6),
+    (RecordField::Sets, 7),
+    (RecordField::Reps, 8),
+    (RecordField::Rpe, 9),
+    (RecordField::Notes, 10),
+];
+
+/// Locate each field's column in a week by its header label.
+///
+/// The header band row with the most label matches inside the week is used. Its labels are
+/// split into the prescribed group and the actual group that follows it: the actual group
+/// starts at the first label from which every remaining label fits a distinct actual field
+/// (see `actual_group_start`). A group with no labels found falls back to the fixed default
+/// offsets.
+pub fn locate_week_columns(
+    header_rows: &[SheetRow],
+    start_col: usize,
+    end_col: usize,
+    profile: &LayoutProfile,
+) -> WeekColumns {
+    let prescribed_labels = labels(&profile.prescribed);
+    let actual_labels = labels(&profile.actual);
+
+    // The week's cells that match a label, in column order
+    let header_cells = |row: &SheetRow| -> Vec<HeaderCell> {
+        (start_col..=end_col)
+            .filter_map(|col| {
+                let label = normalize_label(row.cells.get(col)?);
+                let prescribed = find_field(&prescribed_labels, &label);
+                let actual = find_field(&actual_labels, &label);
+                (prescribed.is_some() || actual.is_some()).then_some(HeaderCell { col, label, prescribed, actual })
+            })
+            .collect()
+    };
+
+    let best_row = header_rows
+        .iter()
+        .map(|row| (header_cells(row), row))
+        .filter(|(cells, _)| !cells.is_empty())
+        .max_by_key(|(cells, row)| (cells.len(), std::cmp::Reverse(row.row_number)));
+
+    let mut columns = WeekColumns::default();
+
+    if let Some((cells, row)) = best_row {
+        let split = actual_group_start(&cells);
+        for (index, cell) in cells.iter().enumerate() {
+            let (group, field) = if index < split {
+                (&mut columns.prescribed, cell.prescribed)
+            } else {
+                (&mut columns.actual, cell.actual)
+            };
+            if let Some(field) = field.filter(|field| !group.contains(*field)) {
+                group.insert(field, cell.col);
+            }
+        }
+
+        debug!("Located columns from header row {}: prescribed {:?}, actual {:?}",
+               row.row_number, columns.prescribed, columns.actual);
+    }
+
+    if columns.prescribed.is_empty() {
+        debug!("No prescribed header labels found for week at column {}, using default offsets", start_col);
+        columns.prescribed = ColumnMap::from_offsets(start_col, DEFAULT_PRESCRIBED_OFFSETS);
+    }
+    if columns.actual.is_empty() {
+        debug!("No actual header labels found for week at column {}, using default offsets", start_col);
+        columns.actual = ColumnMap::from_offsets(start_col, DEFAULT_ACTUAL_OFFSETS);
+    }
+
+    columns
+}
+
+/// A header cell matching a label, with the field it fills in each group
+struct HeaderCell {
+    col: usize,
+    label: String,
+    prescribed: Option<RecordField>,
+    actual: Option<RecordField>,
+}
+
+/// Index of the first cell of the actual group.
+///
+/// An actual group exists once a label repeats an earlier one or only fits the actual group,
+/// and it starts no later than that label. It starts at the earliest cell from which every
+/// remaining label fits a distinct actual field while every earlier label fits a distinct
+/// prescribed field, so "Sets Reps RPE | Load Sets Reps RPE" puts the first "Load" in the
+/// actual group even though it would also fit the prescribed load instruction. Without a
+/// repeated or actual-only label every cell is prescribed.
+fn actual_group_start(cells: &[HeaderCell]) -> usize {
+    let anchor = cells
+        .iter()
+        .enumerate()
+        .position(|(index, cell)| cell.prescribed.is_none() || cells[..index].iter().any(|earlier| earlier.label == cell.label));
+    let Some(anchor) = anchor else {
+        return cells.len();
+    };
+
+    (0..=anchor)
+        .find(|&split| {
+            distinct(cells[..split].iter().map(|cell| cell.prescribed)) && distinct(cells[split..].iter().map(|cell| cell.actual))
+        })
+        .unwrap_or(anchor)
+}
+
+/// True when every field is present and none repeats
+fn distinct(mut fields: impl Iterator<Item = Option<RecordField>>) -> bool {
+    let mut seen = HashSet::new();
+    fields.all(|field| field.is_some_and(|field| seen.insert(field)))
+}
+
+fn labels(field_labels: &FieldLabels) -> Vec<(RecordField, String)> {
+    [
+        (RecordField::Sets, &field_labels.sets),
+        (RecordField::Reps, &field_labels.reps),
+        (RecordField::Load, &field_labels.load),
+        (RecordField::LoadInstruction, &field_labels.load_instruction),
+        (RecordField::Rpe, &field_labels.rpe),
+        (RecordField::Notes, &field_labels.notes),
+        (RecordField::DatePerformed, &field_labels.date_performed),
+    ]
+    .into_iter()
+    .filter_map(|(field, label)| {
+        label.as_deref().map(normalize_label).filter(|l| !l.is_empty()).map(|l| (field, l))
+    })
+    .collect()
+}
+
+fn find_field(labels: &[(RecordField, String)], normalized_cell: &str) -> Option<RecordField> {
+    if normalized_cell.is_empty() {
+        return None;
+    }
+    labels.iter().find(|(_, label)| label == normalized_cell).map(|(field, _)| *field)
+}
+
+/// Lowercase, drop punctuation and collapse whitespace ("Load /" -> "load", "Notes:" -> "notes")
+fn normalize_label(label: &str) -> String {
+    label
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c.to_ascii_lowercase() } else { ' ' })
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cfg::LayoutConfig;
+
+    fn header(labels: &[&str]) -> Vec<SheetRow> {
+        // The week's date sits in column C, its labels start in column D
+        let mut cells = vec![String::new(); 3];
+        cells.extend(labels.iter().map(|label| label.to_string()));
+        vec![SheetRow { row_number: 3, cells }]
+    }
+
+    fn default_profile() -> LayoutProfile {
+        LayoutConfig::default().profile_for("Block 1")
+    }
+
+    fn columns(labels: &[&str], profile: &LayoutProfile) -> WeekColumns {
+        locate_week_columns(&header(labels), 2, 2 + labels.len(), profile)
+    }
+
+    #[test]
+    fn splits_the_default_labels_into_groups() {
+        let week = columns(&["Sets", "Reps", "Load", "RPE", "", "Load", "Sets", "Reps", "RPE", "Notes"], &default_profile());
+        assert_eq!(week.prescribed.get(RecordField::Sets), Some(3));
+        assert_eq!(week.prescribed.get(RecordField::LoadInstruction), Some(5));
+        assert_eq!(week.prescribed.get(RecordField::Rpe), Some(6));
+        assert_eq!(week.actual.get(RecordField::Load), Some(8));
+        assert_eq!(week.actual.get(RecordField::Sets), Some(9));
+        assert_eq!(week.actual.get(RecordField::Notes), Some(12));
+    }
+
+    #[test]
+    fn actual_load_starts_the_actual_group_without_a_prescribed_load() {
+        let week = columns(&["Sets", "Reps", "RPE", "Load", "Sets", "Reps", "RPE", "Notes"], &default_profile());
+        assert_eq!(week.prescribed.get(RecordField::LoadInstruction), None);
+        assert_eq!(week.prescribed.get(RecordField::Rpe), Some(5));
+        assert_eq!(week.actual.get(RecordField::Load), Some(6));
+        assert_eq!(week.actual.get(RecordField::Sets), Some(7));
+    }
+
+    #[test]
+    fn actual_group_without_a_load() {
+        let week = columns(&["Sets", "Reps", "Load", "RPE", "Sets", "Reps", "RPE"], &default_profile());
+        assert_eq!(week.prescribed.get(RecordField::Rpe), Some(6));
+        assert_eq!(week.actual.get(RecordField::Load), None);
+        assert_eq!(week.actual.get(RecordField::Sets), Some(7));
+    }
+
+    #[test]
+    fn prescribed_labels_alone_leave_the_actual_group_at_its_offsets() {
+        let week = columns(&["Sets", "Reps", "Load", "RPE"], &default_profile());
+        assert_eq!(week.prescribed.get(RecordField::LoadInstruction), Some(5));
+        assert_eq!(week.actual.get(RecordField::Load), Some(2 + 6));
+    }
+
+    #[test]
+    fn uses_custom_labels() {
+        let mut layout = LayoutConfig::default();
+        layout.prescribed.load_instruction = Some("Intensity".to_string());
+        layout.actual.load = Some("Weight".to_string());
+        layout.actual.date_performed = Some("Date".to_string());
+        let week = columns(&["Sets", "Reps", "Intensity", "RPE", "Date", "Weight", "Sets", "Reps", "RPE"], &layout.profile_for("Block 1"));
+        assert_eq!(week.prescribed.get(RecordField::LoadInstruction), Some(5));
+        assert_eq!(week.actual.get(RecordField::DatePerformed), Some(7));
+        assert_eq!(week.actual.get(RecordField::Load), Some(8));
+        assert_eq!(week.actual.get(RecordField::Rpe), Some(11));
+    }
+
+    #[test]
+    fn falls_back_to_offsets_without_labels() {
+        let week = columns(&["", "", ""], &default_profile());
+        assert_eq!(week.prescribed.get(RecordField::Sets), Some(3));
+        assert_eq!(week.actual.get(RecordField::Notes), Some(12));
+    }
+}
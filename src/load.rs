@@ -0,0 +1,372 @@
+use anyhow::Result;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::OnceLock;
+use crate::strength::round_to;
+
+const LB_PER_KG: f64 = 2.204_622_621_8;
+
+/// Unit of a load
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LoadUnit {
+    #[default]
+    Lb,
+    Kg,
+}
+
+impl FromStr for LoadUnit {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "lb" | "lbs" | "pound" | "pounds" | "#" => Ok(LoadUnit::Lb),
+            "kg" | "kgs" | "kilo" | "kilos" | "kilogram" | "kilograms" => Ok(LoadUnit::Kg),
+            other => anyhow::bail!("Invalid load unit '{}': expected \"lb\" or \"kg\"", other),
+        }
+    }
+}
+
+impl fmt::Display for LoadUnit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            LoadUnit::Lb => "lb",
+            LoadUnit::Kg => "kg",
+        })
+    }
+}
+
+impl LoadUnit {
+    /// Convert `value` in this unit to `to`, rounded to 0.01
+    pub fn convert(self, value: f64, to: LoadUnit) -> f64 {
+        let converted = match (self, to) {
+            (LoadUnit::Lb, LoadUnit::Kg) => value / LB_PER_KG,
+            (LoadUnit::Kg, LoadUnit::Lb) => value * LB_PER_KG,
+            _ => return value,
+        };
+        round_to(converted, 0.01)
+    }
+    /// Smallest usual jump between loads: 5 lb or 2.5 kg
+    pub fn increment(self) -> f64 {
+        match self {
+            LoadUnit::Lb => 5.0,
+            LoadUnit::Kg => 2.5,
+        }
+    }
+}
+
+/// A load cell, e.g. "305 lbs", "140kg", "BW+25", "2x50", "305/315/325"
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Load {
+    /// Weight of the heaviest set; for bodyweight loads, the weight added (negative if assisted)
+    pub value: f64,
+    pub unit: LoadUnit,
+    /// `value` is per implement or per side: "2x50" dumbbells, "50/side"
+    pub per_side: bool,
+    /// `value` is relative to bodyweight: "BW", "BW+25"
+    pub bodyweight_relative: bool,
+    /// One weight per set when the cell lists several ("305/315/325"), otherwise empty
+    pub per_set: Vec<f64>,
+    /// The cell as written
+    pub raw: String,
+}
+
+impl Load {
+    /// This load expressed in `unit`
+    pub fn in_unit(&self, unit: LoadUnit) -> Load {
+        Load {
+            value: self.unit.convert(self.value, unit),
+            unit,
+            per_set: self.per_set.iter().map(|&w| self.unit.convert(w, unit)).collect(),
+            ..self.clone()
+        }
+    }
+
+    /// The load of the `index`th set (0-based) of a per-set list; `raw` keeps the whole cell
+    pub fn for_set(&self, index: usize) -> Load {
+        Load {
+            value: self.per_set.get(index).copied().unwrap_or(self.value),
+            per_set: Vec::new(),
+            ..self.clone()
+        }
+    }
+
+    /// `per_set` joined with "/", for the CSV
+    pub fn per_set_label(&self) -> String {
+        self.per_set.iter().map(|w| w.to_string()).collect::<Vec<_>>().join("/")
+    }
+}
+
+/// Parse a load cell. Cells without a unit are taken to be in `default_unit`.
+pub fn parse_load(cell: &str, default_unit: LoadUnit) -> Result<Load> {
+    static UNIT: OnceLock<Regex> = OnceLock::new();
+    static PER_SIDE: OnceLock<Regex> = OnceLock::new();
+    static BODYWEIGHT: OnceLock<Regex> = OnceLock::new();
+    static IMPLEMENTS: OnceLock<Regex> = OnceLock::new();
+
+    let unit_re = UNIT.get_or_init(|| {
+        Regex::new(r"(?i)(kilograms?|kilos?|kgs?|pounds?|lbs?|#)").expect("valid unit regex")
+    });
+    let per_side_re = PER_SIDE.get_or_init(|| {
+        Regex::new(r"(?i)(?:/\s*|\bper\s+|\ba\s+)(?:side|hand|arm)\b|\b(?:each|ea)\b").expect("valid per-side regex")
+    });
+    let bodyweight_re = BODYWEIGHT.get_or_init(|| {
+        Regex::new(r"(?i)^(?:bw|body\s*weight)\s*(?:([+-])\s*(\d+(?:\.\d+)?))?$").expect("valid bodyweight regex")
+    });
+    let implements_re = IMPLEMENTS.get_or_init(|| {
+        Regex::new(r"(?i)^(\d+)\s*[x×*]\s*(\d+(?:\.\d+)?)$").expect("valid implements regex")
+    });
+
+    let raw = cell.trim().to_string();
+
+    // Pull out the unit, which may follow any weight in the cell: "140kg", "305/315 lbs"
+    let mut unit = None;
+    for found in unit_re.find_iter(&raw) {
+        let found: LoadUnit = found.as_str().parse()?;
+        if unit.is_some_and(|unit| unit != found) {
+            anyhow::bail!("mixes kg and lb");
+        }
+        unit = Some(found);
+    }
+    let without_unit = unit_re.replace_all(&raw, " ");
+
+    let mut per_side = per_side_re.is_match(&without_unit);
+    let text = per_side_re.replace_all(&without_unit, " ");
+    let text = text.trim();
+
+    let mut load = Load {
+        value: 0.0,
+        unit: unit.unwrap_or(default_unit),
+        per_side,
+        bodyweight_relative: false,
+        per_set: Vec::new(),
+        raw: raw.clone(),
+    };
+
+    if let Some(captures) = bodyweight_re.captures(text) {
+        let added = captures.get(2).map_or(Ok(0.0), |m| m.as_str().parse::<f64>())?;
+        load.bodyweight_relative = true;
+        load.value = if captures.get(1).is_some_and(|sign| sign.as_str() == "-") { -added } else { added };
+        return Ok(load);
+    }
+
+    // "2x50": two dumbbells (or kettlebells) of 50 each. Anything else shaped like that is
+    // a set written as weight x reps ("315x3"), which isn't a load on its own.
+    if let Some(captures) = implements_re.captures(text) {
+        let count: u32 = captures[1].parse()?;
+        let value: f64 = captures[2].parse()?;
+        if !(1..=4).contains(&count) || value < count as f64 {
+            anyhow::bail!("looks like weight x reps, not a load");
+        }
+        per_side |= count > 1;
+        load.per_side = per_side;
+        load.value = value;
+        return Ok(load);
+    }
+
+    let weights: Vec<f64> = text
+        .split(['/', ',', ';'])
+        .map(|part| part.trim().parse::<f64>().ok().filter(|weight| weight.is_finite() && *weight >= 0.0))
+        .collect::<Option<_>>()
+        .ok_or_else(|| anyhow::anyhow!("not a recognized load"))?;
+
+    load.value = weights.iter().copied().fold(f64::MIN, f64::max);
+    if weights.len() > 1 {
+        load.per_set = weights;
+    }
+    Ok(load)
+}
+
+/// What a prescribed load cell asks for when it isn't a weight
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum LoadInstruction {
+    /// Work up to a weight on the day: "find", "work up", "build"
+    Find,
+    /// Derive the weight from the lift's max, percentage not given: "base on max"
+    BaseOnMax,
+    /// Percentage of the lift's max: "75%", "70-75%"
+    Percent { low: f64, high: f64 },
+    /// Percentage of the day's top set of the same lift: "80% of top single";
+    /// `reps` is the top set's rep count when named (single = 1, double = 2, triple = 3)
+    TopSetPercent { low: f64, high: f64, reps: Option<u32> },
+    /// Anything else; only the raw text is kept
+    Other,
+}
+
+impl LoadInstruction {
+    /// Short name for the CSV `load_instruction_kind` column
+    pub fn kind(&self) -> &'static str {
+        match self {
+            LoadInstruction::Find => "find",
+            LoadInstruction::BaseOnMax => "base_on_max",
+            LoadInstruction::Percent { .. } => "percent",
+            LoadInstruction::TopSetPercent { .. } => "top_set_percent",
+            LoadInstruction::Other => "other",
+        }
+    }
+
+    /// The low and high percentage of percentage instructions
+    pub fn percent_range(&self) -> Option<(f64, f64)> {
+        match *self {
+            LoadInstruction::Percent { low, high } | LoadInstruction::TopSetPercent { low, high, .. } => Some((low, high)),
+            _ => None,
+        }
+    }
+
+    /// "75" or "70-75" for percentage instructions
+    pub fn percent_label(&self) -> Option<String> {
+        match self.percent_range()? {
+            (low, high) if low == high => Some(low.to_string()),
+            (low, high) => Some(format!("{}-{}", low, high)),
+        }
+    }
+}
+
+/// Classify a load instruction cell such as "find", "base on max", "70-75%" or
+/// "80% of top single"
+pub fn parse_load_instruction(cell: &str) -> LoadInstruction {
+    static PERCENT: OnceLock<Regex> = OnceLock::new();
+    static FIND: OnceLock<Regex> = OnceLock::new();
+    static TOP_SET: OnceLock<Regex> = OnceLock::new();
+
+    let percent_re = PERCENT.get_or_init(|| {
+        Regex::new(r"(\d+(?:\.\d+)?)\s*%?\s*(?:-|–|to)\s*(\d+(?:\.\d+)?)\s*%|(\d+(?:\.\d+)?)\s*%").expect("valid percent regex")
+    });
+    let top_set_re = TOP_SET.get_or_init(|| {
+        Regex::new(r"(?i)\bof\s+(?:the\s+|today'?s\s+|(?:the\s+)?day'?s\s+)?(?:top|heavy)\s+(single|double|triple|set)\b")
+            .expect("valid top set regex")
+    });
+    let find_re = FIND.get_or_init(|| {
+        Regex::new(r"(?i)\b(?:find|work(?:ing)?\s+up|build(?:\s+up)?|heavy\s+single)\b").expect("valid find regex")
+    });
+
+    if let Some(captures) = percent_re.captures(cell) {
+        let number = |i: usize| captures.get(i).and_then(|m| m.as_str().parse::<f64>().ok());
+        let range = match (number(1), number(2), number(3)) {
+            (Some(low), Some(high), _) => Some((low.min(high), low.max(high))),
+            (_, _, Some(percent)) => Some((percent, percent)),
+            _ => None,
+        };
+        if let Some((low, high)) = range {
+            return match top_set_re.captures(cell) {
+                Some(top) => {
+                    let reps = match top[1].to_ascii_lowercase().as_str() {
+                        "single" => Some(1),
+                        "double" => Some(2),
+                        "triple" => Some(3),
+                        _ => None,
+                    };
+                    LoadInstruction::TopSetPercent { low, high, reps }
+                }
+                None => LoadInstruction::Percent { low, high },
+            };
+        }
+    }
+
+    // "find a heavy single, not a max" asks to work up, so "find" wins over "max"
+    if find_re.is_match(cell) {
+        LoadInstruction::Find
+    } else if cell.to_lowercase().contains("max") {
+        LoadInstruction::BaseOnMax
+    } else {
+        LoadInstruction::Other
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load(cell: &str) -> Load {
+        parse_load(cell, LoadUnit::Lb).unwrap()
+    }
+
+    #[test]
+    fn parses_plain_weights_and_units() {
+        assert_eq!(load("305").value, 305.0);
+        assert_eq!(load("305 lbs").unit, LoadUnit::Lb);
+        let kilos = load("140kg");
+        assert_eq!((kilos.value, kilos.unit), (140.0, LoadUnit::Kg));
+        assert_eq!(parse_load("100", LoadUnit::Kg).unwrap().unit, LoadUnit::Kg);
+        assert!(parse_load("100kg/220lb", LoadUnit::Lb).is_err());
+    }
+
+    #[test]
+    fn parses_per_set_lists() {
+        let sets = load("305/315/325");
+        assert_eq!(sets.value, 325.0);
+        assert_eq!(sets.per_set, vec![305.0, 315.0, 325.0]);
+        assert_eq!(load("5,5,4").per_set, vec![5.0, 5.0, 4.0]);
+        assert_eq!(sets.for_set(1).value, 315.0);
+    }
+
+    #[test]
+    fn parses_bodyweight_loads() {
+        let plain = load("BW");
+        assert!(plain.bodyweight_relative);
+        assert_eq!(plain.value, 0.0);
+        assert_eq!(load("BW+25").value, 25.0);
+        assert_eq!(load("BW-20").value, -20.0);
+        assert_eq!(load("bodyweight - 20").value, -20.0);
+    }
+
+    #[test]
+    fn parses_implements_and_sides() {
+        let dumbbells = load("2x50");
+        assert!(dumbbells.per_side);
+        assert_eq!(dumbbells.value, 50.0);
+        assert!(!load("1x50").per_side);
+        assert!(load("50/side").per_side);
+        assert!(load("45 each").per_side);
+    }
+
+    #[test]
+    fn rejects_weight_by_reps() {
+        assert!(parse_load("315x3", LoadUnit::Lb).is_err());
+        assert!(parse_load("5x3", LoadUnit::Lb).is_err());
+    }
+
+    #[test]
+    fn rejects_non_finite_and_negative_weights() {
+        for cell in ["nan", "inf", "-inf", "-45", "300/nan", "", "heavy"] {
+            assert!(parse_load(cell, LoadUnit::Lb).is_err(), "{:?} should not parse", cell);
+        }
+    }
+
+    #[test]
+    fn converts_between_units() {
+        assert_eq!(LoadUnit::Kg.convert(100.0, LoadUnit::Lb), 220.46);
+        assert_eq!(LoadUnit::Lb.convert(220.46, LoadUnit::Kg), 100.0);
+        let converted = load("100/110 kg").in_unit(LoadUnit::Lb);
+        assert_eq!(converted.per_set, vec![220.46, 242.51]);
+    }
+
+    #[test]
+    fn classifies_load_instructions() {
+        assert_eq!(parse_load_instruction("find 5 RPE"), LoadInstruction::Find);
+        assert_eq!(parse_load_instruction("work up to a heavy single"), LoadInstruction::Find);
+        assert_eq!(parse_load_instruction("base on max"), LoadInstruction::BaseOnMax);
+        assert_eq!(parse_load_instruction("75%"), LoadInstruction::Percent { low: 75.0, high: 75.0 });
+        assert_eq!(parse_load_instruction("75-70%"), LoadInstruction::Percent { low: 70.0, high: 75.0 });
+        assert_eq!(
+            parse_load_instruction("80% of top single"),
+            LoadInstruction::TopSetPercent { low: 80.0, high: 80.0, reps: Some(1) }
+        );
+        assert_eq!(parse_load_instruction("coach's call"), LoadInstruction::Other);
+    }
+
+    #[test]
+    fn prefers_find_over_max() {
+        assert_eq!(parse_load_instruction("find a heavy single, not a max"), LoadInstruction::Find);
+        assert_eq!(parse_load_instruction("build to max"), LoadInstruction::Find);
+    }
+
+    #[test]
+    fn labels_percentages() {
+        assert_eq!(parse_load_instruction("70-75%").percent_label().as_deref(), Some("70-75"));
+        assert_eq!(parse_load_instruction("75%").percent_label().as_deref(), Some("75"));
+        assert_eq!(LoadInstruction::Find.percent_label(), None);
+    }
+}
@@ -1,16 +1,36 @@
 use anyhow::Result;
 use clap::Parser;
 use tracing::info;
-use tracing_subscriber;
 
 mod args;
 mod auth;
 mod cfg;
+mod compliance;
 mod csv_sink;
+mod dates;
+mod exercises;
+mod fatigue;
+mod hashing;
 mod job;
+mod layout;
+mod load;
+mod maxes;
+#[cfg(feature = "parquet")]
+mod parquet_sink;
+mod progression;
+mod prs;
+mod reps;
+mod rpe;
+mod scheduler;
 mod sheets;
+#[cfg(feature = "sqlite")]
+mod sqlite_sink;
 mod state;
+mod strength;
+mod taxonomy;
 mod transform;
+mod volume;
+mod wide;
 
 use args::Args;
 use cfg::Cfg;
@@ -26,17 +46,16 @@ async fn main() -> Result<()> {
     
     // Load configuration
     let cfg = Cfg::load(args)?;
+    cfg.validate()?;
     
     // Initialize authentication
     let hub = auth::create_sheets_hub().await?;
     
     if cfg.once {
         info!("Running once and exiting");
-        job::run_job(cfg, hub).await?;
+        job::run_job(&cfg, &hub).await?;
     } else {
-        // TODO: Implement scheduler logic for repeated runs
-        info!("Scheduler mode not yet implemented");
-        job::run_job(cfg, hub).await?;
+        scheduler::run_scheduler(&cfg, &hub).await?;
     }
     
     info!("sheet_watch completed successfully");
@@ -0,0 +1,275 @@
+use chrono::NaiveDate;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use crate::exercises::ExerciseCatalog;
+use crate::hashing::stable_hash;
+use crate::load::{LoadInstruction, LoadUnit};
+use crate::prs::ExerciseRecords;
+use crate::strength::{estimate, round_to, rpe_percentage};
+use crate::transform::WorkoutRecord;
+
+/// Where a max came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MaxSource {
+    /// Listed under `[maxes.lifts]`
+    Config,
+    /// The lift's best e1RM
+    History,
+}
+
+impl MaxSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MaxSource::Config => "config",
+            MaxSource::History => "history",
+        }
+    }
+}
+
+/// One lift's max
+#[derive(Debug, Clone)]
+pub struct Max {
+    pub exercise_id: String,
+    pub exercise: String,
+    pub value: f64,
+    pub source: MaxSource,
+    /// When a history max was set; None for configured ones
+    pub date: Option<NaiveDate>,
+}
+
+/// The max of each canonical exercise, in one unit. Configured maxes take precedence over
+/// ones derived from history.
+#[derive(Debug, Clone)]
+pub struct MaxTable {
+    unit: LoadUnit,
+    maxes: BTreeMap<String, Max>,
+}
+
+impl MaxTable {
+    /// The configured maxes, keyed by any name or alias of the exercise
+    pub fn from_config(lifts: &HashMap<String, f64>, exercises: &ExerciseCatalog, unit: LoadUnit) -> Self {
+        let maxes = lifts
+            .iter()
+            .map(|(name, &value)| {
+                let exercise = exercises.canonicalize(name);
+                let max = Max { exercise_id: exercise.id.clone(), exercise: exercise.name, value, source: MaxSource::Config, date: None };
+                (exercise.id, max)
+            })
+            .collect();
+        Self { unit, maxes }
+    }
+
+    /// Fill in the best e1RMs kept by PR tracking
+    pub fn add_history(&mut self, history: &HashMap<String, ExerciseRecords>) {
+        for (exercise_id, records) in history {
+            let Some(best) = &records.best_e1rm else {
+                continue;
+            };
+            // Loads are stored in the unit configured when they were recorded
+            let value = match records.unit.parse::<LoadUnit>() {
+                Ok(unit) => unit.convert(best.value, self.unit),
+                Err(_) => best.value,
+            };
+            self.add_estimate(exercise_id, &records.name, value, best.date);
+        }
+    }
+
+    /// Fill in estimates from this run's actual records, which PR tracking may not have seen yet
+    pub fn add_estimates(&mut self, records: &[WorkoutRecord]) {
+        for record in records {
+            if let Some(e1rm) = estimate(record) {
+                self.add_estimate(&record.exercise_id, &record.exercise_canonical, e1rm.preferred(), record.workout_date);
+            }
+        }
+    }
+
+    fn add_estimate(&mut self, exercise_id: &str, exercise: &str, value: f64, date: NaiveDate) {
+        let max = Max {
+            exercise_id: exercise_id.to_string(),
+            exercise: exercise.to_string(),
+            value: round_to(value, 0.01),
+            source: MaxSource::History,
+            date: Some(date),
+        };
+        match self.maxes.get(exercise_id) {
+            Some(current) if current.source == MaxSource::Config || current.value >= max.value => {}
+            _ => {
+                self.maxes.insert(exercise_id.to_string(), max);
+            }
+        }
+    }
+
+    /// Hash of the maxes the records' load instructions are resolved against, so a week is
+    /// re-normalized when one of its lifts' max changes, and only then
+    pub fn fingerprint(&self, records: &[WorkoutRecord]) -> String {
+        let lifts: BTreeSet<&str> = records
+            .iter()
+            .filter(|record| needs_max(record))
+            .map(|record| record.exercise_id.as_str())
+            .collect();
+        let mut parts = vec![self.unit.to_string()];
+        for lift in lifts {
+            parts.push(lift.to_string());
+            parts.push(self.get(lift).map(|max| max.to_string()).unwrap_or_default());
+        }
+        stable_hash(&parts)
+    }
+
+    pub fn get(&self, exercise_id: &str) -> Option<f64> {
+        self.maxes.get(exercise_id).map(|max| max.value)
+    }
+
+    pub fn to_csv_headers() -> Vec<String> {
+        ["exercise_id", "exercise", "max", "unit", "source", "date"]
+            .into_iter()
+            .map(str::to_string)
+            .collect()
+    }
+
+    /// One row per exercise, by exercise ID
+    pub fn to_csv_rows(&self) -> Vec<Vec<String>> {
+        self.maxes
+            .values()
+            .map(|max| {
+                vec![
+                    max.exercise_id.clone(),
+                    max.exercise.clone(),
+                    max.value.to_string(),
+                    self.unit.to_string(),
+                    max.source.as_str().to_string(),
+                    max.date.map(|date| date.to_string()).unwrap_or_default(),
+                ]
+            })
+            .collect()
+    }
+}
+
+/// Fill `resolved_load` on prescriptions whose load is an instruction, returning how many
+/// were resolved. Loads are rounded to a multiple of `increment`.
+///
+/// - "75%", "70-75%": the middle of the range, times the lift's max
+/// - "base on max": the RPE chart percentage for the prescribed reps and RPE, times the max
+/// - "80% of top single": the middle of the range, times the heaviest set of that lift on
+///   the same day (with the named rep count, if any); logged sets are preferred over
+///   prescribed ones
+pub fn resolve_loads(records: &mut [WorkoutRecord], maxes: &MaxTable, increment: f64) -> usize {
+    let mut resolved = 0;
+    for record in records.iter_mut().filter(|record| is_unresolved(record)) {
+        let percent = match record.load_instruction_kind {
+            Some(LoadInstruction::Percent { low, high }) => Some((low + high) / 2.0),
+            Some(LoadInstruction::BaseOnMax) => chart_percentage(record),
+            _ => None,
+        };
+        if let (Some(percent), Some(max)) = (percent, maxes.get(&record.exercise_id)) {
+            record.resolved_load = Some(round_to(max * percent / 100.0, increment));
+            resolved += 1;
+        }
+    }
+
+    // Top sets can themselves be resolved prescriptions, so these go last
+    let top_set_loads: Vec<(usize, f64)> = records
+        .iter()
+        .enumerate()
+        .filter(|(_, record)| is_unresolved(record))
+        .filter_map(|(index, record)| match record.load_instruction_kind {
+            Some(LoadInstruction::TopSetPercent { low, high, reps }) => {
+                let top = top_set(records, record, reps)?;
+                Some((index, round_to(top * (low + high) / 200.0, increment)))
+            }
+            _ => None,
+        })
+        .collect();
+    for (index, load) in &top_set_loads {
+        records[*index].resolved_load = Some(*load);
+    }
+
+    resolved + top_set_loads.len()
+}
+
+fn is_unresolved(record: &WorkoutRecord) -> bool {
+    record.record_type == "prescribed" && record.load.is_none() && record.resolved_load.is_none()
+}
+
+/// A prescription resolved against its lift's max. Top-set instructions follow the same
+/// day's sets, which are part of the week's own cells.
+fn needs_max(record: &WorkoutRecord) -> bool {
+    record.record_type == "prescribed"
+        && record.load.is_none()
+        && matches!(record.load_instruction_kind, Some(LoadInstruction::Percent { .. } | LoadInstruction::BaseOnMax))
+}
+
+/// Chart percentage for the prescription's reps (the low end of a range) at its highest RPE
+fn chart_percentage(record: &WorkoutRecord) -> Option<f64> {
+    let reps = record.reps.as_ref().filter(|r| r.seconds.is_none() && !r.to_rpe)?.min?;
+    let rpe = record.rpe.as_ref()?.per_set.iter().copied().reduce(f64::max)?;
+    rpe_percentage(reps, rpe)
+}
+
+/// Heaviest set of the record's lift on the same training day, other than the record itself
+fn top_set(records: &[WorkoutRecord], record: &WorkoutRecord, reps: Option<u32>) -> Option<f64> {
+    let heaviest = |record_type: &str| {
+        records
+            .iter()
+            .filter(|other| {
+                other.record_type == record_type
+                    && other.id != record.id
+                    && other.exercise_id == record.exercise_id
+                    && other.block_name == record.block_name
+                    && other.week_start_date == record.week_start_date
+                    && other.day_number == record.day_number
+                    && reps.is_none_or(|reps| other.reps.as_ref().and_then(|r| r.min) == Some(reps))
+            })
+            .filter_map(|other| match &other.load {
+                Some(load) if !load.bodyweight_relative => Some(load.value),
+                Some(_) => None,
+                None => other.resolved_load,
+            })
+            .filter(|&weight| weight > 0.0)
+            .reduce(f64::max)
+    };
+    heaviest("actual").or_else(|| heaviest("prescribed"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sheets::SheetRow;
+    use crate::transform::{normalize_block_data, ParseOptions};
+
+    fn row(row_number: usize, cells: &[&str]) -> SheetRow {
+        SheetRow { row_number, cells: cells.iter().map(|cell| cell.to_string()).collect() }
+    }
+
+    /// A squat prescribed as a percentage and a bench with a fixed load
+    fn records() -> Vec<WorkoutRecord> {
+        let rows = vec![
+            row(1, &["", "", "5/19/2025"]),
+            row(2, &["", "", "Week 1"]),
+            row(6, &["", "DAY 1"]),
+            row(7, &["", "Squat", "", "3", "5", "75%", "8"]),
+            row(8, &["", "Bench", "", "3", "8", "200", "7"]),
+        ];
+        normalize_block_data(rows, "Block 1", &ParseOptions::default()).unwrap().records
+    }
+
+    fn table(squat: f64, bench: f64) -> MaxTable {
+        let lifts = HashMap::from([("Squat".to_string(), squat), ("Bench".to_string(), bench)]);
+        MaxTable::from_config(&lifts, &ExerciseCatalog::default(), LoadUnit::Lb)
+    }
+
+    #[test]
+    fn fingerprint_follows_only_the_lifts_in_use() {
+        let records = records();
+        let base = table(400.0, 300.0).fingerprint(&records);
+        assert_eq!(table(400.0, 310.0).fingerprint(&records), base);
+        assert_ne!(table(405.0, 300.0).fingerprint(&records), base);
+        assert_eq!(table(405.0, 300.0).fingerprint(&[]), table(400.0, 300.0).fingerprint(&[]));
+    }
+
+    #[test]
+    fn resolves_percentages_against_the_max() {
+        let mut records = records();
+        assert_eq!(resolve_loads(&mut records, &table(400.0, 300.0), 5.0), 1);
+        let squat = records.iter().find(|r| r.exercise_canonical == "Squat").unwrap();
+        assert_eq!(squat.resolved_load, Some(300.0));
+    }
+}
@@ -0,0 +1,336 @@
+use anyhow::Result;
+use arrow_array::builder::{ListBuilder, StringBuilder};
+use arrow_array::types::{Date32Type, Float64Type, UInt32Type};
+use arrow_array::{
+    new_null_array, ArrayRef, BooleanArray, Date32Array, Float64Array, ListArray, RecordBatch, StringArray,
+    TimestampMicrosecondArray, UInt32Array,
+};
+use arrow_schema::{Field, Schema, SchemaRef};
+use arrow_select::concat::concat_batches;
+use arrow_select::filter::filter_record_batch;
+use chrono::NaiveDate;
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use std::collections::{BTreeMap, HashSet};
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tracing::{debug, info};
+use crate::hashing::slug;
+use crate::strength::estimate;
+use crate::transform::WorkoutRecord;
+
+/// Write records as Parquet under `dir`, one file per block week:
+/// `{dir}/block={block slug}/week={week start}.parquet`.
+///
+/// With `whole_weeks` (block mode, where a re-parsed week is emitted in full) a week's file is
+/// replaced outright, which also drops rows removed from the sheet. Otherwise (legacy raw_range
+/// mode, which only emits the rows added since the last run) the records are merged into the
+/// existing file by ID. Files are written to a temp file and renamed, so readers never see a
+/// partial file. Returns the number of files written.
+pub fn write(dir: &str, records: &[WorkoutRecord], whole_weeks: bool, ensure_directories: bool) -> Result<usize> {
+    let dir = Path::new(dir);
+    if !dir.exists() && !ensure_directories {
+        anyhow::bail!("Output directory does not exist: {:?} (set output_csv.ensure = true to create it)", dir);
+    }
+
+    let mut weeks: BTreeMap<(String, NaiveDate), Vec<&WorkoutRecord>> = BTreeMap::new();
+    for record in records {
+        weeks.entry((slug(&record.block_name), record.week_start_date)).or_default().push(record);
+    }
+
+    info!("Writing {} records to {} Parquet files under {}", records.len(), weeks.len(), dir.display());
+    for ((block, week_start), week_records) in &weeks {
+        let partition = dir.join(format!("block={}", block));
+        fs::create_dir_all(&partition)?;
+        let path = partition.join(format!("week={}.parquet", week_start));
+        let mut batch = record_batch(week_records)?;
+        if !whole_weeks && path.exists() {
+            batch = merge_existing(&path, &batch)?;
+        }
+        write_file(&path, &batch)?;
+        debug!("Wrote {} rows to {}", batch.num_rows(), path.display());
+    }
+
+    Ok(weeks.len())
+}
+
+/// The existing file's rows whose ID isn't in `batch`, followed by `batch`. A file written
+/// with other columns (by an older version) has its rows projected onto `batch`'s columns.
+fn merge_existing(path: &Path, batch: &RecordBatch) -> Result<RecordBatch> {
+    let incoming: HashSet<&str> = ids(batch)?.iter().flatten().collect();
+    let mut batches = Vec::new();
+    for existing in ParquetRecordBatchReaderBuilder::try_new(File::open(path)?)?.build()? {
+        let mut existing = existing?;
+        if existing.schema().fields() != batch.schema().fields() {
+            debug!("{} has different columns, projecting its rows onto the current ones", path.display());
+            existing = project(path, &existing, &batch.schema())?;
+        }
+        let keep: BooleanArray = ids(&existing)?.iter().map(|id| Some(!id.is_some_and(|id| incoming.contains(id)))).collect();
+        batches.push(filter_record_batch(&existing, &keep)?);
+    }
+    batches.push(batch.clone());
+    Ok(concat_batches(&batch.schema(), &batches)?)
+}
+
+/// `batch`'s columns in `schema`'s order, with nulls for the nullable columns it doesn't have.
+/// Fails when a column changed type or a required column is missing, rather than drop rows.
+fn project(path: &Path, batch: &RecordBatch, schema: &SchemaRef) -> Result<RecordBatch> {
+    let columns = schema
+        .fields()
+        .iter()
+        .map(|field| match batch.column_by_name(field.name()) {
+            Some(column) if column.data_type() == field.data_type() => Ok(column.clone()),
+            Some(column) => anyhow::bail!(
+                "{} has column {} as {}, but it is now {}; move the file aside to rewrite it",
+                path.display(), field.name(), column.data_type(), field.data_type()
+            ),
+            None if field.is_nullable() => Ok(new_null_array(field.data_type(), batch.num_rows())),
+            None => anyhow::bail!(
+                "{} has no {} column; move the file aside to rewrite it", path.display(), field.name()
+            ),
+        })
+        .collect::<Result<Vec<ArrayRef>>>()?;
+    Ok(RecordBatch::try_new(schema.clone(), columns)?)
+}
+
+fn ids(batch: &RecordBatch) -> Result<&StringArray> {
+    batch
+        .column_by_name("id")
+        .and_then(|column| column.as_any().downcast_ref::<StringArray>())
+        .ok_or_else(|| anyhow::anyhow!("Parquet batch has no string id column"))
+}
+
+fn write_file(path: &Path, batch: &RecordBatch) -> Result<()> {
+    let temp_path = PathBuf::from(format!("{}.tmp", path.display()));
+    let properties = WriterProperties::builder().set_compression(Compression::SNAPPY).build();
+    let mut writer = ArrowWriter::try_new(File::create(&temp_path)?, batch.schema(), Some(properties))?;
+    writer.write(batch)?;
+    writer.close()?;
+    fs::rename(&temp_path, path)?;
+    Ok(())
+}
+
+/// The long CSV's columns with their natural types: dates as date32, counts as uint32, loads
+/// and RPEs as float64, flags as booleans, and lists (modifiers, per-set values) as lists.
+/// Percentage instructions are split into `load_percent_low`/`load_percent_high`.
+fn record_batch(records: &[&WorkoutRecord]) -> Result<RecordBatch> {
+    let mut columns: Vec<(Field, ArrayRef)> = Vec::new();
+    let mut column = |name: &str, nullable: bool, array: ArrayRef| {
+        columns.push((Field::new(name, array.data_type().clone(), nullable), array));
+    };
+
+    let text = |value: fn(&WorkoutRecord) -> Option<String>| -> ArrayRef {
+        Arc::new(records.iter().map(|r| value(r)).collect::<StringArray>())
+    };
+    let count = |value: fn(&WorkoutRecord) -> Option<u32>| -> ArrayRef {
+        Arc::new(records.iter().map(|r| value(r)).collect::<UInt32Array>())
+    };
+    let number = |value: fn(&WorkoutRecord) -> Option<f64>| -> ArrayRef {
+        Arc::new(records.iter().map(|r| value(r)).collect::<Float64Array>())
+    };
+    let flag = |value: fn(&WorkoutRecord) -> Option<bool>| -> ArrayRef {
+        Arc::new(records.iter().map(|r| value(r)).collect::<BooleanArray>())
+    };
+    let date = |value: fn(&WorkoutRecord) -> NaiveDate| -> ArrayRef {
+        Arc::new(records.iter().map(|r| Some(Date32Type::from_naive_date(value(r)))).collect::<Date32Array>())
+    };
+    let numbers = |value: fn(&WorkoutRecord) -> Option<Vec<f64>>| -> ArrayRef {
+        Arc::new(ListArray::from_iter_primitive::<Float64Type, _, _>(
+            records.iter().map(|r| value(r).map(|values| values.into_iter().map(Some))),
+        ))
+    };
+    let counts = |value: fn(&WorkoutRecord) -> Option<Vec<u32>>| -> ArrayRef {
+        Arc::new(ListArray::from_iter_primitive::<UInt32Type, _, _>(
+            records.iter().map(|r| value(r).map(|values| values.into_iter().map(Some))),
+        ))
+    };
+    let texts = |value: fn(&WorkoutRecord) -> Vec<String>| -> ArrayRef {
+        let mut builder = ListBuilder::new(StringBuilder::new());
+        for record in records {
+            for item in value(record) {
+                builder.values().append_value(item);
+            }
+            builder.append(true);
+        }
+        Arc::new(builder.finish())
+    };
+    let e1rms: Vec<_> = records.iter().map(|r| estimate(r)).collect();
+
+    column("id", false, text(|r| Some(r.id.clone())));
+    column("block_name", false, text(|r| Some(r.block_name.clone())));
+    column("week_start_date", false, date(|r| r.week_start_date));
+    column("week_number", false, count(|r| Some(r.week_number)));
+    column("phase", true, text(|r| r.phase.clone()));
+    column("day_number", false, count(|r| Some(r.day_number)));
+    column("workout_date", false, date(|r| r.workout_date));
+    column("exercise_name", false, text(|r| Some(r.exercise_name.clone())));
+    column("exercise_id", false, text(|r| Some(r.exercise_id.clone())));
+    column("exercise_canonical", false, text(|r| Some(r.exercise_canonical.clone())));
+    column("modifiers", false, texts(|r| r.modifiers.clone()));
+    column("cue", true, text(|r| r.cue.clone()));
+    column("movement_pattern", false, text(|r| Some(r.taxonomy.movement_pattern.as_str().to_string())));
+    column("muscle_groups", false, texts(|r| r.taxonomy.muscle_groups.clone()));
+    column("equipment", false, text(|r| Some(r.taxonomy.equipment.as_str().to_string())));
+    column("lift_category", false, text(|r| Some(r.taxonomy.lift_category.as_str().to_string())));
+    column("record_type", false, text(|r| Some(r.record_type.clone())));
+    column("set_number", true, count(|r| r.set_number));
+    column("sets", true, count(|r| r.sets));
+    column("reps", true, text(|r| r.reps.as_ref().map(|reps| reps.raw.clone())));
+    column("reps_min", true, count(|r| r.reps.as_ref().and_then(|reps| reps.min)));
+    column("reps_max", true, count(|r| r.reps.as_ref().and_then(|reps| reps.max)));
+    column("reps_per_set", true, counts(|r| r.reps.as_ref().map(|reps| reps.per_set.clone())));
+    column("reps_amrap", true, flag(|r| r.reps.as_ref().map(|reps| reps.amrap)));
+    column("reps_seconds", true, count(|r| r.reps.as_ref().and_then(|reps| reps.seconds)));
+    column("reps_to_rpe", true, flag(|r| r.reps.as_ref().map(|reps| reps.to_rpe)));
+    column("load", true, number(|r| r.load.as_ref().map(|load| load.value)));
+    column("load_unit", true, text(|r| r.load.as_ref().map(|load| load.unit.to_string())));
+    column("load_per_side", true, flag(|r| r.load.as_ref().map(|load| load.per_side)));
+    column("load_bodyweight_relative", true, flag(|r| r.load.as_ref().map(|load| load.bodyweight_relative)));
+    column("load_per_set", true, numbers(|r| r.load.as_ref().map(|load| load.per_set.clone())));
+    column("load_raw", true, text(|r| r.load.as_ref().map(|load| load.raw.clone())));
+    column("load_instruction", true, text(|r| r.load_instruction.clone()));
+    column("load_instruction_kind", true, text(|r| r.load_instruction_kind.map(|kind| kind.kind().to_string())));
+    column("load_percent_low", true, number(|r| r.load_instruction_kind.and_then(|kind| kind.percent_range()).map(|(low, _)| low)));
+    column("load_percent_high", true, number(|r| r.load_instruction_kind.and_then(|kind| kind.percent_range()).map(|(_, high)| high)));
+    column("resolved_load", true, number(|r| r.resolved_load));
+    column("rpe", true, text(|r| r.rpe.as_ref().map(|rpe| rpe.raw.clone())));
+    column("rpe_values", true, numbers(|r| r.rpe.as_ref().map(|rpe| rpe.per_set.clone())));
+    column("rpe_from_rir", true, flag(|r| r.rpe.as_ref().map(|rpe| rpe.from_rir)));
+    column("rpe_missing", true, flag(|r| r.rpe.as_ref().map(|rpe| rpe.missing)));
+    column("rpe_qualifier", true, text(|r| r.rpe.as_ref().and_then(|rpe| rpe.qualifier.clone())));
+    column("e1rm_epley", true, Arc::new(e1rms.iter().map(|e| e.map(|e| e.epley)).collect::<Float64Array>()));
+    column("e1rm_brzycki", true, Arc::new(e1rms.iter().map(|e| e.map(|e| e.brzycki)).collect::<Float64Array>()));
+    column("e1rm_rpe", true, Arc::new(e1rms.iter().map(|e| e.and_then(|e| e.rpe)).collect::<Float64Array>()));
+    column("notes", true, text(|r| r.notes.clone()));
+    column("content_hash", false, text(|r| Some(r.content_hash())));
+    column(
+        "processed_at",
+        false,
+        Arc::new(
+            records
+                .iter()
+                .map(|r| Some(r.processed_at.timestamp_micros()))
+                .collect::<TimestampMicrosecondArray>()
+                .with_timezone("UTC"),
+        ),
+    );
+
+    let (fields, arrays): (Vec<Field>, Vec<ArrayRef>) = columns.into_iter().unzip();
+    Ok(RecordBatch::try_new(Arc::new(Schema::new(fields)), arrays)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sheets::SheetRow;
+    use crate::transform::{normalize_block_data, ParseOptions};
+
+    fn row(row_number: usize, cells: &[&str]) -> SheetRow {
+        SheetRow { row_number, cells: cells.iter().map(|cell| cell.to_string()).collect() }
+    }
+
+    /// One week with a squat logged as `squat_reps` and a bench
+    fn records(squat_reps: &str) -> Vec<WorkoutRecord> {
+        let rows = vec![
+            row(1, &["", "", "5/19/2025"]),
+            row(2, &["", "", "Week 1"]),
+            row(6, &["", "DAY 1"]),
+            row(7, &["", "Squat", "", "3", "5", "300", "8", "", "300", "3", squat_reps, "8"]),
+            row(8, &["", "Bench", "", "3", "8", "200", "7", "", "200", "3", "8", "7"]),
+        ];
+        normalize_block_data(rows, "Block 1", &ParseOptions::default()).unwrap().records
+    }
+
+    fn week_file(dir: &Path) -> PathBuf {
+        dir.join("block=block1").join("week=2025-05-19.parquet")
+    }
+
+    fn read(path: &Path) -> RecordBatch {
+        let reader = ParquetRecordBatchReaderBuilder::try_new(File::open(path).unwrap()).unwrap();
+        let schema = reader.schema().clone();
+        let batches: Vec<RecordBatch> = reader.build().unwrap().map(Result::unwrap).collect();
+        concat_batches(&schema, &batches).unwrap()
+    }
+
+    fn read_ids(path: &Path) -> Vec<String> {
+        ids(&read(path)).unwrap().iter().flatten().map(str::to_string).collect()
+    }
+
+    fn sorted_ids(records: &[WorkoutRecord]) -> Vec<String> {
+        let mut ids: Vec<String> = records.iter().map(|r| r.id.clone()).collect();
+        ids.sort();
+        ids
+    }
+
+    #[test]
+    fn whole_weeks_replace_the_file_and_read_back_with_the_same_schema() {
+        let dir = tempfile::tempdir().unwrap();
+        let out = dir.path().to_str().unwrap();
+        assert_eq!(write(out, &records("5,5,4"), true, false).unwrap(), 1);
+
+        let joined = records("5");
+        write(out, &joined, true, false).unwrap();
+
+        let batch = read(&week_file(dir.path()));
+        let expected = record_batch(&joined.iter().collect::<Vec<_>>()).unwrap();
+        assert_eq!(batch.schema(), expected.schema());
+        let mut ids = read_ids(&week_file(dir.path()));
+        ids.sort();
+        assert_eq!(ids, sorted_ids(&joined));
+    }
+
+    #[test]
+    fn merges_into_the_file_by_id() {
+        let dir = tempfile::tempdir().unwrap();
+        let out = dir.path().to_str().unwrap();
+        let mut records = records("5");
+        let bench = records.split_off(2);
+        write(out, &records, false, false).unwrap();
+
+        let mut edited = records[..1].to_vec();
+        edited[0].notes = Some("belt".to_string());
+        write(out, &edited, false, false).unwrap();
+        write(out, &bench, false, false).unwrap();
+
+        let batch = read(&week_file(dir.path()));
+        assert_eq!(batch.num_rows(), 4);
+        let notes = batch.column_by_name("notes").unwrap().as_any().downcast_ref::<StringArray>().unwrap();
+        let ids = read_ids(&week_file(dir.path()));
+        let edited_row = ids.iter().position(|id| *id == edited[0].id).unwrap();
+        assert_eq!(notes.value(edited_row), "belt");
+        assert_eq!(notes.iter().flatten().count(), 1);
+
+        records.extend(bench);
+        let mut ids = ids;
+        ids.sort();
+        assert_eq!(ids, sorted_ids(&records));
+    }
+
+    #[test]
+    fn projects_older_files_onto_the_current_columns() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = week_file(dir.path());
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        let records = records("5");
+        let current = record_batch(&records.iter().collect::<Vec<_>>()).unwrap();
+
+        // An older file without the nullable resolved_load column keeps its rows
+        let mut older = current.clone();
+        older.remove_column(current.schema().index_of("resolved_load").unwrap());
+        write_file(&path, &older).unwrap();
+        write(dir.path().to_str().unwrap(), &records[..1], false, false).unwrap();
+        let batch = read(&path);
+        assert_eq!(batch.schema(), current.schema());
+        assert_eq!(batch.num_rows(), records.len());
+
+        // One without a required column can't be merged into
+        let mut older = current.clone();
+        older.remove_column(current.schema().index_of("content_hash").unwrap());
+        write_file(&path, &older).unwrap();
+        assert!(write(dir.path().to_str().unwrap(), &records[..1], false, false).is_err());
+        assert_eq!(read(&path).num_columns(), current.num_columns() - 1);
+    }
+}
@@ -0,0 +1,377 @@
+use chrono::{Duration, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use tracing::{debug, info};
+use crate::load::LoadInstruction;
+use crate::strength::{estimate, round_to, rpe_percentage};
+use crate::transform::WorkoutRecord;
+
+/// An e1RM estimate from one actual record
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct E1rmPoint {
+    pub date: NaiveDate,
+    pub value: f64,
+}
+
+/// Recent e1RM estimates per canonical exercise, keyed by the record they came from so a
+/// re-normalized record replaces its own estimate
+pub type E1rmHistory = HashMap<String, BTreeMap<String, E1rmPoint>>;
+
+/// What a suggestion needs from a prescription without a load
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UpcomingPrescription {
+    pub id: String,
+    pub block_name: String,
+    pub week_number: u32,
+    pub day_number: u32,
+    pub workout_date: NaiveDate,
+    pub exercise_id: String,
+    pub exercise_canonical: String,
+    #[serde(default)]
+    pub sets: Option<u32>,
+    /// Prescribed reps (the low end of a range); None for timed and to-RPE sets
+    #[serde(default)]
+    pub target_reps: Option<u32>,
+    /// Highest prescribed RPE
+    #[serde(default)]
+    pub target_rpe: Option<f64>,
+    #[serde(default)]
+    pub load_instruction: Option<String>,
+    #[serde(default)]
+    pub load_instruction_kind: Option<LoadInstruction>,
+    #[serde(default)]
+    pub sheet_row: usize,
+    #[serde(default)]
+    pub load_column: Option<usize>,
+}
+
+impl UpcomingPrescription {
+    fn of_record(record: &WorkoutRecord) -> Self {
+        UpcomingPrescription {
+            id: record.id.clone(),
+            block_name: record.block_name.clone(),
+            week_number: record.week_number,
+            day_number: record.day_number,
+            workout_date: record.workout_date,
+            exercise_id: record.exercise_id.clone(),
+            exercise_canonical: record.exercise_canonical.clone(),
+            sets: record.set_count(),
+            target_reps: record.reps.as_ref().filter(|r| r.seconds.is_none() && !r.to_rpe).and_then(|r| r.min),
+            target_rpe: record.rpe.as_ref().and_then(|rpe| rpe.per_set.iter().copied().reduce(f64::max)),
+            load_instruction: record.load_instruction.clone(),
+            load_instruction_kind: record.load_instruction_kind,
+            sheet_row: record.sheet_row,
+            load_column: record.load_column,
+        }
+    }
+}
+
+/// Prescriptions without a load, by block name and week start date, kept until their
+/// session is logged or has passed
+pub type UpcomingHistory = BTreeMap<String, BTreeMap<NaiveDate, Vec<UpcomingPrescription>>>;
+
+#[derive(Debug, Clone, Copy)]
+pub struct ProgressionOptions {
+    /// Estimates older than this before the session are ignored
+    pub lookback_days: i64,
+    /// Sessions up to this many days ahead get a suggestion
+    pub horizon_days: i64,
+    /// Suggested loads are rounded to a multiple of this
+    pub increment: f64,
+    /// Target RPE for "find" prescriptions that don't give one
+    pub find_rpe: f64,
+}
+
+/// Store the estimates of newly normalized actual records, dropping estimates older than
+/// `lookback_days` before each exercise's newest one
+pub fn record_estimates(history: &mut E1rmHistory, records: &[WorkoutRecord], lookback_days: i64) {
+    for record in records {
+        let Some(e1rm) = estimate(record) else {
+            continue;
+        };
+        history.entry(record.exercise_id.clone()).or_default().insert(
+            record.id.clone(),
+            E1rmPoint { date: record.workout_date, value: e1rm.preferred() },
+        );
+    }
+
+    for points in history.values_mut() {
+        if let Some(newest) = points.values().map(|point| point.date).max() {
+            points.retain(|_, point| point.date >= newest - Duration::days(lookback_days));
+        }
+    }
+}
+
+/// Store the upcoming prescriptions of every week the records cover: those with no load and
+/// no actuals logged on their row. With `whole_weeks` (block mode) they replace the week's
+/// stored ones; otherwise (legacy raw_range mode) they are merged in by ID.
+pub fn record_upcoming(history: &mut UpcomingHistory, records: &[WorkoutRecord], whole_weeks: bool) {
+    let mut weeks: HashMap<(&str, NaiveDate), Vec<UpcomingPrescription>> = HashMap::new();
+    let logged: HashSet<&str> = records
+        .iter()
+        .filter(|record| record.record_type == "actual")
+        .map(WorkoutRecord::row_key)
+        .collect();
+
+    for record in records {
+        let week = weeks.entry((&record.block_name, record.week_start_date)).or_default();
+        if record.record_type == "prescribed" && record.load.is_none() && !logged.contains(record.row_key()) {
+            week.push(UpcomingPrescription::of_record(record));
+        }
+    }
+
+    for ((block_name, week_start), upcoming) in weeks {
+        let stored = history.entry(block_name.to_string()).or_default().entry(week_start).or_default();
+        if whole_weeks {
+            *stored = upcoming;
+            continue;
+        }
+        for prescription in upcoming {
+            match stored.iter_mut().find(|existing| existing.id == prescription.id) {
+                Some(existing) => *existing = prescription,
+                None => stored.push(prescription),
+            }
+        }
+    }
+}
+
+/// Drop prescriptions whose session date is before `today`
+pub fn prune_upcoming(history: &mut UpcomingHistory, today: NaiveDate) {
+    for weeks in history.values_mut() {
+        for upcoming in weeks.values_mut() {
+            upcoming.retain(|record| record.workout_date >= today);
+        }
+        weeks.retain(|_, upcoming| !upcoming.is_empty());
+    }
+    history.retain(|_, weeks| !weeks.is_empty());
+}
+
+/// A suggested working load for an upcoming prescription
+#[derive(Debug, Clone)]
+pub struct Suggestion {
+    pub record_id: String,
+    pub block_name: String,
+    pub week_number: u32,
+    pub day_number: u32,
+    pub workout_date: NaiveDate,
+    pub exercise_id: String,
+    pub exercise: String,
+    pub sets: Option<u32>,
+    pub reps: u32,
+    pub rpe: f64,
+    pub load_instruction: Option<String>,
+    /// Best estimate within the lookback window, and the date it was set
+    pub e1rm: f64,
+    pub e1rm_date: NaiveDate,
+    /// Share of the e1RM from the RPE chart
+    pub percent: f64,
+    pub load: f64,
+    pub unit: String,
+    // Where the load is written back to the sheet
+    pub sheet_row: usize,
+    pub load_column: Option<usize>,
+}
+
+impl Suggestion {
+    pub fn to_csv_headers() -> Vec<String> {
+        [
+            "record_id", "block_name", "week_number", "day_number", "workout_date", "exercise_id",
+            "exercise", "sets", "reps", "rpe", "load_instruction", "e1rm", "e1rm_date", "percent",
+            "suggested_load", "unit",
+        ]
+        .into_iter()
+        .map(str::to_string)
+        .collect()
+    }
+
+    pub fn to_csv_row(&self) -> Vec<String> {
+        vec![
+            self.record_id.clone(),
+            self.block_name.clone(),
+            self.week_number.to_string(),
+            self.day_number.to_string(),
+            self.workout_date.to_string(),
+            self.exercise_id.clone(),
+            self.exercise.clone(),
+            self.sets.map(|n| n.to_string()).unwrap_or_default(),
+            self.reps.to_string(),
+            self.rpe.to_string(),
+            self.load_instruction.clone().unwrap_or_default(),
+            self.e1rm.to_string(),
+            self.e1rm_date.to_string(),
+            self.percent.to_string(),
+            self.load.to_string(),
+            self.unit.clone(),
+        ]
+    }
+}
+
+/// Suggest loads for the upcoming prescriptions in the next `horizon_days`, in date order.
+///
+/// The load is the exercise's best recent e1RM times the chart percentage for the prescribed
+/// reps (the low end of a range) at the prescribed RPE (the highest when several are given).
+/// Prescriptions without reps, a target RPE or a recent estimate are skipped; those missing
+/// only the RPE are logged, as the sheet usually just needs one filled in.
+pub fn suggest(
+    upcoming: &UpcomingHistory,
+    e1rms: &E1rmHistory,
+    today: NaiveDate,
+    unit: &str,
+    options: ProgressionOptions,
+) -> Vec<Suggestion> {
+    let horizon = today + Duration::days(options.horizon_days);
+    let mut missing_rpe = 0;
+    let mut suggestions: Vec<Suggestion> = upcoming
+        .values()
+        .flat_map(|weeks| weeks.values().flatten())
+        .filter(|record| (today..=horizon).contains(&record.workout_date))
+        .filter_map(|record| {
+            let reps = record.target_reps?;
+            let find_rpe = (record.load_instruction_kind == Some(LoadInstruction::Find)).then_some(options.find_rpe);
+            let Some(rpe) = record.target_rpe.or(find_rpe) else {
+                debug!("No target RPE for {} on {} (row {}), so no load is suggested",
+                       record.exercise_canonical, record.workout_date, record.sheet_row);
+                missing_rpe += 1;
+                return None;
+            };
+            let percent = rpe_percentage(reps, rpe)?;
+
+            let since = record.workout_date - Duration::days(options.lookback_days);
+            let best = e1rms
+                .get(&record.exercise_id)?
+                .values()
+                .filter(|point| point.date >= since && point.date <= record.workout_date)
+                .max_by(|a, b| a.value.total_cmp(&b.value))?;
+
+            Some(Suggestion {
+                record_id: record.id.clone(),
+                block_name: record.block_name.clone(),
+                week_number: record.week_number,
+                day_number: record.day_number,
+                workout_date: record.workout_date,
+                exercise_id: record.exercise_id.clone(),
+                exercise: record.exercise_canonical.clone(),
+                sets: record.sets,
+                reps,
+                rpe,
+                load_instruction: record.load_instruction.clone(),
+                e1rm: best.value,
+                e1rm_date: best.date,
+                percent,
+                load: round_to(best.value * percent / 100.0, options.increment),
+                unit: unit.to_string(),
+                sheet_row: record.sheet_row,
+                load_column: record.load_column,
+            })
+        })
+        .collect();
+    if missing_rpe > 0 {
+        info!("Skipped {} upcoming prescriptions with no target RPE to suggest a load from", missing_rpe);
+    }
+
+    suggestions.sort_by(|a, b| a.workout_date.cmp(&b.workout_date).then_with(|| a.record_id.cmp(&b.record_id)));
+    suggestions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sheets::SheetRow;
+    use crate::transform::{normalize_block_data, ParseOptions};
+
+    fn row(row_number: usize, cells: &[&str]) -> SheetRow {
+        SheetRow { row_number, cells: cells.iter().map(|cell| cell.to_string()).collect() }
+    }
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 5, day).unwrap()
+    }
+
+    const OPTIONS: ProgressionOptions = ProgressionOptions { lookback_days: 28, horizon_days: 0, increment: 5.0, find_rpe: 8.0 };
+
+    /// Day 1 (5/19) has a squat at RPE 8, a bench "find" with no RPE, a loaded deadlift, a
+    /// press with no RPE and a logged row; Day 2 (5/20) has a heavier squat
+    fn records() -> Vec<WorkoutRecord> {
+        let rows = vec![
+            row(1, &["", "", "5/19/2025"]),
+            row(2, &["", "", "Week 1"]),
+            row(6, &["", "DAY 1"]),
+            row(7, &["", "Squat", "", "3", "5", "", "8"]),
+            row(8, &["", "Bench", "", "3", "5", "find", ""]),
+            row(9, &["", "Deadlift", "", "3", "5", "400", "8"]),
+            row(10, &["", "Press", "", "3", "5", "", ""]),
+            row(11, &["", "Row", "", "3", "10", "", "8", "", "100", "3", "10", "8"]),
+            row(12, &["", "DAY 2"]),
+            row(13, &["", "Squat", "", "3", "3", "", "9"]),
+        ];
+        normalize_block_data(rows, "Block 1", &ParseOptions::default()).unwrap().records
+    }
+
+    fn exercises(upcoming: &UpcomingHistory) -> Vec<(u32, &str)> {
+        upcoming
+            .values()
+            .flat_map(|weeks| weeks.values().flatten())
+            .map(|p| (p.day_number, p.exercise_canonical.as_str()))
+            .collect()
+    }
+
+    /// e1RMs for every upcoming exercise: `value` on `on`, and a much higher one long before
+    fn e1rms(upcoming: &UpcomingHistory, value: f64, on: NaiveDate) -> E1rmHistory {
+        let mut history = E1rmHistory::new();
+        for prescription in upcoming.values().flat_map(|weeks| weeks.values().flatten()) {
+            let points = history.entry(prescription.exercise_id.clone()).or_default();
+            points.insert("recent".to_string(), E1rmPoint { date: on, value });
+            points.insert("stale".to_string(), E1rmPoint { date: on - Duration::days(60), value: value * 2.0 });
+        }
+        history
+    }
+
+    #[test]
+    fn keeps_unloaded_unlogged_prescriptions() {
+        let mut upcoming = UpcomingHistory::new();
+        record_upcoming(&mut upcoming, &records(), true);
+        assert_eq!(exercises(&upcoming), vec![(1, "Squat"), (1, "Bench"), (1, "Press"), (2, "Squat")]);
+
+        prune_upcoming(&mut upcoming, date(20));
+        assert_eq!(exercises(&upcoming), vec![(2, "Squat")]);
+    }
+
+    #[test]
+    fn merges_by_id_without_whole_weeks() {
+        let records = records();
+        let day_two: Vec<WorkoutRecord> = records.iter().filter(|r| r.day_number == 2).cloned().collect();
+
+        let mut upcoming = UpcomingHistory::new();
+        record_upcoming(&mut upcoming, &records, false);
+        record_upcoming(&mut upcoming, &day_two, false);
+        assert_eq!(exercises(&upcoming).len(), 4);
+
+        record_upcoming(&mut upcoming, &day_two, true);
+        assert_eq!(exercises(&upcoming), vec![(2, "Squat")]);
+    }
+
+    #[test]
+    fn suggests_within_the_horizon_from_recent_estimates() {
+        let mut upcoming = UpcomingHistory::new();
+        record_upcoming(&mut upcoming, &records(), true);
+        let e1rms = e1rms(&upcoming, 400.0, date(12));
+
+        // The press has no target RPE; day 2 is past the horizon
+        let suggestions = suggest(&upcoming, &e1rms, date(19), "lb", OPTIONS);
+        let found: Vec<(&str, f64)> = suggestions.iter().map(|s| (s.exercise.as_str(), s.rpe)).collect();
+        assert_eq!(found, vec![("Squat", 8.0), ("Bench", 8.0)]);
+
+        // 5 reps at RPE 8 is 81.1% of the 400 e1RM, 324.4, rounded to 325
+        let squat = &suggestions[0];
+        assert_eq!((squat.e1rm, squat.e1rm_date), (400.0, date(12)));
+        assert_eq!((squat.percent, squat.load), (81.1, 325.0));
+
+        let suggestions = suggest(&upcoming, &e1rms, date(19), "lb", ProgressionOptions { horizon_days: 1, ..OPTIONS });
+        assert_eq!(suggestions.len(), 3);
+        assert_eq!(suggestions[2].workout_date, date(20));
+
+        // An estimate older than the lookback window is ignored
+        let suggestions = suggest(&upcoming, &e1rms, date(19), "lb", ProgressionOptions { lookback_days: 3, ..OPTIONS });
+        assert!(suggestions.is_empty());
+    }
+}
@@ -0,0 +1,176 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use tracing::info;
+use crate::load::LoadUnit;
+use crate::strength::estimate;
+use crate::transform::WorkoutRecord;
+
+/// The set that holds a record
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PrEntry {
+    /// The recorded value: the load for rep PRs, the estimate for e1RM PRs
+    pub value: f64,
+    pub load: f64,
+    pub reps: u32,
+    pub date: NaiveDate,
+    pub record_id: String,
+}
+
+/// Personal records of one canonical exercise, kept in state between runs
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ExerciseRecords {
+    pub name: String,
+    pub unit: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub best_e1rm: Option<PrEntry>,
+    // Heaviest load lifted for each rep count
+    #[serde(default)]
+    pub rep_prs: BTreeMap<u32, PrEntry>,
+}
+
+impl ExerciseRecords {
+    /// Convert the stored loads and estimates to `unit`, if they were recorded in another one
+    fn convert_to(&mut self, unit: LoadUnit) {
+        let Ok(stored) = self.unit.parse::<LoadUnit>() else {
+            return;
+        };
+        if stored == unit {
+            return;
+        }
+        for entry in self.rep_prs.values_mut().chain(self.best_e1rm.as_mut()) {
+            entry.value = stored.convert(entry.value, unit);
+            entry.load = stored.convert(entry.load, unit);
+        }
+        self.unit = unit.to_string();
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrKind {
+    /// Heaviest load for a rep count
+    Reps,
+    /// Highest estimated 1RM
+    E1rm,
+}
+
+impl PrKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PrKind::Reps => "rep_pr",
+            PrKind::E1rm => "e1rm_pr",
+        }
+    }
+}
+
+/// A PR set in this run
+#[derive(Debug, Clone)]
+pub struct PersonalRecord {
+    pub kind: PrKind,
+    pub exercise_id: String,
+    pub exercise: String,
+    pub entry: PrEntry,
+    pub unit: String,
+    /// The value it beat, None for the first record of its kind
+    pub previous: Option<f64>,
+    pub block_name: String,
+    pub detected_at: DateTime<Utc>,
+}
+
+impl PersonalRecord {
+    pub fn to_csv_headers() -> Vec<String> {
+        [
+            "detected_at", "kind", "exercise_id", "exercise", "workout_date", "reps", "load",
+            "value", "previous", "unit", "block_name", "record_id",
+        ]
+        .into_iter()
+        .map(str::to_string)
+        .collect()
+    }
+
+    pub fn to_csv_row(&self) -> Vec<String> {
+        vec![
+            self.detected_at.to_rfc3339(),
+            self.kind.as_str().to_string(),
+            self.exercise_id.clone(),
+            self.exercise.clone(),
+            self.entry.date.to_string(),
+            self.entry.reps.to_string(),
+            self.entry.load.to_string(),
+            self.entry.value.to_string(),
+            self.previous.map(|p| p.to_string()).unwrap_or_default(),
+            self.unit.clone(),
+            self.block_name.clone(),
+            self.entry.record_id.clone(),
+        ]
+    }
+}
+
+/// Compare newly normalized actual records against the stored records, updating them
+/// in place, and return the PRs they set in workout-date order.
+///
+/// Only strictly heavier loads and higher estimates count, so records re-emitted unchanged
+/// by a later run are never reported twice.
+pub fn detect_prs(history: &mut HashMap<String, ExerciseRecords>, records: &[WorkoutRecord]) -> Vec<PersonalRecord> {
+    let mut ordered: Vec<&WorkoutRecord> = records.iter().filter(|r| r.record_type == "actual").collect();
+    ordered.sort_by(|a, b| a.workout_date.cmp(&b.workout_date).then_with(|| a.id.cmp(&b.id)));
+
+    let detected_at = Utc::now();
+    let mut prs = Vec::new();
+
+    for record in ordered {
+        let Some(load) = record.load.as_ref().filter(|load| !load.bodyweight_relative && load.value > 0.0) else {
+            continue;
+        };
+        let Some(reps) = record.reps.as_ref().filter(|reps| reps.seconds.is_none() && !reps.to_rpe).and_then(|reps| reps.min) else {
+            continue;
+        };
+        if reps == 0 {
+            continue;
+        }
+
+        // Stored records may be in a previously configured unit; compare like with like
+        let exercise = history.entry(record.exercise_id.clone()).or_default();
+        exercise.convert_to(load.unit);
+        exercise.name = record.exercise_canonical.clone();
+        exercise.unit = load.unit.to_string();
+
+        let entry = |value: f64| PrEntry {
+            value,
+            load: load.value,
+            reps,
+            date: record.workout_date,
+            record_id: record.id.clone(),
+        };
+        let mut report = |kind: PrKind, entry: PrEntry, previous: Option<f64>| {
+            info!("New {} for {}: {} x {} ({}{}) on {}", if kind == PrKind::Reps { "rep PR" } else { "e1RM PR" },
+                  record.exercise_canonical, entry.load, entry.reps, entry.value, load.unit, entry.date);
+            prs.push(PersonalRecord {
+                kind,
+                exercise_id: record.exercise_id.clone(),
+                exercise: record.exercise_canonical.clone(),
+                entry,
+                unit: load.unit.to_string(),
+                previous,
+                block_name: record.block_name.clone(),
+                detected_at,
+            });
+        };
+
+        let previous = exercise.rep_prs.get(&reps).map(|pr| pr.value);
+        if previous.is_none_or(|previous| load.value > previous) {
+            exercise.rep_prs.insert(reps, entry(load.value));
+            report(PrKind::Reps, entry(load.value), previous);
+        }
+
+        if let Some(e1rm) = estimate(record).map(|e| e.preferred()) {
+            let previous = exercise.best_e1rm.as_ref().map(|pr| pr.value);
+            if previous.is_none_or(|previous| e1rm > previous) {
+                exercise.best_e1rm = Some(entry(e1rm));
+                report(PrKind::E1rm, entry(e1rm), previous);
+            }
+        }
+    }
+
+    prs
+}
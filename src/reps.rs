@@ -0,0 +1,214 @@
+use anyhow::Result;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+
+/// A reps cell, e.g. "7", "8-10", "5,5,4", "3x5", "AMRAP", "5+", "30s", "up to RPE 8"
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RepScheme {
+    /// Fewest reps asked for or done; equal to `max` for a fixed count
+    pub min: Option<u32>,
+    pub max: Option<u32>,
+    /// Reps of each set when the cell lists several ("5,5,4"), otherwise empty
+    pub per_set: Vec<u32>,
+    /// Set count written into the reps cell: the 3 in "3x5"
+    pub sets: Option<u32>,
+    /// As many reps as possible: "AMRAP", "max reps", "5+" (`min` is then the floor)
+    pub amrap: bool,
+    /// Duration of a timed set: "30s", "1 min", "0:45"
+    pub seconds: Option<u32>,
+    /// Reps until a target RPE rather than a count: "up to RPE 8"
+    pub to_rpe: bool,
+    /// The cell as written
+    pub raw: String,
+}
+
+impl RepScheme {
+    fn empty(raw: &str) -> Self {
+        RepScheme {
+            min: None,
+            max: None,
+            per_set: Vec::new(),
+            sets: None,
+            amrap: false,
+            seconds: None,
+            to_rpe: false,
+            raw: raw.to_string(),
+        }
+    }
+
+    /// The scheme of the `index`th set (0-based) of a per-set list
+    pub fn for_set(&self, index: usize) -> RepScheme {
+        let reps = self.per_set.get(index).copied();
+        RepScheme {
+            min: reps,
+            max: reps,
+            per_set: Vec::new(),
+            sets: None,
+            raw: reps.map(|r| r.to_string()).unwrap_or_default(),
+            ..self.clone()
+        }
+    }
+
+    /// Total reps across all sets when the count is known: the per-set list, or the top of
+    /// the range times the record's set count (else the cell's own "3x5" count, else one)
+    pub fn total_reps(&self, record_sets: Option<u32>) -> Option<u32> {
+        if !self.per_set.is_empty() {
+            return Some(self.per_set.iter().sum());
+        }
+        let sets = record_sets.or(self.sets).unwrap_or(1);
+        self.max.or(self.min).map(|reps| reps * sets)
+    }
+
+    /// `per_set` joined with ",", for the CSV
+    pub fn per_set_label(&self) -> String {
+        self.per_set.iter().map(|r| r.to_string()).collect::<Vec<_>>().join(",")
+    }
+}
+
+/// Parse a reps cell. Fails only when nothing in the cell is recognizable.
+pub fn parse_reps(cell: &str) -> Result<RepScheme> {
+    static SETS_BY_REPS: OnceLock<Regex> = OnceLock::new();
+    static RANGE: OnceLock<Regex> = OnceLock::new();
+    static LIST: OnceLock<Regex> = OnceLock::new();
+    static SINGLE: OnceLock<Regex> = OnceLock::new();
+    static AMRAP: OnceLock<Regex> = OnceLock::new();
+    static TO_RPE: OnceLock<Regex> = OnceLock::new();
+    static DURATION: OnceLock<Regex> = OnceLock::new();
+    static CLOCK: OnceLock<Regex> = OnceLock::new();
+
+    let sets_by_reps_re = SETS_BY_REPS.get_or_init(|| {
+        Regex::new(r"^(\d+)\s*[x×*]\s*(\d+)(?:\s*(?:-|–|to)\s*(\d+))?$").expect("valid sets-by-reps regex")
+    });
+    let range_re = RANGE.get_or_init(|| {
+        Regex::new(r"^(\d+)\s*(?:-|–|to)\s*(\d+)$").expect("valid range regex")
+    });
+    let list_re = LIST.get_or_init(|| {
+        Regex::new(r"^\d+(?:\s*[,/;]\s*\d+)+$").expect("valid list regex")
+    });
+    let single_re = SINGLE.get_or_init(|| Regex::new(r"^(\d+)$").expect("valid single regex"));
+    let amrap_re = AMRAP.get_or_init(|| {
+        Regex::new(r"(?i)\bamrap\b|\bmax(?:imum)?\s+reps\b|\+\s*$").expect("valid amrap regex")
+    });
+    let to_rpe_re = TO_RPE.get_or_init(|| {
+        Regex::new(r"(?i)\b(?:up\s+)?to\s+(?:an?\s+)?(?:rpe|rir)\b").expect("valid to-rpe regex")
+    });
+    let duration_re = DURATION.get_or_init(|| {
+        Regex::new(r"(?i)^(\d+)\s*(s|secs?|seconds?|m|mins?|minutes?)$").expect("valid duration regex")
+    });
+    let clock_re = CLOCK.get_or_init(|| Regex::new(r"^(\d*):(\d{2})$").expect("valid clock regex"));
+
+    let raw = cell.trim();
+    let mut scheme = RepScheme::empty(raw);
+
+    if to_rpe_re.is_match(raw) {
+        scheme.to_rpe = true;
+        return Ok(scheme);
+    }
+
+    if let Some(captures) = duration_re.captures(raw) {
+        let amount: u32 = captures[1].parse()?;
+        let seconds = if captures[2].to_ascii_lowercase().starts_with('m') { amount * 60 } else { amount };
+        scheme.seconds = Some(seconds);
+        return Ok(scheme);
+    }
+    if let Some(captures) = clock_re.captures(raw) {
+        let minutes: u32 = if captures[1].is_empty() { 0 } else { captures[1].parse()? };
+        scheme.seconds = Some(minutes * 60 + captures[2].parse::<u32>()?);
+        return Ok(scheme);
+    }
+
+    // Whatever count remains once "AMRAP" / "+" is removed is the floor: "AMRAP 8", "5+"
+    scheme.amrap = amrap_re.is_match(raw);
+    let text = amrap_re.replace_all(raw, " ");
+    let text = text.trim();
+
+    if let Some(captures) = sets_by_reps_re.captures(text) {
+        let min: u32 = captures[2].parse()?;
+        scheme.sets = Some(captures[1].parse()?);
+        scheme.min = Some(min);
+        scheme.max = Some(captures.get(3).map_or(Ok(min), |m| m.as_str().parse())?);
+    } else if let Some(captures) = range_re.captures(text) {
+        let (a, b): (u32, u32) = (captures[1].parse()?, captures[2].parse()?);
+        scheme.min = Some(a.min(b));
+        scheme.max = Some(a.max(b));
+    } else if list_re.is_match(text) {
+        scheme.per_set = text
+            .split([',', '/', ';'])
+            .map(|part| part.trim().parse::<u32>())
+            .collect::<Result<_, _>>()?;
+        scheme.min = scheme.per_set.iter().copied().min();
+        scheme.max = scheme.per_set.iter().copied().max();
+    } else if let Some(captures) = single_re.captures(text) {
+        let reps: u32 = captures[1].parse()?;
+        scheme.min = Some(reps);
+        scheme.max = Some(reps);
+    } else if !(scheme.amrap && text.is_empty()) {
+        anyhow::bail!("not a recognized rep scheme");
+    }
+
+    // An AMRAP set has no upper bound
+    if scheme.amrap {
+        scheme.max = None;
+    }
+
+    Ok(scheme)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reps(cell: &str) -> RepScheme {
+        parse_reps(cell).unwrap()
+    }
+
+    #[test]
+    fn parses_counts_and_ranges() {
+        let single = reps("7");
+        assert_eq!((single.min, single.max), (Some(7), Some(7)));
+        let range = reps("10-8");
+        assert_eq!((range.min, range.max), (Some(8), Some(10)));
+        let sets = reps("3x5");
+        assert_eq!((sets.sets, sets.min, sets.max), (Some(3), Some(5), Some(5)));
+        assert_eq!(reps("4 x 6-8").max, Some(8));
+    }
+
+    #[test]
+    fn parses_per_set_lists() {
+        let list = reps("5,5,4");
+        assert_eq!(list.per_set, vec![5, 5, 4]);
+        assert_eq!((list.min, list.max), (Some(4), Some(5)));
+        assert_eq!(list.total_reps(None), Some(14));
+        assert_eq!(list.for_set(2).min, Some(4));
+        assert_eq!(reps("10/10/10").per_set, vec![10, 10, 10]);
+    }
+
+    #[test]
+    fn parses_amrap_and_targets() {
+        let plus = reps("5+");
+        assert!(plus.amrap);
+        assert_eq!((plus.min, plus.max), (Some(5), None));
+        assert!(reps("AMRAP").amrap);
+        assert!(reps("up to RPE 8").to_rpe);
+    }
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(reps("30s").seconds, Some(30));
+        assert_eq!(reps("1 min").seconds, Some(60));
+        assert_eq!(reps("0:45").seconds, Some(45));
+    }
+
+    #[test]
+    fn totals_use_the_record_set_count() {
+        assert_eq!(reps("5").total_reps(Some(3)), Some(15));
+        assert_eq!(reps("3x5").total_reps(None), Some(15));
+    }
+
+    #[test]
+    fn rejects_unrecognized_cells() {
+        assert!(parse_reps("lots").is_err());
+        assert!(parse_reps("").is_err());
+    }
+}
@@ -0,0 +1,150 @@
+use anyhow::Result;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+
+/// An RPE cell, e.g. "8", "6.5", "@8", "5, 6", "easy 7", "2 RIR", "rate"
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Rpe {
+    /// One RPE per set, or a single value for the whole exercise. A range ("7-8") is
+    /// recorded as its midpoint.
+    pub per_set: Vec<f64>,
+    /// The cell was written as reps in reserve and converted (RPE = 10 - RIR)
+    pub from_rir: bool,
+    /// The cell is a placeholder ("rate", "?", "tbd") rather than a rating
+    pub missing: bool,
+    /// Descriptive words around the number: "easy" in "easy 7"
+    pub qualifier: Option<String>,
+    /// The cell as written
+    pub raw: String,
+}
+
+impl Rpe {
+    /// The RPE of the `index`th set (0-based) of a per-set list
+    pub fn for_set(&self, index: usize) -> Rpe {
+        Rpe {
+            per_set: self.per_set.get(index).copied().into_iter().collect(),
+            ..self.clone()
+        }
+    }
+
+    /// `per_set` joined with ",", for the CSV
+    pub fn values_label(&self) -> String {
+        self.per_set.iter().map(|r| r.to_string()).collect::<Vec<_>>().join(",")
+    }
+}
+
+/// Parse an RPE cell. Fails when a number can't be an RPE (above 10, or RIR above 10).
+pub fn parse_rpe(cell: &str) -> Result<Rpe> {
+    static PLACEHOLDER: OnceLock<Regex> = OnceLock::new();
+    static VALUE: OnceLock<Regex> = OnceLock::new();
+    static RIR: OnceLock<Regex> = OnceLock::new();
+    static NOISE: OnceLock<Regex> = OnceLock::new();
+    static NOTE: OnceLock<Regex> = OnceLock::new();
+
+    let placeholder_re = PLACEHOLDER.get_or_init(|| {
+        Regex::new(r"(?i)^(?:rate|rate\s+it|rpe|tbd|n/?a|\?+|-+|x)$").expect("valid placeholder regex")
+    });
+    let value_re = VALUE.get_or_init(|| {
+        Regex::new(r"(\d+(?:\.\d+)?)(?:\s*(?:-|–|to)\s*(\d+(?:\.\d+)?))?").expect("valid rpe value regex")
+    });
+    let rir_re = RIR.get_or_init(|| Regex::new(r"(?i)\brir\b").expect("valid rir regex"));
+    let noise_re = NOISE.get_or_init(|| Regex::new(r"(?i)\brpe\b|\brir\b|@").expect("valid rpe noise regex"));
+    let note_re = NOTE.get_or_init(|| Regex::new(r"\([^)]*\)").expect("valid rpe note regex"));
+
+    let raw = cell.trim();
+    // A bracketed note restates the rating ("8 (2 RIR)"), so it's only read when the rest of
+    // the cell has no number
+    let without_note = note_re.replace_all(raw, " ");
+    let text = if value_re.is_match(&without_note) { without_note.trim() } else { raw };
+    let mut rpe = Rpe {
+        per_set: Vec::new(),
+        from_rir: rir_re.is_match(text),
+        missing: false,
+        qualifier: None,
+        raw: raw.to_string(),
+    };
+
+    if placeholder_re.is_match(raw) {
+        rpe.missing = true;
+        return Ok(rpe);
+    }
+
+    for captures in value_re.captures_iter(text) {
+        let low: f64 = captures[1].parse()?;
+        let value = match captures.get(2) {
+            Some(high) => (low + high.as_str().parse::<f64>()?) / 2.0,
+            None => low,
+        };
+        if value > 10.0 {
+            anyhow::bail!("{} is outside the RPE/RIR scale", value);
+        }
+        rpe.per_set.push(if rpe.from_rir { 10.0 - value } else { value });
+    }
+
+    let words = noise_re.replace_all(&value_re.replace_all(text, " "), " ")
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+    if !words.is_empty() {
+        rpe.qualifier = Some(words);
+    }
+
+    // A cell with only words ("rate it later") and no number is a placeholder too
+    rpe.missing = rpe.per_set.is_empty();
+    Ok(rpe)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rpe(cell: &str) -> Rpe {
+        parse_rpe(cell).unwrap()
+    }
+
+    #[test]
+    fn parses_values_and_lists() {
+        assert_eq!(rpe("8").per_set, vec![8.0]);
+        assert_eq!(rpe("@6.5").per_set, vec![6.5]);
+        assert_eq!(rpe("7-8").per_set, vec![7.5]);
+        assert_eq!(rpe("5, 6").per_set, vec![5.0, 6.0]);
+    }
+
+    #[test]
+    fn converts_reps_in_reserve() {
+        let rir = rpe("2 RIR");
+        assert!(rir.from_rir);
+        assert_eq!(rir.per_set, vec![8.0]);
+    }
+
+    #[test]
+    fn reads_the_rating_before_a_bracketed_note() {
+        let noted = rpe("8 (2 RIR)");
+        assert!(!noted.from_rir);
+        assert_eq!(noted.per_set, vec![8.0]);
+        assert_eq!(noted.raw, "8 (2 RIR)");
+        assert_eq!(rpe("(2 RIR)").per_set, vec![8.0]);
+    }
+
+    #[test]
+    fn keeps_qualifiers() {
+        let easy = rpe("easy 7");
+        assert_eq!(easy.per_set, vec![7.0]);
+        assert_eq!(easy.qualifier.as_deref(), Some("easy"));
+    }
+
+    #[test]
+    fn marks_placeholders_missing() {
+        for cell in ["rate", "?", "tbd", "rate it later"] {
+            assert!(rpe(cell).missing, "{:?} should be a placeholder", cell);
+        }
+    }
+
+    #[test]
+    fn rejects_values_off_the_scale() {
+        assert!(parse_rpe("11").is_err());
+        assert!(parse_rpe("12 RIR").is_err());
+    }
+}
@@ -0,0 +1,149 @@
+use anyhow::Result;
+use chrono::Utc;
+use google_sheets4::{Sheets, hyper_rustls, hyper};
+use std::str::FromStr;
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::time::Instant;
+use tracing::{info, debug, warn};
+use crate::{
+    cfg::{Cfg, ScheduleConfig},
+    job::{run_with_error_handling, should_run_job},
+};
+
+/// When the next run should happen
+enum Schedule {
+    Interval(Duration),
+    Cron(Box<cron::Schedule>),
+}
+
+impl Schedule {
+    fn from_config(schedule: &ScheduleConfig) -> Result<Self> {
+        if let Some(ref expression) = schedule.cron {
+            let parsed = cron::Schedule::from_str(expression)
+                .map_err(|e| anyhow::anyhow!("Invalid cron expression '{}': {}", expression, e))?;
+            Ok(Schedule::Cron(Box::new(parsed)))
+        } else {
+            Ok(Schedule::Interval(Duration::from_secs(schedule.interval_secs)))
+        }
+    }
+
+    /// Time to wait before the next run, given when the previous run started
+    fn delay_until_next(&self, last_started: Option<Instant>) -> Duration {
+        match self {
+            Schedule::Interval(interval) => match last_started {
+                Some(started) => interval.saturating_sub(started.elapsed()),
+                None => *interval,
+            },
+            Schedule::Cron(schedule) => {
+                let now = Utc::now();
+                match schedule.after(&now).next() {
+                    Some(next) => (next - now).to_std().unwrap_or(Duration::ZERO),
+                    None => {
+                        warn!("Cron schedule has no upcoming runs, falling back to one hour");
+                        Duration::from_secs(3600)
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Run the job repeatedly until SIGINT/SIGTERM is received.
+///
+/// The Sheets hub is created once by the caller and shared across runs. A signal
+/// that arrives while a job is running lets that job finish (and save its state)
+/// before the scheduler exits.
+pub async fn run_scheduler(
+    cfg: &Cfg,
+    hub: &Sheets<hyper_rustls::HttpsConnector<hyper::client::HttpConnector>>,
+) -> Result<()> {
+    let schedule = Schedule::from_config(&cfg.schedule)?;
+
+    match &schedule {
+        Schedule::Interval(interval) => info!("Scheduler started: running every {}s", interval.as_secs()),
+        Schedule::Cron(_) => info!("Scheduler started: cron schedule '{}'", cfg.schedule.cron.as_deref().unwrap_or_default()),
+    }
+
+    let mut shutdown = spawn_shutdown_listener();
+    let mut last_started: Option<Instant> = None;
+    let mut run_count: u64 = 0;
+
+    loop {
+        let skip_wait = last_started.is_none() && cfg.schedule.run_on_start;
+        if !skip_wait {
+            let delay = schedule.delay_until_next(last_started);
+            info!("Next run in {}s", delay.as_secs());
+
+            tokio::select! {
+                _ = tokio::time::sleep(delay) => {}
+                _ = shutdown.changed() => {
+                    info!("Shutdown requested while waiting, stopping scheduler");
+                    break;
+                }
+            }
+        }
+
+        last_started = Some(Instant::now());
+
+        if should_run_job() {
+            run_count += 1;
+            info!("Starting scheduled run #{}", run_count);
+            if let Err(e) = run_with_error_handling(cfg, hub).await {
+                warn!("Scheduled run #{} failed, will retry on next tick: {}", run_count, e);
+            }
+        } else {
+            debug!("Skipping scheduled run");
+        }
+
+        if *shutdown.borrow() {
+            info!("Shutdown requested during run, stopping scheduler");
+            break;
+        }
+    }
+
+    info!("Scheduler stopped after {} run(s)", run_count);
+    Ok(())
+}
+
+/// Spawn a task that flips the returned flag once SIGINT or SIGTERM arrives
+fn spawn_shutdown_listener() -> watch::Receiver<bool> {
+    let (tx, rx) = watch::channel(false);
+
+    tokio::spawn(async move {
+        match wait_for_shutdown_signal().await {
+            Ok(signal_name) => {
+                info!("Received {}, shutting down after the current iteration", signal_name);
+                let _ = tx.send(true);
+            }
+            Err(e) => {
+                warn!("Failed to listen for shutdown signals: {}", e);
+                // Keep the sender alive so the scheduler doesn't treat this as a shutdown
+                std::future::pending::<()>().await;
+                drop(tx);
+            }
+        }
+    });
+
+    rx
+}
+
+#[cfg(unix)]
+async fn wait_for_shutdown_signal() -> Result<&'static str> {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut terminate = signal(SignalKind::terminate())?;
+    tokio::select! {
+        result = tokio::signal::ctrl_c() => {
+            result?;
+            Ok("SIGINT")
+        }
+        _ = terminate.recv() => Ok("SIGTERM"),
+    }
+}
+
+#[cfg(not(unix))]
+async fn wait_for_shutdown_signal() -> Result<&'static str> {
+    tokio::signal::ctrl_c().await?;
+    Ok("Ctrl-C")
+}
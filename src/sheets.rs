@@ -1,7 +1,38 @@
 use anyhow::Result;
-use google_sheets4::{Sheets, hyper_rustls, hyper, api::ValueRange};
+use google_sheets4::{Sheets, hyper_rustls, hyper, api::{BatchUpdateValuesRequest, ValueRange}};
 use regex::Regex;
 use tracing::{info, debug, warn};
+use crate::dates::is_date_header;
+use crate::transform::HEADER_BAND_ROWS;
+
+/// A non-empty row returned by the Sheets API, with its absolute (1-based) sheet row number.
+/// Blank rows are dropped when fetching, so row numbers can have gaps.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SheetRow {
+    pub row_number: usize,
+    pub cells: Vec<String>,
+}
+
+/// Rows fetched for a block: the header band plus the data rows after it
+#[derive(Debug, Clone, Default)]
+pub struct FetchedRows {
+    pub header_rows: Vec<SheetRow>,
+    pub data_rows: Vec<SheetRow>,
+}
+
+impl FetchedRows {
+    /// Highest absolute row number among the data rows
+    pub fn last_data_row(&self) -> Option<usize> {
+        self.data_rows.iter().map(|row| row.row_number).max()
+    }
+    
+    /// Header band followed by data rows, the layout `normalize_block_data` expects
+    pub fn into_rows(self) -> Vec<SheetRow> {
+        let mut rows = self.header_rows;
+        rows.extend(self.data_rows);
+        rows
+    }
+}
 
 /// Detect the optimal column range for a block by analyzing the week structure
 pub async fn detect_block_extent(
@@ -23,7 +54,7 @@ pub async fn detect_block_extent(
     
     match result {
         Ok((_, value_range)) => {
-            let sample_rows = extract_rows_from_response(value_range)?;
+            let sample_rows = extract_rows_from_response(value_range, &sample_range)?;
             
             if sample_rows.is_empty() {
                 anyhow::bail!("No data found in block: {}", block_name);
@@ -49,12 +80,58 @@ pub async fn detect_block_extent(
     }
 }
 
+/// Fetch a range while always including its header band.
+///
+/// `after_row` is the highest absolute sheet row already processed; only data rows below it
+/// are returned, but the header band is always fetched so the parser sees the date/week
+/// headers. With nothing processed yet a single request is made.
+pub async fn fetch_rows_with_header(
+    hub: &Sheets<hyper_rustls::HttpsConnector<hyper::client::HttpConnector>>,
+    sheet_id: &str,
+    range: &str,
+    after_row: usize,
+) -> Result<FetchedRows> {
+    let start_row = range_start_row(range);
+    let header_end = start_row + HEADER_BAND_ROWS - 1;
+    
+    if after_row < start_row {
+        let rows = fetch_rows(hub, sheet_id, range, 0).await?;
+        let (header_rows, data_rows) = rows.into_iter().partition(|row| row.row_number <= header_end);
+        return Ok(FetchedRows { header_rows, data_rows });
+    }
+    
+    let header_range = header_band_range(range)?;
+    debug!("Fetching header band: {}", header_range);
+    let header_rows = fetch_rows(hub, sheet_id, &header_range, 0).await?;
+    
+    // Never let the data window overlap the header band
+    let first_data_row = after_row.max(header_end) + 1;
+    let data_rows = fetch_rows(hub, sheet_id, range, first_data_row - start_row).await?;
+    
+    Ok(FetchedRows { header_rows, data_rows })
+}
+
+/// The first `HEADER_BAND_ROWS` rows of a range (e.g. "Raw!A2:Z" -> "Raw!A2:Z6")
+fn header_band_range(range: &str) -> Result<String> {
+    let (sheet_name, start_row, end_column) = parse_range(range)?;
+    let range_part = range.rsplit_once('!').map(|(_, part)| part).unwrap_or(range);
+    let start_column: String = range_part.chars().take_while(|c| c.is_alphabetic()).collect();
+    let end_row = start_row as usize + HEADER_BAND_ROWS - 1;
+    
+    match end_column {
+        Some(end_column) if !end_column.is_empty() => {
+            Ok(format!("{}!{}{}:{}{}", sheet_name, start_column, start_row, end_column, end_row))
+        }
+        _ => Ok(format!("{}!{}:{}", sheet_name, start_row, end_row)),
+    }
+}
+
 pub async fn fetch_rows(
     hub: &Sheets<hyper_rustls::HttpsConnector<hyper::client::HttpConnector>>,
     sheet_id: &str,
     range: &str,
     start_row: usize,
-) -> Result<Vec<Vec<String>>> {
+) -> Result<Vec<SheetRow>> {
     info!("Fetching rows from sheet {} range {} starting at row {}", sheet_id, range, start_row);
     
     // Construct the actual range with start_row offset
@@ -70,7 +147,7 @@ pub async fn fetch_rows(
     
     match result {
         Ok((_, value_range)) => {
-            let rows = extract_rows_from_response(value_range)?;
+            let rows = extract_rows_from_response(value_range, &adjusted_range)?;
             info!("Successfully fetched {} rows from Google Sheets", rows.len());
             Ok(rows)
         }
@@ -80,9 +157,12 @@ pub async fn fetch_rows(
     }
 }
 
-fn extract_rows_from_response(value_range: ValueRange) -> Result<Vec<Vec<String>>> {
+fn extract_rows_from_response(value_range: ValueRange, requested_range: &str) -> Result<Vec<SheetRow>> {
     let mut rows = Vec::new();
     
+    // Values start at the first row of the range; prefer the range echoed back by the API
+    let first_row = range_start_row(value_range.range.as_deref().unwrap_or(requested_range));
+    
     if let Some(values) = value_range.values {
         for (row_index, row) in values.iter().enumerate() {
             let mut string_row = Vec::new();
@@ -100,12 +180,13 @@ fn extract_rows_from_response(value_range: ValueRange) -> Result<Vec<Vec<String>
             }
             
             // Only include non-empty rows
+            let row_number = first_row + row_index;
             if !string_row.iter().all(|cell| cell.trim().is_empty()) {
                 let column_count = string_row.len();
-                rows.push(string_row);
-                debug!("Row {}: {} columns", row_index + 1, column_count);
+                rows.push(SheetRow { row_number, cells: string_row });
+                debug!("Row {}: {} columns", row_number, column_count);
             } else {
-                debug!("Skipping empty row {}", row_index + 1);
+                debug!("Skipping empty row {}", row_number);
             }
         }
     } else {
@@ -115,6 +196,18 @@ fn extract_rows_from_response(value_range: ValueRange) -> Result<Vec<Vec<String>
     Ok(rows)
 }
 
+/// Absolute row number a range starts at (e.g. "'Block 1'!A3:Z" -> 3), defaulting to 1
+fn range_start_row(range: &str) -> usize {
+    let range_part = range.rsplit_once('!').map(|(_, part)| part).unwrap_or(range);
+    range_part
+        .chars()
+        .skip_while(|c| c.is_alphabetic() || *c == '$')
+        .take_while(|c| c.is_ascii_digit())
+        .collect::<String>()
+        .parse()
+        .unwrap_or(1)
+}
+
 fn adjust_range_for_start_row(range: &str, start_row: usize) -> Result<String> {
     if start_row == 0 {
         return Ok(range.to_string());
@@ -123,7 +216,7 @@ fn adjust_range_for_start_row(range: &str, start_row: usize) -> Result<String> {
     // Parse range like "Raw!A2:Z" and adjust the start row
     // For example, if range is "Raw!A2:Z" and start_row is 5, return "Raw!A7:Z"
     
-    if let Some((sheet_part, range_part)) = range.split_once('!') {
+    if let Some((sheet_part, range_part)) = range.rsplit_once('!') {
         // Split the range part into start and end
         if let Some((start_cell, end_cell)) = range_part.split_once(':') {
             // Extract the starting row number from start_cell (e.g., "A2" -> 2)
@@ -178,7 +271,7 @@ fn adjust_range_for_start_row(range: &str, start_row: usize) -> Result<String> {
 pub fn parse_range(range: &str) -> Result<(String, u32, Option<String>)> {
     // Parse sheet range format like "Raw!A2:Z" into (sheet_name, start_row, end_column)
     
-    if let Some((sheet_part, range_part)) = range.split_once('!') {
+    if let Some((sheet_part, range_part)) = range.rsplit_once('!') {
         let sheet_name = sheet_part.to_string();
         
         if let Some((start_cell, end_cell)) = range_part.split_once(':') {
@@ -210,10 +303,73 @@ pub fn parse_range(range: &str) -> Result<(String, u32, Option<String>)> {
     }
 }
 
+/// A1 address of a cell given its column index within `range` and its absolute row,
+/// e.g. ("Block 3!A1:BZ", 4, 12) -> "Block 3!E12"
+pub fn cell_address(range: &str, column_index: usize, row_number: usize) -> Result<String> {
+    let (sheet_name, _, _) = parse_range(range)?;
+    let range_part = range.rsplit_once('!').map(|(_, part)| part).unwrap_or(range);
+    let start_column = range_part
+        .chars()
+        .take_while(|c| c.is_ascii_alphabetic())
+        .fold(0, |index, c| index * 26 + (c.to_ascii_uppercase() as usize - 'A' as usize + 1));
+    
+    // Bijective base 26: 1 -> A, 26 -> Z, 27 -> AA
+    let mut number = start_column.max(1) + column_index;
+    let mut letters = Vec::new();
+    while number > 0 {
+        let remainder = (number - 1) % 26;
+        letters.push((b'A' + remainder as u8) as char);
+        number = (number - 1) / 26;
+    }
+    let column: String = letters.into_iter().rev().collect();
+    
+    Ok(format!("{}!{}{}", sheet_name, column, row_number))
+}
+
+/// Write single-cell values in one batch request, parsed as if typed into the sheet.
+/// Returns the number of cells updated.
+pub async fn write_cells(
+    hub: &Sheets<hyper_rustls::HttpsConnector<hyper::client::HttpConnector>>,
+    sheet_id: &str,
+    cells: &[(String, String)],
+) -> Result<usize> {
+    if cells.is_empty() {
+        return Ok(0);
+    }
+    
+    let request = BatchUpdateValuesRequest {
+        data: Some(cells.iter().map(|(address, value)| ValueRange {
+            range: Some(address.clone()),
+            values: Some(vec![vec![serde_json::Value::String(value.clone())]]),
+            ..ValueRange::default()
+        }).collect()),
+        value_input_option: Some("USER_ENTERED".to_string()),
+        ..BatchUpdateValuesRequest::default()
+    };
+    
+    let result = hub
+        .spreadsheets()
+        .values_batch_update(request, sheet_id)
+        .doit()
+        .await;
+    
+    match result {
+        Ok((_, response)) => {
+            let updated = response.total_updated_cells.unwrap_or_default() as usize;
+            info!("Wrote {} cells to sheet {}", updated, sheet_id);
+            Ok(updated)
+        }
+        Err(e) => {
+            anyhow::bail!("Failed to write cells to Google Sheets: {}", e);
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct BlockInfo {
     pub name: String,
     pub block_number: u32,
+    pub tab_id: i32,              // Sheets `sheetId`, stable across renames and column changes
 }
 
 /// Discover all block tabs in the spreadsheet by querying sheet metadata
@@ -241,7 +397,7 @@ pub async fn discover_block_tabs(
             if let Some(sheets) = spreadsheet.sheets {
                 for sheet in sheets {
                     if let Some(properties) = sheet.properties {
-                        if let Some(title) = properties.title {
+                        if let (Some(title), Some(tab_id)) = (properties.title, properties.sheet_id) {
                             debug!("Found sheet: '{}'", title);
                             
                             // Check if this sheet matches the Block pattern
@@ -251,9 +407,10 @@ pub async fn discover_block_tabs(
                                         let block_info = BlockInfo {
                                             name: title.clone(),
                                             block_number,
+                                            tab_id,
                                         };
                                         blocks.push(block_info);
-                                        info!("Discovered block: {} (number: {})", title, block_number);
+                                        info!("Discovered block: {} (number: {}, sheetId: {})", title, block_number, tab_id);
                                     }
                                 }
                             } else {
@@ -277,15 +434,15 @@ pub async fn discover_block_tabs(
 }
 
 /// Find the rightmost column that contains week data (date headers or exercise data)
-fn find_rightmost_week_column(sample_rows: &[Vec<String>]) -> Result<usize> {
+fn find_rightmost_week_column(sample_rows: &[SheetRow]) -> Result<usize> {
     let mut max_column = 0;
     
     // Look for date patterns in the first few rows to find week boundaries
-    for (_row_idx, row) in sample_rows.iter().take(5).enumerate() {
-        for (col_idx, cell) in row.iter().enumerate() {
+    for row in sample_rows.iter().take(HEADER_BAND_ROWS) {
+        for (col_idx, cell) in row.cells.iter().enumerate() {
             let trimmed = cell.trim();
             
-            // Check for date headers (like "5/19/2025")
+            // Check for date headers (like "5/19/2025" or "May 19, 2025")
             if is_date_header(trimmed) {
                 debug!("Found date header '{}' at column {}", trimmed, col_idx);
                 max_column = max_column.max(col_idx);
@@ -307,7 +464,7 @@ fn find_rightmost_week_column(sample_rows: &[Vec<String>]) -> Result<usize> {
     
     // Look for the rightmost non-empty data in exercise rows
     for row in sample_rows.iter().skip(3) { // Skip header rows
-        for (col_idx, cell) in row.iter().enumerate() {
+        for (col_idx, cell) in row.cells.iter().enumerate() {
             if !cell.trim().is_empty() && has_workout_data(cell.trim()) {
                 max_column = max_column.max(col_idx);
             }
@@ -323,32 +480,12 @@ fn find_rightmost_week_column(sample_rows: &[Vec<String>]) -> Result<usize> {
     Ok(max_column)
 }
 
-/// Check if a cell contains a date header pattern
-fn is_date_header(cell: &str) -> bool {
-    let trimmed = cell.trim();
-    if trimmed.is_empty() {
-        return false;
-    }
-    
-    // Simple date pattern: M/D/YYYY or MM/DD/YYYY
-    let parts: Vec<&str> = trimmed.split('/').collect();
-    let is_date = parts.len() == 3 && 
-        parts[0].parse::<u32>().is_ok() && 
-        parts[1].parse::<u32>().is_ok() && 
-        parts[2].parse::<u32>().map(|y| y > 2020 && y < 2030).unwrap_or(false);
-    
-    if is_date {
-        debug!("Detected date header: '{}'", trimmed);
-    }
-    
-    is_date
-}
-
-/// Check if a cell looks like a week header (e.g., "week 1", "week 2", "deload")
+/// Check if a cell looks like a week header (e.g., "week 1", "week 2", "deload", "taper")
 fn is_week_header(cell: &str) -> bool {
     let lower = cell.to_lowercase();
     lower.starts_with("week") || lower == "deload" || 
-    lower.contains("week") || lower.contains("phase")
+    lower.contains("week") || lower.contains("phase") ||
+    lower.contains("taper") || lower.contains("peak")
 }
 
 /// Check if a cell looks like an exercise data header
@@ -374,11 +511,11 @@ fn has_workout_data(cell: &str) -> bool {
 }
 
 /// Count the number of weeks detected in the sample
-fn count_weeks_in_sample(sample_rows: &[Vec<String>]) -> usize {
+fn count_weeks_in_sample(sample_rows: &[SheetRow]) -> usize {
     let mut week_count = 0;
     
-    for row in sample_rows.iter().take(5) {
-        for cell in row {
+    for row in sample_rows.iter().take(HEADER_BAND_ROWS) {
+        for cell in &row.cells {
             if is_date_header(cell.trim()) {
                 week_count += 1;
             }
@@ -406,4 +543,45 @@ fn column_number_to_letter(mut col_num: usize) -> String {
     result
 }
 
- 
\ No newline at end of file
+ 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_the_start_row_of_a_range() {
+        assert_eq!(range_start_row("Raw!A2:Z"), 2);
+        assert_eq!(range_start_row("'Block 1'!$B$14:Z"), 14);
+        assert_eq!(range_start_row("'Q1!Log'!C7:Z9"), 7);
+        assert_eq!(range_start_row("Block 1!A:Z"), 1);
+    }
+
+    #[test]
+    fn header_band_covers_the_first_rows() {
+        assert_eq!(header_band_range("Raw!A2:Z").unwrap(), "Raw!A2:Z6");
+        assert_eq!(header_band_range("'Block 1'!B1:AZ200").unwrap(), "'Block 1'!B1:AZ5");
+        assert_eq!(header_band_range("'Q1!Log'!A3:Z").unwrap(), "'Q1!Log'!A3:Z7");
+        assert!(header_band_range("A1:Z").is_err());
+    }
+
+    #[test]
+    fn offsets_the_start_row() {
+        assert_eq!(adjust_range_for_start_row("Raw!A2:Z", 0).unwrap(), "Raw!A2:Z");
+        assert_eq!(adjust_range_for_start_row("Raw!A2:Z", 5).unwrap(), "Raw!A7:Z");
+        assert_eq!(adjust_range_for_start_row("'Block 1'!A1:AZ", 40).unwrap(), "'Block 1'!A41:AZ");
+        assert_eq!(adjust_range_for_start_row("'Q1!Log'!B3", 2).unwrap(), "'Q1!Log'!B5");
+        assert_eq!(adjust_range_for_start_row("A1:Z", 9).unwrap(), "A10:Z");
+    }
+
+    #[test]
+    fn addresses_cells_past_column_z() {
+        assert_eq!(cell_address("Block 3!A1:BZ", 4, 12).unwrap(), "Block 3!E12");
+        assert_eq!(cell_address("Block 3!A1:BZ", 25, 3).unwrap(), "Block 3!Z3");
+        assert_eq!(cell_address("Block 3!A1:BZ", 26, 3).unwrap(), "Block 3!AA3");
+        assert_eq!(cell_address("Block 3!A1:BZ", 51, 3).unwrap(), "Block 3!AZ3");
+        assert_eq!(cell_address("Block 3!A1:BZ", 52, 3).unwrap(), "Block 3!BA3");
+        assert_eq!(cell_address("'Block 1'!C2:ZZ", 24, 8).unwrap(), "'Block 1'!AA8");
+        assert_eq!(cell_address("'Block 1'!AZ2:ZZ", 1, 8).unwrap(), "'Block 1'!BA8");
+        assert_eq!(cell_address("Block 1!A1:ZZ", 702, 1).unwrap(), "Block 1!AAA1");
+    }
+}
@@ -0,0 +1,510 @@
+use anyhow::Result;
+use rusqlite::{params, Connection, Transaction};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use tracing::{debug, info};
+use crate::hashing::slug;
+use crate::transform::{group_by_row, record_id_family, WorkoutRecord};
+
+/// Schema migrations, applied in order. `PRAGMA user_version` holds the number already
+/// applied, so a database is brought up to date the first time a newer binary opens it.
+/// Never edit a released migration; append a new one.
+const MIGRATIONS: &[&str] = &[
+    // 1: blocks, weeks, sessions, exercises, prescriptions and performed sets
+    "
+    CREATE TABLE blocks (
+        id TEXT PRIMARY KEY,
+        name TEXT NOT NULL
+    );
+
+    CREATE TABLE weeks (
+        id TEXT PRIMARY KEY,
+        block_id TEXT NOT NULL REFERENCES blocks(id),
+        week_start_date TEXT NOT NULL,
+        week_number INTEGER NOT NULL,
+        phase TEXT
+    );
+    CREATE INDEX weeks_by_block ON weeks(block_id, week_start_date);
+
+    CREATE TABLE sessions (
+        id TEXT PRIMARY KEY,
+        week_id TEXT NOT NULL REFERENCES weeks(id),
+        day_number INTEGER NOT NULL,
+        workout_date TEXT NOT NULL
+    );
+    CREATE INDEX sessions_by_week ON sessions(week_id);
+    CREATE INDEX sessions_by_date ON sessions(workout_date);
+
+    CREATE TABLE exercises (
+        id TEXT PRIMARY KEY,
+        name TEXT NOT NULL,
+        movement_pattern TEXT NOT NULL,
+        muscle_groups TEXT NOT NULL,
+        equipment TEXT NOT NULL,
+        lift_category TEXT NOT NULL
+    );
+
+    CREATE TABLE prescriptions (
+        id TEXT PRIMARY KEY,
+        session_id TEXT NOT NULL REFERENCES sessions(id),
+        exercise_id TEXT NOT NULL REFERENCES exercises(id),
+        exercise_name TEXT NOT NULL,
+        modifiers TEXT,
+        cue TEXT,
+        sets INTEGER,
+        reps TEXT,
+        reps_min INTEGER,
+        reps_max INTEGER,
+        load REAL,
+        load_unit TEXT,
+        load_instruction TEXT,
+        load_instruction_kind TEXT,
+        load_percent TEXT,
+        resolved_load REAL,
+        rpe TEXT,
+        notes TEXT,
+        content_hash TEXT NOT NULL,
+        processed_at TEXT NOT NULL
+    );
+    CREATE INDEX prescriptions_by_session ON prescriptions(session_id);
+    CREATE INDEX prescriptions_by_exercise ON prescriptions(exercise_id);
+
+    CREATE TABLE performed_sets (
+        id TEXT PRIMARY KEY,
+        session_id TEXT NOT NULL REFERENCES sessions(id),
+        prescription_id TEXT REFERENCES prescriptions(id),
+        exercise_id TEXT NOT NULL REFERENCES exercises(id),
+        exercise_name TEXT NOT NULL,
+        modifiers TEXT,
+        workout_date TEXT NOT NULL,
+        set_number INTEGER,
+        sets INTEGER,
+        reps TEXT,
+        reps_min INTEGER,
+        reps_max INTEGER,
+        load REAL,
+        load_unit TEXT,
+        load_per_side INTEGER,
+        load_bodyweight_relative INTEGER,
+        rpe TEXT,
+        notes TEXT,
+        content_hash TEXT NOT NULL,
+        processed_at TEXT NOT NULL
+    );
+    CREATE INDEX performed_sets_by_session ON performed_sets(session_id);
+    CREATE INDEX performed_sets_by_exercise ON performed_sets(exercise_id, workout_date);
+    ",
+];
+
+/// Outcome of an upsert, for logging
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SqliteStats {
+    pub prescriptions: usize,
+    pub performed_sets: usize,
+    pub pruned: usize,
+}
+
+/// Open the database, creating it and applying any pending migrations
+pub fn open(db_path: &str, ensure_directories: bool) -> Result<Connection> {
+    if let Some(parent) = Path::new(db_path).parent().filter(|p| !p.as_os_str().is_empty()) {
+        if ensure_directories {
+            fs::create_dir_all(parent)?;
+        } else if !parent.exists() {
+            anyhow::bail!("Output directory does not exist: {:?} (set output_csv.ensure = true to create it)", parent);
+        }
+    }
+
+    let mut conn = Connection::open(db_path)?;
+    migrate(&mut conn)?;
+    Ok(conn)
+}
+
+fn migrate(conn: &mut Connection) -> Result<()> {
+    let applied: i64 = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+    let applied = applied.max(0) as usize;
+    if applied > MIGRATIONS.len() {
+        anyhow::bail!("Database schema version {} is newer than this build supports ({})", applied, MIGRATIONS.len());
+    }
+
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(applied) {
+        let tx = conn.transaction()?;
+        tx.execute_batch(migration)?;
+        tx.pragma_update(None, "user_version", (index + 1) as i64)?;
+        tx.commit()?;
+        info!("Applied SQLite schema migration {}", index + 1);
+    }
+    Ok(())
+}
+
+/// ID of a block: its name as a slug, as in record IDs
+pub fn block_id(block_name: &str) -> String {
+    slug(block_name)
+}
+
+/// ID of a block week: the block and the week's start date
+pub fn week_id(record: &WorkoutRecord) -> String {
+    format!("{}_{}", block_id(&record.block_name), record.week_start_date)
+}
+
+/// ID of a training day within a block week
+pub fn session_id(record: &WorkoutRecord) -> String {
+    format!("{}_d{}", week_id(record), record.day_number)
+}
+
+/// Upsert records into the database by their deterministic IDs, in one transaction.
+///
+/// Prescribed records go to `prescriptions` and actual records to `performed_sets`, each
+/// linked to the prescription on its sheet row. Their blocks, weeks, sessions and exercises
+/// are upserted along with them. Rows of an incoming record's ID family that aren't incoming
+/// are deleted, so re-splitting a row's sets leaves no stale rows. With `prune_missing`, rows of a week present in `records`
+/// whose ID is not are deleted, along with sessions left without any.
+pub fn upsert(db_path: &str, records: &[WorkoutRecord], ensure_directories: bool, prune_missing: bool) -> Result<SqliteStats> {
+    info!("Upserting {} records into SQLite database: {}", records.len(), db_path);
+
+    let mut conn = open(db_path, ensure_directories)?;
+    let stats = upsert_records(&mut conn, records, prune_missing)?;
+    info!("Upserted into {}: {} prescriptions, {} performed sets, {} pruned",
+          db_path, stats.prescriptions, stats.performed_sets, stats.pruned);
+
+    Ok(stats)
+}
+
+/// Upsert records into an open, migrated database in one transaction, as for `upsert`
+fn upsert_records(conn: &mut Connection, records: &[WorkoutRecord], prune_missing: bool) -> Result<SqliteStats> {
+    let tx = conn.transaction()?;
+    let mut stats = SqliteStats::default();
+
+    // The prescription on each sheet row, for linking performed sets
+    let prescription_ids: HashMap<&str, &str> = group_by_row(records)
+        .into_iter()
+        .filter_map(|row| Some((row.key, row.prescribed?.id.as_str())))
+        .collect();
+
+    for record in records {
+        upsert_context(&tx, record)?;
+        match record.record_type.as_str() {
+            "prescribed" => {
+                upsert_prescription(&tx, record)?;
+                stats.prescriptions += 1;
+            }
+            "actual" => {
+                upsert_performed_set(&tx, record, prescription_ids.get(record.row_key()).copied())?;
+                stats.performed_sets += 1;
+            }
+            other => debug!("Skipping record {} with record type '{}'", record.id, other),
+        }
+    }
+
+    let ids: HashSet<&str> = records.iter().map(|record| record.id.as_str()).collect();
+    let families: BTreeSet<&str> = records.iter().map(|record| record_id_family(&record.id)).collect();
+    for family in &families {
+        stats.pruned += prune_family(&tx, family, &ids)?;
+    }
+
+    if prune_missing {
+        let weeks: BTreeSet<String> = records.iter().map(week_id).collect();
+        for week in &weeks {
+            stats.pruned += prune_week(&tx, week, &ids)?;
+        }
+    }
+
+    tx.commit()?;
+    Ok(stats)
+}
+
+/// The block, week, session and exercise a record belongs to
+fn upsert_context(tx: &Transaction, record: &WorkoutRecord) -> Result<()> {
+    let mut block = tx.prepare_cached(
+        "INSERT INTO blocks (id, name) VALUES (?1, ?2)
+         ON CONFLICT(id) DO UPDATE SET name = excluded.name",
+    )?;
+    block.execute(params![block_id(&record.block_name), record.block_name])?;
+
+    let mut week = tx.prepare_cached(
+        "INSERT INTO weeks (id, block_id, week_start_date, week_number, phase) VALUES (?1, ?2, ?3, ?4, ?5)
+         ON CONFLICT(id) DO UPDATE SET week_number = excluded.week_number, phase = excluded.phase",
+    )?;
+    week.execute(params![
+        week_id(record),
+        block_id(&record.block_name),
+        record.week_start_date.to_string(),
+        record.week_number,
+        record.phase,
+    ])?;
+
+    // A session's date is the calendar date of its day; a logged "date performed" stays on the set
+    let mut session = tx.prepare_cached(
+        "INSERT INTO sessions (id, week_id, day_number, workout_date) VALUES (?1, ?2, ?3, ?4)
+         ON CONFLICT(id) DO UPDATE SET workout_date = CASE WHEN ?5 THEN excluded.workout_date ELSE workout_date END",
+    )?;
+    session.execute(params![
+        session_id(record),
+        week_id(record),
+        record.day_number,
+        record.workout_date.to_string(),
+        record.record_type == "prescribed",
+    ])?;
+
+    let mut exercise = tx.prepare_cached(
+        "INSERT INTO exercises (id, name, movement_pattern, muscle_groups, equipment, lift_category)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+         ON CONFLICT(id) DO UPDATE SET name = excluded.name, movement_pattern = excluded.movement_pattern,
+             muscle_groups = excluded.muscle_groups, equipment = excluded.equipment,
+             lift_category = excluded.lift_category",
+    )?;
+    exercise.execute(params![
+        record.exercise_id,
+        record.exercise_canonical,
+        record.taxonomy.movement_pattern.as_str(),
+        record.taxonomy.muscle_groups.join(";"),
+        record.taxonomy.equipment.as_str(),
+        record.taxonomy.lift_category.as_str(),
+    ])?;
+
+    Ok(())
+}
+
+fn upsert_prescription(tx: &Transaction, record: &WorkoutRecord) -> Result<()> {
+    let mut statement = tx.prepare_cached(
+        "INSERT INTO prescriptions (id, session_id, exercise_id, exercise_name, modifiers, cue, sets, reps,
+             reps_min, reps_max, load, load_unit, load_instruction, load_instruction_kind, load_percent,
+             resolved_load, rpe, notes, content_hash, processed_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20)
+         ON CONFLICT(id) DO UPDATE SET session_id = excluded.session_id, exercise_id = excluded.exercise_id,
+             exercise_name = excluded.exercise_name, modifiers = excluded.modifiers, cue = excluded.cue,
+             sets = excluded.sets, reps = excluded.reps, reps_min = excluded.reps_min,
+             reps_max = excluded.reps_max, load = excluded.load, load_unit = excluded.load_unit,
+             load_instruction = excluded.load_instruction,
+             load_instruction_kind = excluded.load_instruction_kind, load_percent = excluded.load_percent,
+             resolved_load = excluded.resolved_load, rpe = excluded.rpe, notes = excluded.notes,
+             content_hash = excluded.content_hash, processed_at = excluded.processed_at",
+    )?;
+    let reps = record.reps.as_ref();
+    let instruction = record.load_instruction_kind.as_ref();
+    statement.execute(params![
+        record.id,
+        session_id(record),
+        record.exercise_id,
+        record.exercise_name,
+        non_empty(record.modifiers.join(";")),
+        record.cue,
+        record.set_count(),
+        reps.map(|r| r.raw.clone()),
+        reps.and_then(|r| r.min),
+        reps.and_then(|r| r.max),
+        record.load.as_ref().map(|l| l.value),
+        record.load.as_ref().map(|l| l.unit.to_string()),
+        record.load_instruction,
+        instruction.map(|i| i.kind()),
+        instruction.and_then(|i| i.percent_label()),
+        record.resolved_load,
+        record.rpe.as_ref().and_then(|r| non_empty(r.values_label())),
+        record.notes,
+        record.content_hash(),
+        record.processed_at.to_rfc3339(),
+    ])?;
+    Ok(())
+}
+
+fn upsert_performed_set(tx: &Transaction, record: &WorkoutRecord, prescription_id: Option<&str>) -> Result<()> {
+    let mut statement = tx.prepare_cached(
+        "INSERT INTO performed_sets (id, session_id, prescription_id, exercise_id, exercise_name, modifiers,
+             workout_date, set_number, sets, reps, reps_min, reps_max, load, load_unit, load_per_side,
+             load_bodyweight_relative, rpe, notes, content_hash, processed_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20)
+         ON CONFLICT(id) DO UPDATE SET session_id = excluded.session_id,
+             prescription_id = excluded.prescription_id, exercise_id = excluded.exercise_id,
+             exercise_name = excluded.exercise_name, modifiers = excluded.modifiers,
+             workout_date = excluded.workout_date, set_number = excluded.set_number, sets = excluded.sets,
+             reps = excluded.reps, reps_min = excluded.reps_min, reps_max = excluded.reps_max,
+             load = excluded.load, load_unit = excluded.load_unit, load_per_side = excluded.load_per_side,
+             load_bodyweight_relative = excluded.load_bodyweight_relative, rpe = excluded.rpe,
+             notes = excluded.notes, content_hash = excluded.content_hash, processed_at = excluded.processed_at",
+    )?;
+    let reps = record.reps.as_ref();
+    let load = record.load.as_ref();
+    statement.execute(params![
+        record.id,
+        session_id(record),
+        prescription_id,
+        record.exercise_id,
+        record.exercise_name,
+        non_empty(record.modifiers.join(";")),
+        record.workout_date.to_string(),
+        record.set_number,
+        record.set_count(),
+        reps.map(|r| if r.per_set.is_empty() { r.raw.clone() } else { r.per_set_label() }),
+        reps.and_then(|r| r.min),
+        reps.and_then(|r| r.max),
+        load.map(|l| l.value),
+        load.map(|l| l.unit.to_string()),
+        load.map(|l| l.per_side),
+        load.map(|l| l.bodyweight_relative),
+        record.rpe.as_ref().and_then(|r| non_empty(r.values_label())),
+        record.notes,
+        record.content_hash(),
+        record.processed_at.to_rfc3339(),
+    ])?;
+    Ok(())
+}
+
+/// Delete the rows of an ID family (the ID with or without a per-set suffix) that aren't in
+/// `ids`, so an actual split into per-set rows doesn't leave its single row behind
+fn prune_family(tx: &Transaction, family: &str, ids: &HashSet<&str>) -> Result<usize> {
+    let mut pruned = 0;
+    for table in ["performed_sets", "prescriptions"] {
+        let stale: Vec<String> = tx
+            .prepare_cached(&format!("SELECT id FROM {} WHERE id = ?1 OR id GLOB ?1 || '_s[0-9]*'", table))?
+            .query_map([family], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<String>>>()?
+            .into_iter()
+            .filter(|id| !ids.contains(id.as_str()) && record_id_family(id) == family)
+            .collect();
+        for id in &stale {
+            pruned += tx.execute(&format!("DELETE FROM {} WHERE id = ?1", table), [id])?;
+        }
+    }
+    Ok(pruned)
+}
+
+/// Delete a week's prescriptions and performed sets that aren't in `ids`, then its sessions
+/// with nothing left in them. Returns the number of rows deleted.
+fn prune_week(tx: &Transaction, week_id: &str, ids: &HashSet<&str>) -> Result<usize> {
+    let mut pruned = 0;
+    // Performed sets first: they reference prescriptions, and foreign keys are enforced
+    for table in ["performed_sets", "prescriptions"] {
+        let stale: Vec<String> = tx
+            .prepare(&format!(
+                "SELECT t.id FROM {} t JOIN sessions s ON s.id = t.session_id WHERE s.week_id = ?1",
+                table
+            ))?
+            .query_map([week_id], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<String>>>()?
+            .into_iter()
+            .filter(|id| !ids.contains(id.as_str()))
+            .collect();
+        for id in &stale {
+            pruned += tx.execute(&format!("DELETE FROM {} WHERE id = ?1", table), [id])?;
+        }
+    }
+
+    pruned += tx.execute(
+        "DELETE FROM sessions WHERE week_id = ?1
+             AND id NOT IN (SELECT session_id FROM prescriptions)
+             AND id NOT IN (SELECT session_id FROM performed_sets)",
+        [week_id],
+    )?;
+    Ok(pruned)
+}
+
+fn non_empty(value: String) -> Option<String> {
+    (!value.is_empty()).then_some(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sheets::SheetRow;
+    use crate::transform::{normalize_block_data, ParseOptions};
+
+    fn row(row_number: usize, cells: &[&str]) -> SheetRow {
+        SheetRow { row_number, cells: cells.iter().map(|cell| cell.to_string()).collect() }
+    }
+
+    /// Day 1 has a squat logged as `squat_reps` and a bench; Day 2 has a deadlift
+    fn records(squat_reps: &str) -> Vec<WorkoutRecord> {
+        let rows = vec![
+            row(1, &["", "", "5/19/2025"]),
+            row(2, &["", "", "Week 1"]),
+            row(6, &["", "DAY 1"]),
+            row(7, &["", "Squat", "", "3", "5", "300", "8", "", "300", "3", squat_reps, "8"]),
+            row(8, &["", "Bench", "", "3", "8", "200", "7", "", "200", "3", "8", "7"]),
+            row(9, &["", "DAY 2"]),
+            row(10, &["", "Deadlift", "", "3", "5", "400", "8"]),
+        ];
+        normalize_block_data(rows, "Block 1", &ParseOptions::default()).unwrap().records
+    }
+
+    fn database() -> Connection {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrate(&mut conn).unwrap();
+        conn
+    }
+
+    fn count(conn: &Connection, table: &str) -> usize {
+        conn.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| row.get(0)).unwrap()
+    }
+
+    fn squat_ids(conn: &Connection) -> Vec<String> {
+        let mut statement = conn.prepare("SELECT id FROM performed_sets WHERE exercise_name = 'Squat' ORDER BY id").unwrap();
+        statement.query_map([], |row| row.get(0)).unwrap().collect::<rusqlite::Result<_>>().unwrap()
+    }
+
+    #[test]
+    fn migrates_once() {
+        let mut conn = database();
+        migrate(&mut conn).unwrap();
+        let version: i64 = conn.pragma_query_value(None, "user_version", |row| row.get(0)).unwrap();
+        assert_eq!(version, MIGRATIONS.len() as i64);
+        assert_eq!(count(&conn, "prescriptions"), 0);
+
+        conn.pragma_update(None, "user_version", MIGRATIONS.len() as i64 + 1).unwrap();
+        assert!(migrate(&mut conn).is_err());
+    }
+
+    #[test]
+    fn upserts_in_place_and_links_sets_to_their_prescription() {
+        let mut conn = database();
+        let stats = upsert_records(&mut conn, &records("5"), false).unwrap();
+        assert_eq!((stats.prescriptions, stats.performed_sets, stats.pruned), (3, 2, 0));
+
+        let mut edited = records("5");
+        for record in edited.iter_mut().filter(|r| r.exercise_canonical == "Bench") {
+            record.notes = Some("elbow".to_string());
+        }
+        upsert_records(&mut conn, &edited, false).unwrap();
+
+        assert_eq!((count(&conn, "prescriptions"), count(&conn, "performed_sets"), count(&conn, "sessions")), (3, 2, 2));
+        let (notes, linked): (String, bool) = conn
+            .query_row(
+                "SELECT p.notes, EXISTS(SELECT 1 FROM performed_sets s WHERE s.prescription_id = p.id)
+                 FROM prescriptions p WHERE p.exercise_name = 'Bench'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!((notes.as_str(), linked), ("elbow", true));
+    }
+
+    #[test]
+    fn prunes_a_rows_other_set_splits() {
+        let mut conn = database();
+        upsert_records(&mut conn, &records("5"), false).unwrap();
+        let single = squat_ids(&conn);
+
+        let stats = upsert_records(&mut conn, &records("5,5,4"), false).unwrap();
+        assert_eq!(stats.pruned, 1);
+        let split = squat_ids(&conn);
+        assert_eq!(split.len(), 3);
+        assert!(split.iter().all(|id| record_id_family(id) == single[0]));
+
+        let stats = upsert_records(&mut conn, &records("5"), false).unwrap();
+        assert_eq!(stats.pruned, 3);
+        assert_eq!(squat_ids(&conn), single);
+    }
+
+    #[test]
+    fn prunes_missing_rows_and_empty_sessions_of_incoming_weeks() {
+        let mut conn = database();
+        upsert_records(&mut conn, &records("5"), false).unwrap();
+        let squat: Vec<WorkoutRecord> = records("5").into_iter().filter(|r| r.exercise_canonical == "Squat").collect();
+
+        assert_eq!(upsert_records(&mut conn, &squat, false).unwrap().pruned, 0);
+
+        // Bench's prescription and set, the deadlift's prescription and the emptied Day 2
+        let stats = upsert_records(&mut conn, &squat, true).unwrap();
+        assert_eq!(stats.pruned, 4);
+        assert_eq!((count(&conn, "prescriptions"), count(&conn, "performed_sets"), count(&conn, "sessions")), (1, 1, 1));
+    }
+}
@@ -1,35 +1,94 @@
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use chrono::NaiveDate;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
+use std::io::Write;
 use std::path::Path;
-use tracing::{info, debug};
+use tracing::{info, debug, warn};
+use crate::compliance::ComplianceHistory;
+use crate::fatigue::SessionHistory;
+use crate::progression::{E1rmHistory, UpcomingHistory};
+use crate::prs::ExerciseRecords;
+use crate::sheets::BlockInfo;
+use crate::volume::VolumeHistory;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct State {
-    // Legacy single range support
-    pub last_processed_row: usize,
+    // Legacy single range support: highest absolute sheet row processed
+    #[serde(alias = "last_processed_row")]
+    pub last_row_seen: usize,
     pub last_updated: chrono::DateTime<chrono::Utc>,
     pub total_processed: usize,
     
-    // Multi-block support: track last processed row per block
+    // Multi-block support: track progress and content hashes per block,
+    // keyed by the tab's Sheets sheetId so range changes don't orphan entries
+    #[serde(default)]
+    pub blocks: HashMap<i32, BlockState>,
+    
+    // Pre-sheetId format keyed by range string; migrated by `migrate_range_keys`
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
     pub block_states: HashMap<String, BlockState>,
+    
+    // Personal records keyed by canonical exercise ID
+    #[serde(default)]
+    pub prs: HashMap<String, ExerciseRecords>,
+    
+    // Weekly training volume per block, the source of the volume reports
+    #[serde(default)]
+    pub volume: VolumeHistory,
+    
+    // Daily RPE and set counts per block week, the source of the fatigue report
+    #[serde(default)]
+    pub sessions: SessionHistory,
+    
+    // Prescriptions paired with their actuals per block week, re-checked against today every run
+    #[serde(default)]
+    pub compliance: ComplianceHistory,
+    
+    // Recent e1RM estimates and unlogged prescriptions, the inputs of load suggestions
+    #[serde(default)]
+    pub e1rms: E1rmHistory,
+    #[serde(default)]
+    pub upcoming: UpcomingHistory,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct BlockState {
-    pub last_processed_row: usize,
+    // Tab title and the range fetched on the last run, for reference only
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub last_range: String,
+    
+    // Highest absolute sheet row seen in the block
+    #[serde(alias = "last_processed_row")]
+    pub last_row_seen: usize,
     pub total_processed: usize,
     pub last_updated: chrono::DateTime<chrono::Utc>,
+    
+    // Hash of every cell in the block as of the last run
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_hash: Option<String>,
+    // Hash of each week's column group, keyed by the week's start date header
+    #[serde(default)]
+    pub week_hashes: HashMap<String, String>,
 }
 
 impl Default for State {
     fn default() -> Self {
         Self {
-            last_processed_row: 0,
+            last_row_seen: 0,
             last_updated: chrono::Utc::now(),
             total_processed: 0,
+            blocks: HashMap::new(),
             block_states: HashMap::new(),
+            prs: HashMap::new(),
+            volume: VolumeHistory::new(),
+            sessions: SessionHistory::new(),
+            compliance: ComplianceHistory::new(),
+            e1rms: E1rmHistory::new(),
+            upcoming: UpcomingHistory::new(),
         }
     }
 }
@@ -39,43 +98,131 @@ impl State {
         Self::default()
     }
     
-    pub fn update_processed(&mut self, new_row_count: usize) {
-        self.last_processed_row += new_row_count;
+    /// Record that sheet rows up to `last_row_seen` (absolute row number) are done
+    pub fn update_processed(&mut self, last_row_seen: usize, new_row_count: usize) {
+        self.last_row_seen = self.last_row_seen.max(last_row_seen);
         self.total_processed += new_row_count;
         self.last_updated = chrono::Utc::now();
     }
     
-    pub fn get_block_state(&self, block_range: &str) -> BlockState {
-        self.block_states.get(block_range).cloned().unwrap_or_else(|| {
-            BlockState {
-                last_processed_row: 0,
-                total_processed: 0,
-                last_updated: chrono::Utc::now(),
+    /// Move entries keyed by range string (e.g. "Block 3!A1:AZ") onto their tab's sheetId.
+    ///
+    /// When several ranges map to the same tab (the range changed between runs), the most
+    /// recently updated entry wins. Entries with no matching tab are kept for a later run.
+    /// Returns the number of tabs migrated.
+    pub fn migrate_range_keys(&mut self, blocks: &[BlockInfo]) -> usize {
+        if self.block_states.is_empty() {
+            return 0;
+        }
+        
+        let mut migrated = HashSet::new();
+        let mut unmatched = HashMap::new();
+        
+        for (range, mut block_state) in self.block_states.drain() {
+            let title = range.split('!').next().unwrap_or(&range);
+            let Some(block) = blocks.iter().find(|b| b.name == title) else {
+                unmatched.insert(range, block_state);
+                continue;
+            };
+            
+            block_state.title = block.name.clone();
+            block_state.last_range = range.clone();
+            
+            match self.blocks.get(&block.tab_id) {
+                Some(existing) if existing.last_updated >= block_state.last_updated => {
+                    debug!("Dropping older state entry for {} ({})", block.name, range);
+                }
+                _ => {
+                    debug!("Migrated state for {} from range key '{}' to sheetId {}", block.name, range, block.tab_id);
+                    self.blocks.insert(block.tab_id, block_state);
+                    migrated.insert(block.tab_id);
+                }
             }
-        })
+        }
+        
+        if !unmatched.is_empty() {
+            warn!("Kept {} state entries that match no discovered tab: {:?}", 
+                  unmatched.len(), unmatched.keys().collect::<Vec<_>>());
+        }
+        self.block_states = unmatched;
+        
+        migrated.len()
+    }
+    
+    /// Drop the stored volume, sessions, compliance pairs and upcoming prescriptions of
+    /// re-normalized weeks that no longer produce any records
+    pub fn clear_weeks(&mut self, weeks: &[(String, NaiveDate)]) {
+        for (block_name, week_start) in weeks {
+            remove_week(&mut self.volume, block_name, *week_start);
+            remove_week(&mut self.sessions, block_name, *week_start);
+            remove_week(&mut self.compliance, block_name, *week_start);
+            remove_week(&mut self.upcoming, block_name, *week_start);
+        }
+    }
+    
+    /// True when the block's content hash matches the one stored by the last run
+    pub fn is_block_unchanged(&self, tab_id: i32, content_hash: &str) -> bool {
+        self.blocks
+            .get(&tab_id)
+            .and_then(|block| block.content_hash.as_deref())
+            == Some(content_hash)
+    }
+    
+    /// Weeks whose hash differs from the stored one (including weeks never seen before)
+    pub fn changed_weeks(&self, tab_id: i32, week_hashes: &[(String, String)]) -> HashSet<String> {
+        let stored = self.blocks.get(&tab_id).map(|block| &block.week_hashes);
+        
+        week_hashes
+            .iter()
+            .filter(|(week_key, hash)| stored.and_then(|weeks| weeks.get(week_key)) != Some(hash))
+            .map(|(week_key, _)| week_key.clone())
+            .collect()
     }
     
-    pub fn update_block_state(&mut self, block_range: &str, new_row_count: usize) {
+    /// Record the block's current content after its changed weeks were re-normalized
+    pub fn update_block_content(
+        &mut self,
+        block: &BlockInfo,
+        block_range: &str,
+        last_row_seen: usize,
+        emitted_records: usize,
+        content_hash: String,
+        week_hashes: Vec<(String, String)>,
+    ) {
         let now = chrono::Utc::now();
-        let block_state = self.block_states.entry(block_range.to_string()).or_insert_with(|| {
+        let block_state = self.blocks.entry(block.tab_id).or_insert_with(|| {
             BlockState {
-                last_processed_row: 0,
+                title: String::new(),
+                last_range: String::new(),
+                last_row_seen: 0,
                 total_processed: 0,
                 last_updated: now,
+                content_hash: None,
+                week_hashes: HashMap::new(),
             }
         });
         
-        block_state.last_processed_row += new_row_count;
-        block_state.total_processed += new_row_count;
+        block_state.title = block.name.clone();
+        block_state.last_range = block_range.to_string();
+        block_state.last_row_seen = last_row_seen;
+        block_state.total_processed += emitted_records;
         block_state.last_updated = now;
+        block_state.content_hash = Some(content_hash);
+        block_state.week_hashes = week_hashes.into_iter().collect();
         
         // Also update global counters
-        self.total_processed += new_row_count;
+        self.total_processed += emitted_records;
         self.last_updated = now;
     }
-    
-    pub fn get_next_row_for_block(&self, block_range: &str) -> usize {
-        self.get_block_state(block_range).last_processed_row + 1
+}
+
+/// Remove one week from a per-block weekly history, and the block once it has no weeks left
+fn remove_week<T>(history: &mut BTreeMap<String, BTreeMap<NaiveDate, T>>, block_name: &str, week_start: NaiveDate) {
+    if let Some(weeks) = history.get_mut(block_name) {
+        weeks.remove(&week_start);
+        if weeks.is_empty() {
+            history.remove(block_name);
+        }
     }
 }
 
@@ -92,8 +239,8 @@ pub fn load_state(state_path: &str) -> Result<State> {
     let state: State = serde_json::from_str(&content)
         .map_err(|e| anyhow::anyhow!("Failed to parse state file: {}", e))?;
     
-    info!("Loaded state: last_processed_row={}, total_processed={}", 
-          state.last_processed_row, state.total_processed);
+    info!("Loaded state: last_row_seen={}, total_processed={}", 
+          state.last_row_seen, state.total_processed);
     
     Ok(state)
 }
@@ -106,11 +253,24 @@ pub fn save_state(state_path: &str, state: &State) -> Result<()> {
         fs::create_dir_all(parent)?;
     }
     
+    // Write a temp file and rename it over the old state, so a crash mid-write can't lose
+    // the block hashes and history
+    let path = Path::new(state_path);
+    let file_name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| anyhow::anyhow!("Invalid state path: {}", state_path))?;
+    let temp_path = path.with_file_name(format!(".{}.tmp", file_name));
     let json = serde_json::to_string_pretty(state)?;
-    fs::write(state_path, json)?;
+    {
+        let mut file = fs::File::create(&temp_path)?;
+        file.write_all(json.as_bytes())?;
+        file.sync_all()?;
+    }
+    fs::rename(&temp_path, path)?;
     
-    info!("Saved state: last_processed_row={}, total_processed={}", 
-          state.last_processed_row, state.total_processed);
+    info!("Saved state: last_row_seen={}, total_processed={}", 
+          state.last_row_seen, state.total_processed);
     
     Ok(())
 }
@@ -126,4 +286,128 @@ pub fn backup_state(state_path: &str) -> Result<()> {
     }
     
     Ok(())
-} 
\ No newline at end of file
+} 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block(name: &str, tab_id: i32) -> BlockInfo {
+        BlockInfo { name: name.to_string(), block_number: 1, tab_id }
+    }
+
+    fn hashes(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs.iter().map(|(week, hash)| (week.to_string(), hash.to_string())).collect()
+    }
+
+    #[test]
+    fn migrates_range_keys_onto_sheet_ids() {
+        let json = r#"{
+            "last_processed_row": 0,
+            "last_updated": "2025-06-01T00:00:00Z",
+            "total_processed": 30,
+            "block_states": {
+                "Block 1!A1:Z": {"last_processed_row": 40, "total_processed": 20, "last_updated": "2025-05-01T00:00:00Z"},
+                "Block 1!A1:AB": {"last_processed_row": 44, "total_processed": 24, "last_updated": "2025-05-08T00:00:00Z"},
+                "Block 9!A1:Z": {"last_processed_row": 10, "total_processed": 10, "last_updated": "2025-05-01T00:00:00Z"}
+            }
+        }"#;
+        let mut state: State = serde_json::from_str(json).unwrap();
+        let blocks = [block("Block 1", 7), block("Block 2", 8)];
+
+        assert_eq!(state.migrate_range_keys(&blocks), 1);
+        let migrated = &state.blocks[&7];
+        assert_eq!((migrated.title.as_str(), migrated.last_range.as_str()), ("Block 1", "Block 1!A1:AB"));
+        assert_eq!(migrated.last_row_seen, 44);
+
+        // Unmatched entries are kept for a later run; nothing is migrated twice
+        assert_eq!(state.block_states.keys().collect::<Vec<_>>(), vec!["Block 9!A1:Z"]);
+        assert_eq!(state.migrate_range_keys(&blocks), 0);
+        assert_eq!(state.blocks.len(), 1);
+
+        // A migrated entry doesn't replace one already stored under the sheetId
+        state.blocks.get_mut(&7).unwrap().last_updated = chrono::Utc::now();
+        state.block_states.insert("Block 1!A1:Z".to_string(), stale_entry());
+        assert_eq!(state.migrate_range_keys(&blocks), 0);
+        assert_eq!(state.blocks[&7].last_row_seen, 44);
+    }
+
+    fn stale_entry() -> BlockState {
+        BlockState {
+            title: String::new(),
+            last_range: String::new(),
+            last_row_seen: 99,
+            total_processed: 0,
+            last_updated: chrono::DateTime::UNIX_EPOCH,
+            content_hash: None,
+            week_hashes: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn only_edited_and_new_weeks_change() {
+        let mut state = State::new();
+        let block = block("Block 1", 7);
+        let first = hashes(&[("5/19/2025", "a"), ("5/26/2025", "b")]);
+        assert_eq!(state.changed_weeks(7, &first).len(), 2);
+
+        state.update_block_content(&block, "Block 1!A1:Z40", 40, 12, "block".to_string(), first);
+        assert!(state.is_block_unchanged(7, "block"));
+        assert!(!state.is_block_unchanged(7, "edited"));
+        assert!(!state.is_block_unchanged(8, "block"));
+
+        let second = hashes(&[("5/19/2025", "a"), ("5/26/2025", "c"), ("6/2/2025", "d")]);
+        let changed = state.changed_weeks(7, &second);
+        assert_eq!(changed, HashSet::from(["5/26/2025".to_string(), "6/2/2025".to_string()]));
+    }
+
+    #[test]
+    fn update_block_content_replaces_hashes_and_adds_counts() {
+        let mut state = State::new();
+        let block = block("Block 1", 7);
+        state.update_block_content(&block, "Block 1!A1:Z40", 40, 12, "one".to_string(), hashes(&[("5/19/2025", "a")]));
+        state.update_block_content(&block, "Block 1!A1:AB44", 44, 3, "two".to_string(), hashes(&[("5/26/2025", "b")]));
+
+        let stored = &state.blocks[&7];
+        assert_eq!((stored.last_range.as_str(), stored.last_row_seen, stored.total_processed), ("Block 1!A1:AB44", 44, 15));
+        assert_eq!(stored.content_hash.as_deref(), Some("two"));
+        assert_eq!(stored.week_hashes, HashMap::from([("5/26/2025".to_string(), "b".to_string())]));
+        assert_eq!(state.total_processed, 15);
+    }
+
+    #[test]
+    fn saves_atomically_and_loads_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state").join("state.json");
+        let path = path.to_str().unwrap();
+        assert_eq!(load_state(path).unwrap().total_processed, 0);
+
+        let mut state = State::new();
+        state.update_block_content(&block("Block 1", 7), "Block 1!A1:Z40", 40, 12, "block".to_string(), hashes(&[("5/19/2025", "a")]));
+        save_state(path, &state).unwrap();
+        save_state(path, &state).unwrap();
+
+        let files: Vec<_> = fs::read_dir(dir.path().join("state")).unwrap().map(|entry| entry.unwrap().file_name()).collect();
+        assert_eq!(files, vec!["state.json"]);
+        let loaded = load_state(path).unwrap();
+        assert!(loaded.is_block_unchanged(7, "block"));
+        assert_eq!(loaded.blocks[&7].week_hashes["5/19/2025"], "a");
+    }
+
+    #[test]
+    fn clears_emptied_weeks_from_every_history() {
+        let week = |day| NaiveDate::from_ymd_opt(2025, 5, day).unwrap();
+        let mut state = State::new();
+        for day in [19, 26] {
+            state.volume.entry("Block 1".to_string()).or_default().insert(week(day), Default::default());
+            state.sessions.entry("Block 1".to_string()).or_default().insert(week(day), Default::default());
+        }
+        state.compliance.entry("Block 2".to_string()).or_default().insert(week(19), Vec::new());
+
+        state.clear_weeks(&[("Block 1".to_string(), week(26)), ("Block 2".to_string(), week(19))]);
+
+        assert_eq!(state.volume["Block 1"].keys().collect::<Vec<_>>(), vec![&week(19)]);
+        assert_eq!(state.sessions["Block 1"].keys().collect::<Vec<_>>(), vec![&week(19)]);
+        assert!(state.compliance.is_empty());
+    }
+}
@@ -0,0 +1,124 @@
+use crate::transform::WorkoutRecord;
+
+/// Highest rep count an estimate is made from; beyond this the formulas drift badly
+pub const MAX_ESTIMATE_REPS: u32 = 12;
+
+/// Estimated one-rep maxes for a set
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct E1rm {
+    pub epley: f64,
+    pub brzycki: f64,
+    /// From the RPE percentage table; None without an RPE in the table's range
+    pub rpe: Option<f64>,
+}
+
+impl E1rm {
+    /// The estimate used for PRs: the RPE table when an RPE was recorded, otherwise Epley
+    pub fn preferred(&self) -> f64 {
+        self.rpe.unwrap_or(self.epley)
+    }
+}
+
+/// Epley: weight x (1 + reps / 30); a single is the weight itself
+pub fn epley(weight: f64, reps: u32) -> f64 {
+    if reps <= 1 {
+        weight
+    } else {
+        weight * (1.0 + reps as f64 / 30.0)
+    }
+}
+
+/// Brzycki: weight x 36 / (37 - reps)
+pub fn brzycki(weight: f64, reps: u32) -> f64 {
+    weight * 36.0 / (37.0 - reps as f64)
+}
+
+/// Percentage of 1RM by "reps + reps in reserve", in half-rep steps from 1 to 16.
+/// This is the usual RPE chart: 5 reps @ RPE 8 reads the same as 7 reps @ RPE 10.
+const RPE_PERCENTAGES: [f64; 31] = [
+    100.0, 97.8, 95.5, 93.9, 92.2, 90.7, 89.2, 87.8, 86.3, 85.0, 83.7, 82.4, 81.1, 79.9, 78.6, 77.4,
+    76.2, 75.1, 73.9, 72.3, 70.7, 69.4, 68.0, 66.7, 65.3, 64.0, 62.6, 61.3, 59.9, 58.6, 57.4,
+];
+
+/// Percentage of 1RM for `reps` at `rpe` (6-10), if the chart covers it
+pub fn rpe_percentage(reps: u32, rpe: f64) -> Option<f64> {
+    if !(6.0..=10.0).contains(&rpe) || reps == 0 || reps > MAX_ESTIMATE_REPS {
+        return None;
+    }
+    let index = ((reps as f64 + 10.0 - rpe - 1.0) * 2.0).round() as usize;
+    RPE_PERCENTAGES.get(index).copied()
+}
+
+/// Estimate the 1RM of an actual record's top set.
+///
+/// Needs a load that isn't bodyweight-relative and a rep count of 1 to `MAX_ESTIMATE_REPS`.
+/// When a record covers several sets, the heaviest load is paired with the fewest reps and
+/// the highest RPE, so the estimate never overstates what was lifted.
+pub fn estimate(record: &WorkoutRecord) -> Option<E1rm> {
+    if record.record_type != "actual" {
+        return None;
+    }
+    let load = record.load.as_ref().filter(|load| !load.bodyweight_relative && load.value > 0.0)?;
+    let reps = record.reps.as_ref().filter(|reps| reps.seconds.is_none() && !reps.to_rpe)?.min?;
+    if reps == 0 || reps > MAX_ESTIMATE_REPS {
+        return None;
+    }
+
+    let rpe = record
+        .rpe
+        .as_ref()
+        .and_then(|rpe| rpe.per_set.iter().copied().reduce(f64::max));
+
+    Some(E1rm {
+        epley: round_to(epley(load.value, reps), 0.1),
+        brzycki: round_to(brzycki(load.value, reps), 0.1),
+        rpe: rpe.and_then(|rpe| rpe_percentage(reps, rpe)).map(|percentage| round_to(load.value * 100.0 / percentage, 0.1)),
+    })
+}
+
+/// Round `value` to the nearest multiple of `step`: a plate increment for loads, or 0.1 or
+/// 0.01 for reported figures. Steps that divide 1 scale up instead of dividing, so 0.1 gives
+/// 0.3 rather than 0.30000000000000004. A step of zero or less leaves the value as is.
+pub fn round_to(value: f64, step: f64) -> f64 {
+    if step <= 0.0 {
+        return value;
+    }
+    let scale = (1.0 / step).round();
+    if step < 1.0 && (scale * step - 1.0).abs() < 1e-9 {
+        (value * scale).round() / scale
+    } else {
+        (value / step).round() * step
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn epley_and_brzycki() {
+        assert_eq!(epley(300.0, 1), 300.0);
+        assert_eq!(epley(300.0, 5), 350.0);
+        assert_eq!(brzycki(300.0, 1), 300.0);
+        assert!((brzycki(300.0, 10) - 400.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn reads_the_rpe_chart() {
+        assert_eq!(rpe_percentage(1, 10.0), Some(100.0));
+        assert_eq!(rpe_percentage(5, 8.0), rpe_percentage(7, 10.0));
+        assert_eq!(rpe_percentage(3, 8.5), Some(87.8));
+        assert_eq!(rpe_percentage(5, 5.0), None);
+        assert_eq!(rpe_percentage(0, 8.0), None);
+        assert_eq!(rpe_percentage(MAX_ESTIMATE_REPS + 1, 8.0), None);
+    }
+
+    #[test]
+    fn rounds_to_increments_and_decimals() {
+        assert_eq!(round_to(324.4, 5.0), 325.0);
+        assert_eq!(round_to(101.2, 2.5), 100.0);
+        assert_eq!(round_to(0.29, 0.1), 0.3);
+        assert_eq!(round_to(16.3299, 0.01), 16.33);
+        assert_eq!(round_to(101.2, 0.0), 101.2);
+    }
+}
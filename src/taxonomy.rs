@@ -0,0 +1,291 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+
+/// How the body moves in an exercise
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MovementPattern {
+    Squat,
+    Hinge,
+    Lunge,
+    HorizontalPush,
+    VerticalPush,
+    HorizontalPull,
+    VerticalPull,
+    Carry,
+    Core,
+    Isolation,
+    #[default]
+    Other,
+}
+
+impl MovementPattern {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MovementPattern::Squat => "squat",
+            MovementPattern::Hinge => "hinge",
+            MovementPattern::Lunge => "lunge",
+            MovementPattern::HorizontalPush => "horizontal_push",
+            MovementPattern::VerticalPush => "vertical_push",
+            MovementPattern::HorizontalPull => "horizontal_pull",
+            MovementPattern::VerticalPull => "vertical_pull",
+            MovementPattern::Carry => "carry",
+            MovementPattern::Core => "core",
+            MovementPattern::Isolation => "isolation",
+            MovementPattern::Other => "other",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Equipment {
+    Barbell,
+    Dumbbell,
+    Kettlebell,
+    Machine,
+    Cable,
+    Band,
+    Bodyweight,
+    #[default]
+    Other,
+}
+
+impl Equipment {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Equipment::Barbell => "barbell",
+            Equipment::Dumbbell => "dumbbell",
+            Equipment::Kettlebell => "kettlebell",
+            Equipment::Machine => "machine",
+            Equipment::Cable => "cable",
+            Equipment::Band => "band",
+            Equipment::Bodyweight => "bodyweight",
+            Equipment::Other => "other",
+        }
+    }
+}
+
+/// Which competition lift an exercise trains, if any
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LiftCategory {
+    /// Competition squat or a barbell squat variant
+    Squat,
+    /// Competition bench press or a barbell bench variant
+    Bench,
+    /// Competition deadlift or a barbell deadlift variant
+    Deadlift,
+    #[default]
+    Accessory,
+    Core,
+}
+
+impl LiftCategory {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LiftCategory::Squat => "squat",
+            LiftCategory::Bench => "bench",
+            LiftCategory::Deadlift => "deadlift",
+            LiftCategory::Accessory => "accessory",
+            LiftCategory::Core => "core",
+        }
+    }
+
+    /// Squat, bench or deadlift
+    pub fn is_main_lift(&self) -> bool {
+        matches!(self, LiftCategory::Squat | LiftCategory::Bench | LiftCategory::Deadlift)
+    }
+}
+
+/// Classification of a canonical exercise
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct Taxonomy {
+    pub movement_pattern: MovementPattern,
+    /// Primary muscle group first
+    pub muscle_groups: Vec<String>,
+    pub equipment: Equipment,
+    pub lift_category: LiftCategory,
+}
+
+/// Per-exercise overrides of the built-in classification, set in the alias file
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct TaxonomyOverride {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub movement_pattern: Option<MovementPattern>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub muscle_groups: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub equipment: Option<Equipment>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lift_category: Option<LiftCategory>,
+}
+
+impl Taxonomy {
+    /// This classification with any overridden fields replaced
+    pub fn with_override(self, overrides: &TaxonomyOverride) -> Taxonomy {
+        Taxonomy {
+            movement_pattern: overrides.movement_pattern.unwrap_or(self.movement_pattern),
+            muscle_groups: overrides.muscle_groups.clone().unwrap_or(self.muscle_groups),
+            equipment: overrides.equipment.unwrap_or(self.equipment),
+            lift_category: overrides.lift_category.unwrap_or(self.lift_category),
+        }
+    }
+}
+
+/// A built-in rule: exercises whose name matches get this pattern, muscles and category
+struct Rule {
+    pattern: Regex,
+    movement_pattern: MovementPattern,
+    muscle_groups: &'static [&'static str],
+    lift_category: LiftCategory,
+}
+
+/// Built-in rules, most specific first; the first match wins
+fn rules() -> &'static [Rule] {
+    static RULES: OnceLock<Vec<Rule>> = OnceLock::new();
+    RULES.get_or_init(|| {
+        use LiftCategory::*;
+        use MovementPattern as P;
+
+        let rules: [(&str, MovementPattern, &[&str], LiftCategory); 21] = [
+            (r"plank|\babs?\b|crunch|sit\s*-?\s*ups?|leg raise|pallof|dead\s*bug|ab wheel|rollout|hollow|side bend|russian twist|\bcore\b",
+             P::Core, &["abs", "obliques"], Core),
+            (r"split squat|bulgarian|lunge|step\s*-?\s*up", P::Lunge, &["quads", "glutes"], Accessory),
+            (r"leg press|hack squat|belt squat", P::Squat, &["quads", "glutes"], Accessory),
+            (r"leg extension", P::Isolation, &["quads"], Accessory),
+            (r"leg curl|hamstring curl|nordic|glute ham", P::Isolation, &["hamstrings"], Accessory),
+            (r"goblet", P::Squat, &["quads", "glutes"], Accessory),
+            (r"squat", P::Squat, &["quads", "glutes", "adductors"], Squat),
+            (r"romanian|\brdls?\b|stiff\s*-?\s*leg|good\s*mornings?|hip thrust|glute bridge|back extension|hyperextension|pull\s*-?\s*through|swing",
+             P::Hinge, &["hamstrings", "glutes", "lower back"], Accessory),
+            (r"deadlift|\bdls?\b|rack pull|block pull", P::Hinge, &["hamstrings", "glutes", "lower back", "upper back"], Deadlift),
+            (r"overhead press|\bohp\b|military|shoulder press|push press|landmine press", P::VerticalPush, &["shoulders", "triceps"], Accessory),
+            (r"incline|chest press|\bfly|\bflye|pec deck", P::HorizontalPush, &["chest", "shoulders"], Accessory),
+            (r"bench|floor press|board press|spoto|larsen", P::HorizontalPush, &["chest", "triceps", "shoulders"], Bench),
+            (r"push\s*-?\s*ups?|\bdips?\b", P::HorizontalPush, &["chest", "triceps"], Accessory),
+            (r"pull\s*-?\s*ups?|chin\s*-?\s*ups?|pull\s*-?\s*downs?|\blat\b", P::VerticalPull, &["lats", "biceps"], Accessory),
+            (r"face pull|rear delt|reverse fly", P::Isolation, &["rear delts", "upper back"], Accessory),
+            (r"\brows?\b|rowing", P::HorizontalPull, &["upper back", "lats", "biceps"], Accessory),
+            (r"curl", P::Isolation, &["biceps"], Accessory),
+            (r"tricep|push\s*-?\s*downs?|skull|\bjm press|extension", P::Isolation, &["triceps"], Accessory),
+            (r"lateral raise|front raise|\braises?\b", P::Isolation, &["shoulders"], Accessory),
+            (r"calf|calves", P::Isolation, &["calves"], Accessory),
+            (r"carry|farmer|suitcase|\bshrugs?\b", P::Carry, &["grip", "traps"], Accessory),
+        ];
+
+        rules
+            .into_iter()
+            .map(|(pattern, movement_pattern, muscle_groups, lift_category)| Rule {
+                pattern: Regex::new(&format!("(?i){}", pattern)).expect("valid taxonomy regex"),
+                movement_pattern,
+                muscle_groups,
+                lift_category,
+            })
+            .collect()
+    })
+}
+
+/// Equipment keywords, checked in order; exercises matching none fall back by pattern
+fn detect_equipment(name: &str, movement_pattern: MovementPattern) -> Equipment {
+    static EQUIPMENT: OnceLock<Vec<(Regex, Equipment)>> = OnceLock::new();
+    let equipment = EQUIPMENT.get_or_init(|| {
+        [
+            (r"\bdbs?\b|dumb\s*bells?|goblet", Equipment::Dumbbell),
+            (r"\bkbs?\b|kettle\s*bells?|swing", Equipment::Kettlebell),
+            (r"cable|push\s*-?\s*downs?|pull\s*-?\s*downs?|face pull|pallof", Equipment::Cable),
+            (r"machine|leg press|hack squat|belt squat|smith|leg extension|leg curl|pec deck|chest press", Equipment::Machine),
+            (r"\bbands?\b|banded", Equipment::Band),
+            (r"push\s*-?\s*ups?|pull\s*-?\s*ups?|chin\s*-?\s*ups?|\bdips?\b|plank|body\s*weight|\bbw\b|nordic|hanging|sit\s*-?\s*ups?|crunch|dead\s*bug|hollow|step\s*-?\s*up|lunge",
+             Equipment::Bodyweight),
+            (r"barbell|\bbb\b|\bssb\b|safety bar|trap bar|hex bar|cambered", Equipment::Barbell),
+        ]
+        .into_iter()
+        .map(|(pattern, equipment)| (Regex::new(&format!("(?i){}", pattern)).expect("valid equipment regex"), equipment))
+        .collect()
+    });
+
+    equipment
+        .iter()
+        .find(|(pattern, _)| pattern.is_match(name))
+        .map(|&(_, equipment)| equipment)
+        .unwrap_or(match movement_pattern {
+            MovementPattern::Squat | MovementPattern::Hinge | MovementPattern::HorizontalPush
+            | MovementPattern::VerticalPush | MovementPattern::HorizontalPull => Equipment::Barbell,
+            _ => Equipment::Other,
+        })
+}
+
+/// Classify an exercise from its canonical name with the built-in rules.
+/// Squat, bench and deadlift variants done with anything but a barbell count as accessories.
+pub fn classify(name: &str) -> Taxonomy {
+    let Some(rule) = rules().iter().find(|rule| rule.pattern.is_match(name)) else {
+        return Taxonomy {
+            equipment: detect_equipment(name, MovementPattern::Other),
+            ..Taxonomy::default()
+        };
+    };
+
+    let equipment = detect_equipment(name, rule.movement_pattern);
+    let lift_category = if rule.lift_category.is_main_lift() && equipment != Equipment::Barbell {
+        LiftCategory::Accessory
+    } else {
+        rule.lift_category
+    };
+
+    Taxonomy {
+        movement_pattern: rule.movement_pattern,
+        muscle_groups: rule.muscle_groups.iter().map(|m| m.to_string()).collect(),
+        equipment,
+        lift_category,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_competition_lifts() {
+        let squat = classify("Low Bar Squat");
+        assert_eq!(squat.movement_pattern, MovementPattern::Squat);
+        assert_eq!(squat.equipment, Equipment::Barbell);
+        assert_eq!(squat.lift_category, LiftCategory::Squat);
+        assert_eq!(squat.muscle_groups[0], "quads");
+
+        assert_eq!(classify("Larsen Press").lift_category, LiftCategory::Bench);
+        assert_eq!(classify("Deficit Deadlift").lift_category, LiftCategory::Deadlift);
+    }
+
+    #[test]
+    fn specific_rules_win_over_general_ones() {
+        assert_eq!(classify("Bulgarian Split Squat").movement_pattern, MovementPattern::Lunge);
+        assert_eq!(classify("Romanian Deadlift").lift_category, LiftCategory::Accessory);
+        assert_eq!(classify("Incline Bench").lift_category, LiftCategory::Accessory);
+        assert_eq!(classify("Hack Squat").equipment, Equipment::Machine);
+    }
+
+    #[test]
+    fn main_lift_variants_without_a_barbell_are_accessories() {
+        let bench = classify("DB Bench");
+        assert_eq!(bench.equipment, Equipment::Dumbbell);
+        assert_eq!(bench.lift_category, LiftCategory::Accessory);
+    }
+
+    #[test]
+    fn falls_back_to_other() {
+        let unknown = classify("Sled Push");
+        assert_eq!(unknown.movement_pattern, MovementPattern::Other);
+        assert_eq!(unknown.equipment, Equipment::Other);
+        assert!(unknown.muscle_groups.is_empty());
+    }
+
+    #[test]
+    fn applies_overrides() {
+        let overrides = TaxonomyOverride { lift_category: Some(LiftCategory::Squat), ..TaxonomyOverride::default() };
+        let taxonomy = classify("SSB Squat").with_override(&overrides);
+        assert_eq!(taxonomy.lift_category, LiftCategory::Squat);
+        assert_eq!(taxonomy.equipment, Equipment::Barbell);
+    }
+}
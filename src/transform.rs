@@ -1,29 +1,62 @@
 use serde::{Deserialize, Serialize};
-use chrono::{DateTime, Utc, NaiveDate, Duration, Datelike};
+use chrono::{DateTime, Utc, NaiveDate, Duration, Datelike, Weekday};
 use anyhow::Result;
-use tracing::debug;
+use tracing::{debug, warn};
+use crate::hashing::{hash_rows, slug, stable_hash};
+use crate::sheets::SheetRow;
+use crate::cfg::LayoutProfile;
+use crate::exercises::{CanonicalExercise, ExerciseCatalog};
+use crate::strength::estimate;
+use crate::taxonomy::Taxonomy;
+use crate::dates::{is_date_header, parse_date, parse_date_in_year, DateOrder};
+use crate::layout::{locate_week_columns, RecordField, WeekColumns};
+use crate::load::{parse_load, parse_load_instruction, Load, LoadInstruction, LoadUnit};
+use crate::reps::{parse_reps, RepScheme};
+use crate::rpe::{parse_rpe, Rpe};
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::ops::Range;
+use std::sync::{Arc, OnceLock};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WorkoutRecord {
-    pub id: String,
+    pub id: String,               // Deterministic: block, week position, day, sheet row, record type
     pub block_name: String,
-    pub week_start_date: String,  // e.g., "5/19/2025"
+    pub week_start_date: NaiveDate, // Serialized as ISO 8601, e.g. "2025-05-19"
     pub week_number: u32,         // 1, 2, 3, etc.
+    pub phase: Option<String>,    // "deload", "taper", "test", "peak"
     pub day_number: u32,          // 1, 2, 3, etc. 
-    pub workout_date: String,     // Calculated actual workout date
-    pub exercise_name: String,
+    pub workout_date: NaiveDate,  // Calendar-derived date, or the date actually performed
+    pub exercise_name: String,    // As written in the sheet
+    pub exercise_id: String,      // Canonical exercise, from the alias file or derived from the name
+    pub exercise_canonical: String,
+    pub modifiers: Vec<String>,   // "w/belt", "paused", "1s pause", "T&G", ...
+    pub cue: Option<String>,      // Coach instruction after a `*` in the exercise cell
+    pub taxonomy: Taxonomy,       // Movement pattern, muscle groups, equipment, lift category
     pub record_type: String,      // "prescribed" or "actual"
+    pub set_number: Option<u32>,  // 1-based, when an actual record was expanded into one row per set
     
     // Workout data
     pub sets: Option<u32>,
-    pub reps: Option<String>,     // Can be "7", "8-10", etc.
-    pub load: Option<f64>,
+    pub reps: Option<RepScheme>,  // "7", "8-10", "5,5,4", "3x5", "AMRAP", ...
+    pub load: Option<Load>,       // Normalized to the configured unit; raw text kept in `load.raw`
     pub load_instruction: Option<String>, // "find", "base on max", etc.
-    pub rpe: Option<String>,      // Can be "5", "5, 6", "easy 7", etc.
+    pub load_instruction_kind: Option<LoadInstruction>,
+    #[serde(default)]
+    pub resolved_load: Option<f64>, // A percentage or "base on max" instruction worked out against the maxes table
+    pub rpe: Option<Rpe>,         // "5", "5, 6", "easy 7", "@8", "2 RIR", ...
     pub notes: Option<String>,
     
     // Metadata
     pub processed_at: DateTime<Utc>,
+    
+    // Position in the sheet, not written to the CSV: the absolute row, and for prescriptions
+    // the column (within the fetched range) a suggested load is written back to
+    #[serde(default)]
+    pub sheet_row: usize,
+    #[serde(default)]
+    pub load_column: Option<usize>,
 }
 
 impl WorkoutRecord {
@@ -33,130 +66,531 @@ impl WorkoutRecord {
             "block_name".to_string(),
             "week_start_date".to_string(),
             "week_number".to_string(),
+            "phase".to_string(),
             "day_number".to_string(),
             "workout_date".to_string(),
             "exercise_name".to_string(),
+            "exercise_id".to_string(),
+            "exercise_canonical".to_string(),
+            "modifiers".to_string(),
+            "cue".to_string(),
+            "movement_pattern".to_string(),
+            "muscle_groups".to_string(),
+            "equipment".to_string(),
+            "lift_category".to_string(),
             "record_type".to_string(),
+            "set_number".to_string(),
             "sets".to_string(),
             "reps".to_string(),
+            "reps_min".to_string(),
+            "reps_max".to_string(),
+            "reps_per_set".to_string(),
+            "reps_amrap".to_string(),
+            "reps_seconds".to_string(),
+            "reps_to_rpe".to_string(),
             "load".to_string(),
+            "load_unit".to_string(),
+            "load_per_side".to_string(),
+            "load_bodyweight_relative".to_string(),
+            "load_per_set".to_string(),
+            "load_raw".to_string(),
             "load_instruction".to_string(),
+            "load_instruction_kind".to_string(),
+            "load_percent".to_string(),
+            "resolved_load".to_string(),
             "rpe".to_string(),
+            "rpe_values".to_string(),
+            "rpe_from_rir".to_string(),
+            "rpe_missing".to_string(),
+            "rpe_qualifier".to_string(),
+            "e1rm_epley".to_string(),
+            "e1rm_brzycki".to_string(),
+            "e1rm_rpe".to_string(),
             "notes".to_string(),
+            "content_hash".to_string(),
             "processed_at".to_string(),
         ]
     }
     
     pub fn to_csv_row(&self) -> Vec<String> {
-        vec![
+        let mut row = vec![
             self.id.clone(),
             self.block_name.clone(),
-            self.week_start_date.clone(),
+            self.week_start_date.to_string(),
             self.week_number.to_string(),
+            self.phase.clone().unwrap_or_default(),
             self.day_number.to_string(),
-            self.workout_date.clone(),
+            self.workout_date.to_string(),
             self.exercise_name.clone(),
+            self.exercise_id.clone(),
+            self.exercise_canonical.clone(),
+            self.modifiers.join(";"),
+            self.cue.clone().unwrap_or_default(),
+            self.taxonomy.movement_pattern.as_str().to_string(),
+            self.taxonomy.muscle_groups.join(";"),
+            self.taxonomy.equipment.as_str().to_string(),
+            self.taxonomy.lift_category.as_str().to_string(),
             self.record_type.clone(),
+            self.set_number.map(|n| n.to_string()).unwrap_or_default(),
             self.sets.map(|s| s.to_string()).unwrap_or_default(),
-            self.reps.clone().unwrap_or_default(),
-            self.load.map(|l| l.to_string()).unwrap_or_default(),
-            self.load_instruction.clone().unwrap_or_default(),
-            self.rpe.clone().unwrap_or_default(),
+        ];
+        row.extend(self.reps_fields());
+        row.extend(self.load_fields());
+        // Depends on the maxes table rather than the sheet, so not part of the content hash
+        row.push(self.resolved_load.map(|l| l.to_string()).unwrap_or_default());
+        row.extend(self.rpe_fields());
+        row.extend(self.e1rm_fields());
+        row.extend([
             self.notes.clone().unwrap_or_default(),
+            self.content_hash(),
             self.processed_at.to_rfc3339(),
+        ]);
+        row
+    }
+    
+    /// Sets the record covers: the sets column, else the count in the reps cell ("3x5"),
+    /// else the length of a per-set reps or load list. None when nothing says.
+    pub fn set_count(&self) -> Option<u32> {
+        self.sets
+            .or_else(|| self.reps.as_ref().and_then(|r| r.sets))
+            .or_else(|| self.reps.as_ref().map(|r| r.per_set.len() as u32).filter(|&n| n > 0))
+            .or_else(|| self.load.as_ref().map(|l| l.per_set.len() as u32).filter(|&n| n > 0))
+    }
+    
+    /// The record's ID without the record type and set suffix, shared by every record
+    /// read from the same sheet row (see `record_id`)
+    pub fn row_key(&self) -> &str {
+        self.id
+            .rsplit_once(&format!("_{}", self.record_type))
+            .map_or(self.id.as_str(), |(key, _)| key)
+    }
+    
+    /// Hash of the record's content, excluding `id` and `processed_at`.
+    /// Two runs over an unchanged sheet produce the same hash.
+    pub fn content_hash(&self) -> String {
+        let mut parts = vec![
+            self.block_name.clone(),
+            self.week_start_date.to_string(),
+            self.week_number.to_string(),
+            self.phase.clone().unwrap_or_default(),
+            self.day_number.to_string(),
+            self.workout_date.to_string(),
+            self.exercise_name.clone(),
+            self.exercise_id.clone(),
+            self.exercise_canonical.clone(),
+            self.modifiers.join(";"),
+            self.cue.clone().unwrap_or_default(),
+            self.taxonomy.movement_pattern.as_str().to_string(),
+            self.taxonomy.muscle_groups.join(";"),
+            self.taxonomy.equipment.as_str().to_string(),
+            self.taxonomy.lift_category.as_str().to_string(),
+            self.record_type.clone(),
+            self.set_number.map(|n| n.to_string()).unwrap_or_default(),
+            self.sets.map(|s| s.to_string()).unwrap_or_default(),
+        ];
+        parts.extend(self.reps_fields());
+        parts.extend(self.load_fields());
+        parts.extend(self.rpe_fields());
+        parts.extend([
+            self.notes.clone().unwrap_or_default(),
+        ]);
+        stable_hash(&parts)
+    }
+    
+    /// CSV cells for the reps columns, from `reps` through `reps_to_rpe`
+    fn reps_fields(&self) -> Vec<String> {
+        let reps = self.reps.as_ref();
+        vec![
+            reps.map(|r| r.raw.clone()).unwrap_or_default(),
+            reps.and_then(|r| r.min).map(|n| n.to_string()).unwrap_or_default(),
+            reps.and_then(|r| r.max).map(|n| n.to_string()).unwrap_or_default(),
+            reps.map(RepScheme::per_set_label).unwrap_or_default(),
+            reps.map(|r| r.amrap.to_string()).unwrap_or_default(),
+            reps.and_then(|r| r.seconds).map(|n| n.to_string()).unwrap_or_default(),
+            reps.map(|r| r.to_rpe.to_string()).unwrap_or_default(),
+        ]
+    }
+    
+    /// CSV cells for the load columns, from `load` through `load_percent`
+    fn load_fields(&self) -> Vec<String> {
+        let load = self.load.as_ref();
+        let instruction = self.load_instruction_kind.as_ref();
+        vec![
+            load.map(|l| l.value.to_string()).unwrap_or_default(),
+            load.map(|l| l.unit.to_string()).unwrap_or_default(),
+            load.map(|l| l.per_side.to_string()).unwrap_or_default(),
+            load.map(|l| l.bodyweight_relative.to_string()).unwrap_or_default(),
+            load.map(Load::per_set_label).unwrap_or_default(),
+            load.map(|l| l.raw.clone()).unwrap_or_default(),
+            self.load_instruction.clone().unwrap_or_default(),
+            instruction.map(|i| i.kind().to_string()).unwrap_or_default(),
+            instruction.and_then(LoadInstruction::percent_label).unwrap_or_default(),
+        ]
+    }
+    
+    /// Estimated 1RM columns, filled in for actual records with a load and reps.
+    /// Derived from the other fields, so not part of the content hash.
+    fn e1rm_fields(&self) -> Vec<String> {
+        let e1rm = estimate(self);
+        vec![
+            e1rm.map(|e| e.epley.to_string()).unwrap_or_default(),
+            e1rm.map(|e| e.brzycki.to_string()).unwrap_or_default(),
+            e1rm.and_then(|e| e.rpe).map(|e| e.to_string()).unwrap_or_default(),
+        ]
+    }
+    
+    /// CSV cells for the RPE columns, from `rpe` through `rpe_qualifier`
+    fn rpe_fields(&self) -> Vec<String> {
+        let rpe = self.rpe.as_ref();
+        vec![
+            rpe.map(|r| r.raw.clone()).unwrap_or_default(),
+            rpe.map(Rpe::values_label).unwrap_or_default(),
+            rpe.map(|r| r.from_rir.to_string()).unwrap_or_default(),
+            rpe.map(|r| r.missing.to_string()).unwrap_or_default(),
+            rpe.and_then(|r| r.qualifier.clone()).unwrap_or_default(),
         ]
     }
 }
 
+/// The prescription and actuals read from one sheet row
+#[derive(Debug, Clone)]
+pub struct RowRecords<'a> {
+    /// The ID shared by the row's records, without the record type
+    pub key: &'a str,
+    pub prescribed: Option<&'a WorkoutRecord>,
+    /// One record, or one per set when the actual was expanded
+    pub actuals: Vec<&'a WorkoutRecord>,
+}
+
+impl<'a> RowRecords<'a> {
+    /// The prescription, else the first actual
+    pub fn first(&self) -> &'a WorkoutRecord {
+        self.prescribed.or(self.actuals.first().copied()).expect("every row has a record")
+    }
+}
+
+/// Group records by the sheet row they came from, in the order rows first appear.
+/// A prescription and its actuals share a block, week, day and exercise.
+pub fn group_by_row(records: &[WorkoutRecord]) -> Vec<RowRecords<'_>> {
+    let mut rows: Vec<RowRecords> = Vec::new();
+    let mut positions: HashMap<&str, usize> = HashMap::new();
+    for record in records {
+        let key = record.row_key();
+        let index = *positions.entry(key).or_insert_with(|| {
+            rows.push(RowRecords { key, prescribed: None, actuals: Vec::new() });
+            rows.len() - 1
+        });
+        if record.record_type == "prescribed" {
+            rows[index].prescribed = Some(record);
+        } else {
+            rows[index].actuals.push(record);
+        }
+    }
+    rows
+}
+
+/// Build a record ID from the record's position in the sheet (`row_number` is the absolute sheet row).
+///
+/// The week is identified by its position in the block (1st, 2nd, ... date header),
+/// not the parsed week label, so mislabelled weeks never collide.
+pub fn record_id(block_name: &str, week_position: usize, day_number: u32, row_number: usize, record_type: &str) -> String {
+    format!("{}_w{}_d{}_r{}_{}", slug(block_name), week_position, day_number, row_number, record_type)
+}
+
+/// A record ID without its per-set suffix ("…_actual_s2" -> "…_actual"). An actual split into
+/// per-set rows and the single row it replaces share a family.
+pub fn record_id_family(id: &str) -> &str {
+    match id.rsplit_once("_s") {
+        Some((family, set)) if !set.is_empty() && set.chars().all(|c| c.is_ascii_digit()) => family,
+        _ => id,
+    }
+}
+
+/// Rows at the top of a block that hold the date and week headers
+pub const HEADER_BAND_ROWS: usize = 5;
+
+/// Per-block settings that control how a block is parsed
+#[derive(Debug, Clone, Default)]
+pub struct ParseOptions {
+    pub layout: LayoutProfile,
+    // Weekday of each "DAY N" (index 0 = Day 1); None means consecutive days from the week start
+    pub training_days: Option<Vec<Weekday>>,
+    pub date_order: DateOrder,
+    // Loads without a unit are read in this unit, and every load is converted to it
+    pub load_unit: LoadUnit,
+    // Alias dictionary used to canonicalize exercise names
+    pub exercises: Arc<ExerciseCatalog>,
+}
+
+impl ParseOptions {
+    /// Hash of every option that changes the parsed records. It is mixed into the block and
+    /// week content hashes, so editing the layout, calendar, units or aliases re-normalizes
+    /// blocks whose cells didn't change.
+    pub fn fingerprint(&self) -> String {
+        stable_hash(&[
+            serde_json::to_string(&self.layout).unwrap_or_default(),
+            format!("{:?}", self.training_days),
+            format!("{:?}", self.date_order),
+            self.load_unit.to_string(),
+            self.exercises.fingerprint().to_string(),
+        ])
+    }
+}
+
+/// A cell that couldn't be interpreted; the record (or week) it belongs to is skipped or
+/// falls back to a default, and the warning is reported by the job
+#[derive(Debug, Clone)]
+pub struct ParseWarning {
+    pub block_name: String,
+    pub row_number: usize,
+    pub field: &'static str,
+    pub value: String,
+    pub message: String,
+}
+
+impl fmt::Display for ParseWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} row {}: {} '{}' {}", self.block_name, self.row_number, self.field, self.value, self.message)
+    }
+}
+
+/// Records parsed from a block, plus the cells that couldn't be interpreted
+#[derive(Debug, Default)]
+pub struct NormalizedBlock {
+    pub records: Vec<WorkoutRecord>,
+    pub warnings: Vec<ParseWarning>,
+    /// Every week with a readable start date, in column order
+    pub weeks: Vec<ParsedWeek>,
+}
+
+/// One week of a normalized block
+#[derive(Debug, Clone)]
+pub struct ParsedWeek {
+    /// The week's key, as in `week_content_hashes`
+    pub key: String,
+    pub start_date: NaiveDate,
+    /// Where the week's records are in `NormalizedBlock::records`
+    pub records: Range<usize>,
+}
+
+impl NormalizedBlock {
+    /// The records of one week; empty for a week that produced none
+    pub fn week_records(&self, week_key: &str) -> &[WorkoutRecord] {
+        self.weeks
+            .iter()
+            .find(|week| week.key == week_key)
+            .map_or(&[], |week| &self.records[week.records.clone()])
+    }
+}
+
 #[derive(Debug, Clone)]
 struct WeekInfo {
     week_number: u32,
-    start_date: String,
+    phase: Option<String>,
+    // Raw header text; parsed with the block's date order when normalizing
+    start_label: String,
+    header_row: usize,
     start_col: usize,
     end_col: usize,
 }
 
+/// Where a record came from in the block; shared by the prescribed and actual extractors
+struct RecordContext<'a> {
+    block_name: &'a str,
+    week: &'a WeekInfo,
+    week_start: NaiveDate,
+    columns: &'a WeekColumns,
+    week_position: usize,
+    day_number: u32,
+    workout_date: NaiveDate,
+    date_order: DateOrder,
+    load_unit: LoadUnit,
+    exercise_name: &'a str,
+    exercise: &'a CanonicalExercise,
+    row_number: usize,
+}
+
 #[derive(Debug, Clone)]
 struct DayInfo {
     day_number: u32,
     row_index: usize,
 }
 
-pub fn normalize_block_data(raw_rows: Vec<Vec<String>>, block_name: &str) -> Result<Vec<WorkoutRecord>> {
+/// Normalize a block into workout records, week by week
+pub fn normalize_block_data(raw_rows: Vec<SheetRow>, block_name: &str, options: &ParseOptions) -> Result<NormalizedBlock> {
+    let mut normalized = NormalizedBlock::default();
     if raw_rows.is_empty() {
-        return Ok(Vec::new());
+        return Ok(normalized);
     }
     
     debug!("Processing block: {} with {} rows", block_name, raw_rows.len());
     
     // Step 1: Parse the header structure to identify weeks
     let weeks = parse_week_structure(&raw_rows)?;
+    let keys = week_keys(&weeks);
     debug!("Found {} weeks in block {}", weeks.len(), block_name);
     
+    if weeks.is_empty() {
+        warn!("No week headers found in the first {} rows of {}; no records will be produced. \
+               Is the header band missing from the fetched rows?", HEADER_BAND_ROWS, block_name);
+        return Ok(normalized);
+    }
+    
+    // Locate each week's prescribed/actual columns from the header labels
+    let header_rows = header_band(&raw_rows);
+    let week_columns: Vec<WeekColumns> = weeks
+        .iter()
+        .map(|week| locate_week_columns(header_rows, week.start_col, week.end_col, &options.layout))
+        .collect();
+    
     // Step 2: Identify day rows and exercise rows
     let (day_rows, exercise_rows) = identify_row_types(&raw_rows)?;
     debug!("Found {} day markers and {} exercise rows", day_rows.len(), exercise_rows.len());
     
     // Step 3: Process each exercise for each week and day
-    let mut workout_records = Vec::new();
-    
-    for week in &weeks {
+    for (week_index, week) in weeks.iter().enumerate() {
+        let week_start = match parse_date(&week.start_label, options.date_order) {
+            Ok(date) => date,
+            Err(e) => {
+                normalized.warnings.push(ParseWarning {
+                    block_name: block_name.to_string(),
+                    row_number: week.header_row,
+                    field: "week_start_date",
+                    value: week.start_label.clone(),
+                    message: format!("{}; skipping week {}", e, week_index + 1),
+                });
+                continue;
+            }
+        };
+        let first_record = normalized.records.len();
+        
         for day in &day_rows {
-            let workout_date = calculate_workout_date(&week.start_date, day.day_number)?;
+            let workout_date = calculate_workout_date(week_start, day.day_number, options.training_days.as_deref());
             
             // Find exercises for this day
             let day_exercises = find_exercises_for_day(&raw_rows, day.row_index, &exercise_rows);
             
             for exercise_row_idx in day_exercises {
                 if let Some(exercise_row) = raw_rows.get(exercise_row_idx) {
-                    if let Some(exercise_name) = exercise_row.get(1) {
+                    if let Some(exercise_name) = exercise_row.cells.get(1) {
                         if !exercise_name.trim().is_empty() && exercise_name != "Exercise" {
-                            // Extract prescribed and actual data for this week
-                            let prescribed = extract_prescribed_data(
-                                exercise_row, week, block_name, &week.start_date, 
-                                week.week_number, day.day_number, &workout_date, exercise_name
-                            )?;
+                            let exercise = options.exercises.canonicalize(exercise_name);
+                            let ctx = RecordContext {
+                                block_name,
+                                week,
+                                week_start,
+                                columns: &week_columns[week_index],
+                                week_position: week_index + 1,
+                                day_number: day.day_number,
+                                workout_date,
+                                date_order: options.date_order,
+                                load_unit: options.load_unit,
+                                exercise_name,
+                                exercise: &exercise,
+                                row_number: exercise_row.row_number,
+                            };
                             
-                            let actual = extract_actual_data(
-                                exercise_row, week, block_name, &week.start_date,
-                                week.week_number, day.day_number, &workout_date, exercise_name
-                            )?;
+                            // Extract prescribed and actual data for this week
+                            let prescribed = extract_prescribed_data(&exercise_row.cells, &ctx, &mut normalized.warnings)?;
+                            let actual = extract_actual_data(&exercise_row.cells, &ctx, &mut normalized.warnings)?;
                             
                             if let Some(p) = prescribed {
-                                workout_records.push(p);
+                                normalized.records.push(p);
                             }
                             if let Some(a) = actual {
-                                workout_records.push(a);
+                                normalized.records.extend(expand_sets(a, exercise_row.row_number, &mut normalized.warnings));
                             }
                         }
                     }
                 }
             }
         }
+        normalized.weeks.push(ParsedWeek {
+            key: keys[week_index].clone(),
+            start_date: week_start,
+            records: first_record..normalized.records.len(),
+        });
     }
     
-    debug!("Generated {} workout records for block {}", workout_records.len(), block_name);
-    Ok(workout_records)
+    debug!("Generated {} workout records for block {}", normalized.records.len(), block_name);
+    Ok(normalized)
+}
+
+/// Hash each week's column group (plus the day/exercise label columns), keyed by week.
+/// Returns `(week_key, hash)` pairs in column order.
+pub fn week_content_hashes(raw_rows: &[SheetRow]) -> Result<Vec<(String, String)>> {
+    let weeks = parse_week_structure(raw_rows)?;
+    let keys = week_keys(&weeks);
+    
+    let hashes = weeks.iter().zip(keys).map(|(week, key)| {
+        let grid: Vec<SheetRow> = raw_rows
+            .iter()
+            .map(|row| {
+                let labels = row.cells.iter().take(LABEL_COLUMNS);
+                let week_cells = row.cells.iter().skip(week.start_col).take((week.end_col + 1).saturating_sub(week.start_col));
+                SheetRow {
+                    row_number: row.row_number,
+                    cells: labels.chain(week_cells).cloned().collect(),
+                }
+            })
+            .collect();
+        (key, hash_rows(&grid))
+    }).collect();
+    
+    Ok(hashes)
+}
+
+/// Columns A and B hold day markers and exercise names shared by every week
+const LABEL_COLUMNS: usize = 2;
+
+/// Stable key per week: its start date header, suffixed if a date repeats
+fn week_keys(weeks: &[WeekInfo]) -> Vec<String> {
+    let mut seen = HashSet::new();
+    weeks.iter().map(|week| {
+        let mut key = week.start_label.trim().to_string();
+        let mut suffix = 2;
+        while !seen.insert(key.clone()) {
+            key = format!("{}#{}", week.start_label.trim(), suffix);
+            suffix += 1;
+        }
+        key
+    }).collect()
 }
 
-fn parse_week_structure(raw_rows: &[Vec<String>]) -> Result<Vec<WeekInfo>> {
+/// The rows within `HEADER_BAND_ROWS` sheet rows of the first row. Blank rows are not
+/// returned, so the band is measured in sheet rows rather than returned rows.
+fn header_band(raw_rows: &[SheetRow]) -> &[SheetRow] {
+    let header_end = raw_rows.first().map_or(0, |row| row.row_number + HEADER_BAND_ROWS);
+    let len = raw_rows.iter().take_while(|row| row.row_number < header_end).count();
+    &raw_rows[..len]
+}
+
+fn parse_week_structure(raw_rows: &[SheetRow]) -> Result<Vec<WeekInfo>> {
     let mut weeks = Vec::new();
     
-    // Look for date headers (like "5/19/2025") in the first few rows
-    for (row_idx, row) in raw_rows.iter().take(5).enumerate() {
-        debug!("Row {} has {} columns: {:?}", row_idx, row.len(), row.iter().take(20).collect::<Vec<_>>());
-        for (col_idx, cell) in row.iter().enumerate() {
+    // Look for date headers (like "5/19/2025" or "May 19, 2025") in the header band
+    for (row_idx, row) in header_band(raw_rows).iter().enumerate() {
+        debug!("Row {} has {} columns: {:?}", row.row_number, row.cells.len(), row.cells.iter().take(20).collect::<Vec<_>>());
+        for (col_idx, cell) in row.cells.iter().enumerate() {
             if is_date_header(cell) {
-                // Look for week number in the row below
-                let week_number = if let Some(next_row) = raw_rows.get(row_idx + 1) {
-                    parse_week_number(next_row.get(col_idx).unwrap_or(&String::new()))
-                } else {
-                    weeks.len() as u32 + 1
-                };
+                // The week label is usually in the row below the date, sometimes above it
+                let below = raw_rows.get(row_idx + 1).and_then(|r| r.cells.get(col_idx));
+                let above = row_idx.checked_sub(1).and_then(|i| raw_rows.get(i)).and_then(|r| r.cells.get(col_idx));
+                let label = [below, above]
+                    .into_iter()
+                    .flatten()
+                    .map(|cell| parse_week_label(cell))
+                    .find(|label| label.number.is_some() || label.phase.is_some())
+                    .unwrap_or_default();
+                
+                // Fall back to the week's position among the date headers
+                let week_number = label.number.unwrap_or(weeks.len() as u32 + 1);
                 
                 weeks.push(WeekInfo {
                     week_number,
-                    start_date: cell.clone(),
+                    phase: label.phase,
+                    start_label: cell.clone(),
+                    header_row: row.row_number,
                     start_col: col_idx,
                     end_col: col_idx + 12, // Estimate, will refine
                 });
@@ -164,56 +598,62 @@ fn parse_week_structure(raw_rows: &[Vec<String>]) -> Result<Vec<WeekInfo>> {
         }
     }
     
-    // Refine end columns based on next week start or total columns
+    // Refine end columns based on next week start or the widest row
+    let max_width = raw_rows.iter().map(|row| row.cells.len()).max().unwrap_or(0);
     for i in 0..weeks.len() {
         if i + 1 < weeks.len() {
-            weeks[i].end_col = weeks[i + 1].start_col - 1;
-        } else if let Some(first_row) = raw_rows.first() {
-            weeks[i].end_col = first_row.len() - 1;
+            weeks[i].end_col = weeks[i + 1].start_col.saturating_sub(1).max(weeks[i].start_col);
+        } else {
+            weeks[i].end_col = max_width.saturating_sub(1).max(weeks[i].start_col);
         }
     }
     
     Ok(weeks)
 }
 
-fn is_date_header(cell: &str) -> bool {
-    // Check for date patterns like "5/19/2025", "5/26/2025"
-    let trimmed = cell.trim();
-    if trimmed.is_empty() {
-        return false;
-    }
+/// What a week header cell says about the week
+#[derive(Debug, Clone, Default)]
+struct WeekLabel {
+    number: Option<u32>,
+    phase: Option<String>,
+}
+
+/// Parse labels like "Week 3", "wk 7", "Week 6 (taper)" or "Deload"
+fn parse_week_label(cell: &str) -> WeekLabel {
+    static WEEK_NUMBER: OnceLock<Regex> = OnceLock::new();
+    static PHASE: OnceLock<Regex> = OnceLock::new();
     
-    // Simple date pattern: M/D/YYYY or MM/DD/YYYY
-    let parts: Vec<&str> = trimmed.split('/').collect();
-    let is_date = parts.len() == 3 && 
-        parts[0].parse::<u32>().is_ok() && 
-        parts[1].parse::<u32>().is_ok() && 
-        parts[2].parse::<u32>().is_ok();
+    let week_number = WEEK_NUMBER.get_or_init(|| {
+        Regex::new(r"(?i)\b(?:week|wk)\s*#?\s*(\d{1,3})\b").expect("valid week regex")
+    });
+    let phase = PHASE.get_or_init(|| {
+        Regex::new(r"(?i)\b(deload|taper(?:ing)?|test(?:ing)?|peak(?:ing)?)\b").expect("valid phase regex")
+    });
     
-    if is_date {
-        debug!("Found date header: '{}'", trimmed);
-    }
+    let number = week_number
+        .captures(cell)
+        .and_then(|captures| captures.get(1))
+        .and_then(|m| m.as_str().parse::<u32>().ok());
     
-    is_date
-}
-
-fn parse_week_number(cell: &str) -> u32 {
-    let cell = cell.to_lowercase();
-    if cell.contains("week 1") { 1 }
-    else if cell.contains("week 2") { 2 }
-    else if cell.contains("week 3") { 3 }
-    else if cell.contains("week 4") { 4 }
-    else if cell.contains("week 5") { 5 }
-    else if cell.contains("deload") { 6 }
-    else { 1 }
+    let phase = phase.captures(cell).and_then(|captures| captures.get(1)).map(|m| {
+        let phase = m.as_str().to_lowercase();
+        match phase.as_str() {
+            "tapering" => "taper".to_string(),
+            "testing" => "test".to_string(),
+            "peaking" => "peak".to_string(),
+            _ => phase,
+        }
+    });
+    
+    WeekLabel { number, phase }
 }
 
-fn identify_row_types(raw_rows: &[Vec<String>]) -> Result<(Vec<DayInfo>, Vec<usize>)> {
+fn identify_row_types(raw_rows: &[SheetRow]) -> Result<(Vec<DayInfo>, Vec<usize>)> {
     let mut day_rows = Vec::new();
     let mut exercise_rows = Vec::new();
     
     for (row_idx, row) in raw_rows.iter().enumerate() {
-        if let Some(first_cell) = row.get(1) { // Column B (index 1)
+        if let Some(first_cell) = row.cells.get(1) { // Column B (index 1)
             let cell = first_cell.trim().to_uppercase();
             
             // Check for day markers
@@ -243,13 +683,13 @@ fn identify_row_types(raw_rows: &[Vec<String>]) -> Result<(Vec<DayInfo>, Vec<usi
     Ok((day_rows, exercise_rows))
 }
 
-fn find_exercises_for_day(raw_rows: &[Vec<String>], day_row_idx: usize, exercise_rows: &[usize]) -> Vec<usize> {
+fn find_exercises_for_day(raw_rows: &[SheetRow], day_row_idx: usize, exercise_rows: &[usize]) -> Vec<usize> {
     // Find exercise rows that come after this day marker but before the next day marker
     let next_day_idx = raw_rows.iter()
         .enumerate()
         .skip(day_row_idx + 1)
         .find(|(_, row)| {
-            if let Some(cell) = row.get(1) {
+            if let Some(cell) = row.cells.get(1) {
                 cell.trim().to_uppercase().starts_with("DAY ")
             } else {
                 false
@@ -264,138 +704,349 @@ fn find_exercises_for_day(raw_rows: &[Vec<String>], day_row_idx: usize, exercise
         .collect()
 }
 
-fn calculate_workout_date(week_start_date: &str, day_number: u32) -> Result<String> {
-    // Parse the date string (e.g., "5/19/2025")
-    let parts: Vec<&str> = week_start_date.split('/').collect();
-    if parts.len() != 3 {
-        return Ok(week_start_date.to_string());
+/// Date of "DAY N" in the week starting at `week_start`.
+///
+/// With a training-day calendar, Day 1 is the first date on or after the week start that falls
+/// on the first configured weekday, and each later day is the next date after the previous day
+/// on its weekday, so `["Sat", "Mon"]` puts Day 2 on the Monday after Day 1. Days past the end
+/// of the list follow the last listed day, one day apart. Without a calendar, Day N is N-1 days
+/// after the week start.
+fn calculate_workout_date(week_start: NaiveDate, day_number: u32, training_days: Option<&[Weekday]>) -> NaiveDate {
+    let index = day_number.saturating_sub(1) as usize;
+    
+    match training_days.filter(|days| !days.is_empty()) {
+        Some(days) => {
+            let mut date = week_start - Duration::days(1);
+            for weekday in days.iter().take(index + 1) {
+                let offset = (weekday.num_days_from_monday() + 6 - date.weekday().num_days_from_monday()) % 7 + 1;
+                date += Duration::days(offset as i64);
+            }
+            let past_end = (index + 1).saturating_sub(days.len());
+            if past_end > 0 {
+                debug!("Day {} is past the configured training days, counting on from the last one", day_number);
+            }
+            date + Duration::days(past_end as i64)
+        }
+        None => week_start + Duration::days(index as i64),
+    }
+}
+
+/// Parse a load cell into the block's unit, recording a warning if it isn't a load
+fn load_or_warn(cell: &str, ctx: &RecordContext, warnings: &mut Vec<ParseWarning>) -> Option<Load> {
+    match parse_load(cell, ctx.load_unit) {
+        Ok(load) => Some(load.in_unit(ctx.load_unit)),
+        Err(e) => {
+            warnings.push(ParseWarning {
+                block_name: ctx.block_name.to_string(),
+                row_number: ctx.row_number,
+                field: "load",
+                value: cell.to_string(),
+                message: format!("{}; leaving load empty", e),
+            });
+            None
+        }
+    }
+}
+
+/// Parse a sets cell, recording a warning if it isn't a set count
+fn sets_or_warn(cell: &str, ctx: &RecordContext, warnings: &mut Vec<ParseWarning>) -> Option<u32> {
+    match cell.trim().parse::<u32>() {
+        Ok(sets) => Some(sets),
+        Err(_) => {
+            warnings.push(ParseWarning {
+                block_name: ctx.block_name.to_string(),
+                row_number: ctx.row_number,
+                field: "sets",
+                value: cell.to_string(),
+                message: "not a set count; leaving sets empty".to_string(),
+            });
+            None
+        }
+    }
+}
+
+/// Parse a reps cell, recording a warning if it isn't a rep scheme
+fn reps_or_warn(cell: &str, ctx: &RecordContext, warnings: &mut Vec<ParseWarning>) -> Option<RepScheme> {
+    match parse_reps(cell) {
+        Ok(reps) => Some(reps),
+        Err(e) => {
+            warnings.push(ParseWarning {
+                block_name: ctx.block_name.to_string(),
+                row_number: ctx.row_number,
+                field: "reps",
+                value: cell.to_string(),
+                message: format!("{}; leaving reps empty", e),
+            });
+            None
+        }
     }
+}
+
+/// Parse an RPE cell, recording a warning if it isn't an RPE
+fn rpe_or_warn(cell: &str, ctx: &RecordContext, warnings: &mut Vec<ParseWarning>) -> Option<Rpe> {
+    match parse_rpe(cell) {
+        Ok(rpe) => Some(rpe),
+        Err(e) => {
+            warnings.push(ParseWarning {
+                block_name: ctx.block_name.to_string(),
+                row_number: ctx.row_number,
+                field: "rpe",
+                value: cell.to_string(),
+                message: format!("{}; leaving rpe empty", e),
+            });
+            None
+        }
+    }
+}
+
+/// Split one actual record into a row per set when the sheet lists each set's reps
+/// ("5,5,4") or load ("305/315/325"). Lists of different lengths are left as one row.
+/// An RPE list of the same length is split across the rows too.
+fn expand_sets(record: WorkoutRecord, row_number: usize, warnings: &mut Vec<ParseWarning>) -> Vec<WorkoutRecord> {
+    let rep_sets = record.reps.as_ref().map_or(0, |r| r.per_set.len());
+    let load_sets = record.load.as_ref().map_or(0, |l| l.per_set.len());
+    let set_count = rep_sets.max(load_sets);
+    let rpe_sets = record.rpe.as_ref().map_or(0, |r| r.per_set.len());
     
-    let month: u32 = parts[0].parse().unwrap_or(1);
-    let day: u32 = parts[1].parse().unwrap_or(1);
-    let year: i32 = parts[2].parse().unwrap_or(2025);
+    if set_count < 2 {
+        return vec![record];
+    }
+    if rep_sets > 1 && load_sets > 1 && rep_sets != load_sets {
+        warnings.push(ParseWarning {
+            block_name: record.block_name.clone(),
+            row_number,
+            field: "reps",
+            value: record.reps.as_ref().map(|r| r.raw.clone()).unwrap_or_default(),
+            message: format!("lists {} sets but the load lists {}; keeping a single row", rep_sets, load_sets),
+        });
+        return vec![record];
+    }
     
-    if let Some(start_date) = NaiveDate::from_ymd_opt(year, month, day) {
-        // Add days based on workout day (Day 1 = Monday = +0, Day 2 = Tuesday = +1, etc.)
-        let workout_date = start_date + Duration::days((day_number - 1) as i64);
-        Ok(format!("{}/{}/{}", workout_date.month(), workout_date.day(), workout_date.year()))
-    } else {
-        Ok(week_start_date.to_string())
+    (0..set_count)
+        .map(|index| WorkoutRecord {
+            id: format!("{}_s{}", record.id, index + 1),
+            set_number: Some(index as u32 + 1),
+            sets: Some(1),
+            reps: record.reps.as_ref().map(|r| if rep_sets > 1 { r.for_set(index) } else { r.clone() }),
+            load: record.load.as_ref().map(|l| if load_sets > 1 { l.for_set(index) } else { l.clone() }),
+            rpe: record.rpe.as_ref().map(|r| if rpe_sets == set_count { r.for_set(index) } else { r.clone() }),
+            ..record.clone()
+        })
+        .collect()
+}
+
+/// Split a load instruction cell: a concrete weight ("305", "140kg") is a load,
+/// anything else ("find", "70%") is an instruction
+fn split_load_instruction(cell: Option<&str>, ctx: &RecordContext) -> (Option<Load>, Option<String>, Option<LoadInstruction>) {
+    match cell {
+        Some(cell) => match parse_load(cell, ctx.load_unit) {
+            Ok(load) => (Some(load.in_unit(ctx.load_unit)), None, None),
+            Err(_) => (None, Some(cell.to_string()), Some(parse_load_instruction(cell))),
+        },
+        None => (None, None, None),
     }
 }
 
-fn extract_prescribed_data(
-    row: &[String], week: &WeekInfo, block_name: &str, week_start_date: &str,
-    week_number: u32, day_number: u32, workout_date: &str, exercise_name: &str
-) -> Result<Option<WorkoutRecord>> {
+fn extract_prescribed_data(row: &[String], ctx: &RecordContext, warnings: &mut Vec<ParseWarning>) -> Result<Option<WorkoutRecord>> {
+    let week = ctx.week;
     
-    // Prescribed data columns within this week's range
-    let sets_col = week.start_col + 1;
-    let reps_col = week.start_col + 2;
-    let load_instruction_col = week.start_col + 3;
-    let rpe_col = week.start_col + 4;
-    
-    let sets = row.get(sets_col).and_then(|s| s.trim().parse::<u32>().ok());
-    let reps = row.get(reps_col).map(|s| s.trim().to_string()).filter(|s| !s.is_empty());
-    let load_instruction = row.get(load_instruction_col).map(|s| s.trim().to_string()).filter(|s| !s.is_empty());
-    let rpe = row.get(rpe_col).map(|s| s.trim().to_string()).filter(|s| !s.is_empty());
+    // Prescribed data columns within this week's range, located by header label
+    let columns = &ctx.columns.prescribed;
+    let sets = columns.cell(row, RecordField::Sets).and_then(|cell| sets_or_warn(cell, ctx, warnings));
+    let reps = columns.cell(row, RecordField::Reps).and_then(|cell| reps_or_warn(cell, ctx, warnings));
+    let sets = sets.or(reps.as_ref().and_then(|r| r.sets));
+    let (instructed_load, load_instruction, load_instruction_kind) =
+        split_load_instruction(columns.cell(row, RecordField::LoadInstruction), ctx);
+    let load = columns.cell(row, RecordField::Load).and_then(|cell| load_or_warn(cell, ctx, warnings)).or(instructed_load);
+    let rpe = columns.cell(row, RecordField::Rpe).and_then(|cell| rpe_or_warn(cell, ctx, warnings));
+    let notes = columns.cell(row, RecordField::Notes).map(str::to_string);
     
     // Only create record if we have some meaningful prescribed data
-    if sets.is_some() || reps.is_some() || load_instruction.is_some() || rpe.is_some() {
-        let id = format!("{}_{}_w{}_d{}_prescribed_{}", 
-                        block_name.replace(" ", ""), 
-                        exercise_name.replace(" ", "").replace("/", ""), 
-                        week_number, day_number, Utc::now().timestamp_millis());
-        
+    if sets.is_some() || reps.is_some() || load.is_some() || load_instruction.is_some() || rpe.is_some() {
         Ok(Some(WorkoutRecord {
-            id,
-            block_name: block_name.to_string(),
-            week_start_date: week_start_date.to_string(),
-            week_number,
-            day_number,
-            workout_date: workout_date.to_string(),
-            exercise_name: exercise_name.to_string(),
+            id: record_id(ctx.block_name, ctx.week_position, ctx.day_number, ctx.row_number, "prescribed"),
+            block_name: ctx.block_name.to_string(),
+            week_start_date: ctx.week_start,
+            week_number: week.week_number,
+            phase: week.phase.clone(),
+            day_number: ctx.day_number,
+            workout_date: ctx.workout_date,
+            exercise_name: ctx.exercise_name.to_string(),
+            exercise_id: ctx.exercise.id.clone(),
+            exercise_canonical: ctx.exercise.name.clone(),
+            modifiers: ctx.exercise.modifiers.clone(),
+            cue: ctx.exercise.cue.clone(),
+            taxonomy: ctx.exercise.taxonomy.clone(),
             record_type: "prescribed".to_string(),
+            set_number: None,
             sets,
             reps,
-            load: None,
+            load,
             load_instruction,
+            load_instruction_kind,
+            resolved_load: None,
             rpe,
-            notes: None,
+            notes,
             processed_at: Utc::now(),
+            sheet_row: ctx.row_number,
+            // Only a dedicated load column; the instruction cell holds the coach's text
+            load_column: columns.get(RecordField::Load),
         }))
     } else {
         Ok(None)
     }
 }
 
-fn extract_actual_data(
-    row: &[String], week: &WeekInfo, block_name: &str, week_start_date: &str,
-    week_number: u32, day_number: u32, workout_date: &str, exercise_name: &str
-) -> Result<Option<WorkoutRecord>> {
+fn extract_actual_data(row: &[String], ctx: &RecordContext, warnings: &mut Vec<ParseWarning>) -> Result<Option<WorkoutRecord>> {
+    let week = ctx.week;
     
-    // Actual data columns within this week's range
-    let load_col = week.start_col + 6;
-    let sets_col = week.start_col + 7;
-    let reps_col = week.start_col + 8;
-    let rpe_col = week.start_col + 9;
-    let notes_col = week.start_col + 10;
+    // Actual data columns within this week's range, located by header label
+    let columns = &ctx.columns.actual;
+    let (instructed_load, load_instruction, load_instruction_kind) =
+        split_load_instruction(columns.cell(row, RecordField::LoadInstruction), ctx);
+    let load = columns.cell(row, RecordField::Load).and_then(|cell| load_or_warn(cell, ctx, warnings)).or(instructed_load);
+    let sets = columns.cell(row, RecordField::Sets).and_then(|cell| sets_or_warn(cell, ctx, warnings));
+    let reps = columns.cell(row, RecordField::Reps).and_then(|cell| reps_or_warn(cell, ctx, warnings));
+    let sets = sets.or(reps.as_ref().and_then(|r| r.sets));
+    let rpe = columns.cell(row, RecordField::Rpe).and_then(|cell| rpe_or_warn(cell, ctx, warnings));
+    let notes = columns.cell(row, RecordField::Notes).map(str::to_string);
     
-    let load = row.get(load_col).and_then(|s| s.trim().parse::<f64>().ok());
-    let sets = row.get(sets_col).and_then(|s| s.trim().parse::<u32>().ok());
-    let reps = row.get(reps_col).map(|s| s.trim().to_string()).filter(|s| !s.is_empty());
-    let rpe = row.get(rpe_col).map(|s| s.trim().to_string()).filter(|s| !s.is_empty());
-    let notes = row.get(notes_col).map(|s| s.trim().to_string()).filter(|s| !s.is_empty());
+    // A filled-in "date performed" column overrides the calendar-derived date
+    let performed_date = columns
+        .cell(row, RecordField::DatePerformed)
+        .and_then(|cell| match parse_date_in_year(cell, ctx.date_order, ctx.week_start.year()) {
+            Ok(date) => Some(date),
+            Err(e) => {
+                warnings.push(ParseWarning {
+                    block_name: ctx.block_name.to_string(),
+                    row_number: ctx.row_number,
+                    field: "date_performed",
+                    value: cell.to_string(),
+                    message: format!("{}; using the calendar date", e),
+                });
+                None
+            }
+        });
     
     // Only create record if we have some meaningful actual data
     if load.is_some() || sets.is_some() || reps.is_some() || rpe.is_some() || notes.is_some() {
-        let id = format!("{}_{}_w{}_d{}_actual_{}", 
-                        block_name.replace(" ", ""), 
-                        exercise_name.replace(" ", "").replace("/", ""), 
-                        week_number, day_number, Utc::now().timestamp_millis());
-        
         Ok(Some(WorkoutRecord {
-            id,
-            block_name: block_name.to_string(),
-            week_start_date: week_start_date.to_string(),
-            week_number,
-            day_number,
-            workout_date: workout_date.to_string(),
-            exercise_name: exercise_name.to_string(),
+            id: record_id(ctx.block_name, ctx.week_position, ctx.day_number, ctx.row_number, "actual"),
+            block_name: ctx.block_name.to_string(),
+            week_start_date: ctx.week_start,
+            week_number: week.week_number,
+            phase: week.phase.clone(),
+            day_number: ctx.day_number,
+            workout_date: performed_date.unwrap_or(ctx.workout_date),
+            exercise_name: ctx.exercise_name.to_string(),
+            exercise_id: ctx.exercise.id.clone(),
+            exercise_canonical: ctx.exercise.name.clone(),
+            modifiers: ctx.exercise.modifiers.clone(),
+            cue: ctx.exercise.cue.clone(),
+            taxonomy: ctx.exercise.taxonomy.clone(),
             record_type: "actual".to_string(),
+            set_number: None,
             sets,
             reps,
             load,
-            load_instruction: None,
+            load_instruction,
+            load_instruction_kind,
+            resolved_load: None,
             rpe,
             notes,
             processed_at: Utc::now(),
+            sheet_row: ctx.row_number,
+            load_column: None,
         }))
     } else {
         Ok(None)
     }
 }
 
-// Legacy function for backwards compatibility
-pub fn normalize_row(raw_row: Vec<String>) -> Result<WorkoutRecord> {
-    // For now, create a simple record - this will be replaced by the block processor
-    let id = format!("legacy_{}", Utc::now().timestamp_millis());
-    
-    Ok(WorkoutRecord {
-        id,
-        block_name: "Legacy".to_string(),
-        week_start_date: "".to_string(),
-        week_number: 1,
-        day_number: 1,
-        workout_date: "".to_string(),
-        exercise_name: raw_row.get(1).cloned().unwrap_or_default(),
-        record_type: "legacy".to_string(),
-        sets: None,
-        reps: None,
-        load: None,
-        load_instruction: None,
-        rpe: None,
-        notes: None,
-        processed_at: Utc::now(),
-    })
-} 
\ No newline at end of file
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(row_number: usize, cells: &[&str]) -> SheetRow {
+        SheetRow { row_number, cells: cells.iter().map(|cell| cell.to_string()).collect() }
+    }
+
+    /// Two weeks of four columns each after the day and exercise label columns
+    fn block() -> Vec<SheetRow> {
+        vec![
+            row(1, &["", "", "5/19/2025", "", "", "", "5/26/2025"]),
+            row(2, &["", "", "Week 1", "", "", "", "Week 2"]),
+            row(3, &["DAY 1", "Squat", "3", "5", "300", "8", "3", "5", "305", "8"]),
+        ]
+    }
+
+    #[test]
+    fn hashes_each_week_by_its_start_date() {
+        let hashes = week_content_hashes(&block()).unwrap();
+        let keys: Vec<&str> = hashes.iter().map(|(key, _)| key.as_str()).collect();
+        assert_eq!(keys, vec!["5/19/2025", "5/26/2025"]);
+        assert_eq!(week_content_hashes(&block()).unwrap(), hashes);
+    }
+
+    #[test]
+    fn an_edit_only_changes_its_week() {
+        let before = week_content_hashes(&block()).unwrap();
+        let mut edited = block();
+        edited[2].cells[8] = "315".to_string();
+        let after = week_content_hashes(&edited).unwrap();
+        assert_eq!(before[0], after[0]);
+        assert_ne!(before[1], after[1]);
+    }
+
+    #[test]
+    fn label_edits_change_every_week() {
+        let before = week_content_hashes(&block()).unwrap();
+        let mut edited = block();
+        edited[2].cells[1] = "Front Squat".to_string();
+        let after = week_content_hashes(&edited).unwrap();
+        assert_ne!(before[0].1, after[0].1);
+        assert_ne!(before[1].1, after[1].1);
+    }
+
+    #[test]
+    fn repeated_start_dates_get_distinct_keys() {
+        let mut repeated = block();
+        repeated[0].cells[6] = "5/19/2025".to_string();
+        let keys: Vec<String> = week_content_hashes(&repeated).unwrap().into_iter().map(|(key, _)| key).collect();
+        assert_eq!(keys, vec!["5/19/2025", "5/19/2025#2"]);
+    }
+
+    #[test]
+    fn keeps_each_weeks_records_together() {
+        let rows = vec![
+            row(1, &["", "", "5/19/2025", "", "", "", "", "", "", "", "", "", "", "5/26/2025"]),
+            row(6, &["", "DAY 1"]),
+            row(7, &["", "Squat", "", "3", "5", "300", "8", "", "", "", "", "", "", "", "3", "5", "305", "8"]),
+        ];
+        let normalized = normalize_block_data(rows, "Block 1", &ParseOptions::default()).unwrap();
+        let keys: Vec<&str> = normalized.weeks.iter().map(|week| week.key.as_str()).collect();
+        assert_eq!(keys, vec!["5/19/2025", "5/26/2025"]);
+        let second = normalized.week_records("5/26/2025");
+        assert_eq!(second.len(), 1);
+        assert_eq!(second[0].load.as_ref().map(|load| load.value), Some(305.0));
+        assert!(normalized.week_records("6/2/2025").is_empty());
+    }
+
+    #[test]
+    fn strips_per_set_suffixes_from_ids() {
+        assert_eq!(record_id_family("block1_w1_d1_r5_actual_s2"), "block1_w1_d1_r5_actual");
+        assert_eq!(record_id_family("block1_w1_d1_r5_actual"), "block1_w1_d1_r5_actual");
+        assert_eq!(record_id_family("sets_block_w1_d1_r5_actual"), "sets_block_w1_d1_r5_actual");
+    }
+
+    #[test]
+    fn dates_training_days_in_order() {
+        let monday = NaiveDate::from_ymd_opt(2025, 5, 19).unwrap();
+        let days = [Weekday::Sat, Weekday::Mon];
+        assert_eq!(calculate_workout_date(monday, 1, Some(&days)), monday + Duration::days(5));
+        assert_eq!(calculate_workout_date(monday, 2, Some(&days)), monday + Duration::days(7));
+        assert_eq!(calculate_workout_date(monday, 3, Some(&days)), monday + Duration::days(8));
+        assert_eq!(calculate_workout_date(monday, 4, Some(&days)), monday + Duration::days(9));
+        assert_eq!(calculate_workout_date(monday, 2, None), monday + Duration::days(1));
+    }
+}
@@ -0,0 +1,425 @@
+use anyhow::Result;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+use tracing::info;
+use crate::csv_sink::replace_rows;
+use crate::strength::round_to;
+use crate::transform::WorkoutRecord;
+
+/// Lowest RPE that counts a set as a hard set
+pub const HARD_SET_RPE: f64 = 7.0;
+
+/// Volume done in a group of actual sets
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct VolumeTotals {
+    pub sets: u32,
+    pub reps: u32,
+    /// Load x reps over the sets with an absolute load
+    pub tonnage: f64,
+    /// Sets at or above `HARD_SET_RPE`
+    pub hard_sets: u32,
+    // Reps behind `tonnage`, to average the load over
+    pub loaded_reps: u32,
+    // Sum of recorded RPEs and the number of sets they cover, to average the RPE over
+    pub rpe_total: f64,
+    pub rated_sets: u32,
+}
+
+impl VolumeTotals {
+    /// Totals of one actual record; None for prescribed records
+    pub fn of_record(record: &WorkoutRecord) -> Option<VolumeTotals> {
+        if record.record_type != "actual" || (record.reps.is_none() && record.load.is_none()) {
+            return None;
+        }
+
+        let sets = record.set_count().unwrap_or(1);
+        // Timed holds and sets taken to an RPE have no rep count
+        let reps = record
+            .reps
+            .as_ref()
+            .filter(|r| r.seconds.is_none() && !r.to_rpe)
+            .and_then(|r| r.total_reps(Some(sets)))
+            .unwrap_or(0);
+
+        let mut totals = VolumeTotals { sets, reps, ..VolumeTotals::default() };
+
+        // Loads given per side or per implement ("2x50", "50/side") count both sides
+        if let Some(load) = record.load.as_ref().filter(|l| !l.bodyweight_relative && l.value > 0.0) {
+            let sides = if load.per_side { 2.0 } else { 1.0 };
+            let per_set_reps = record.reps.as_ref().map(|r| r.per_set.as_slice()).unwrap_or_default();
+            let tonnage: f64 = if !load.per_set.is_empty() && load.per_set.len() == per_set_reps.len() {
+                load.per_set.iter().zip(per_set_reps).map(|(&weight, &reps)| weight * reps as f64).sum()
+            } else {
+                load.value * reps as f64
+            };
+            totals.tonnage = tonnage * sides;
+            totals.loaded_reps = reps;
+        }
+
+        if let Some(rpe) = record.rpe.as_ref().filter(|r| !r.per_set.is_empty()) {
+            // One RPE for the record covers every set; a list rates one set per value
+            let ratings: Vec<(f64, u32)> = match rpe.per_set.as_slice() {
+                [single] => vec![(*single, sets)],
+                values => values.iter().take(sets as usize).map(|&value| (value, 1)).collect(),
+            };
+            for (value, count) in ratings {
+                totals.rpe_total += value * count as f64;
+                totals.rated_sets += count;
+                if value >= HARD_SET_RPE {
+                    totals.hard_sets += count;
+                }
+            }
+        }
+
+        Some(totals)
+    }
+
+    pub fn add(&mut self, other: &VolumeTotals) {
+        self.sets += other.sets;
+        self.reps += other.reps;
+        self.tonnage += other.tonnage;
+        self.hard_sets += other.hard_sets;
+        self.loaded_reps += other.loaded_reps;
+        self.rpe_total += other.rpe_total;
+        self.rated_sets += other.rated_sets;
+    }
+
+    /// Average load per loaded rep
+    pub fn average_load(&self) -> Option<f64> {
+        (self.loaded_reps > 0).then(|| round_to(self.tonnage / self.loaded_reps as f64, 0.01))
+    }
+
+    /// Average RPE per rated set
+    pub fn average_rpe(&self) -> Option<f64> {
+        (self.rated_sets > 0).then(|| round_to(self.rpe_total / self.rated_sets as f64, 0.01))
+    }
+}
+
+/// One exercise's volume within a week
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ExerciseVolume {
+    pub name: String,
+    pub movement_pattern: String,
+    pub totals: VolumeTotals,
+}
+
+/// A block week's volume by exercise ID, kept in state so summaries cover weeks not
+/// re-normalized this run
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WeekVolume {
+    pub week_number: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub phase: Option<String>,
+    pub unit: String,
+    pub exercises: BTreeMap<String, ExerciseVolume>,
+}
+
+impl WeekVolume {
+    /// Add another part of the same week's volume to this one
+    fn absorb(&mut self, other: WeekVolume) {
+        if self.unit.is_empty() {
+            self.unit = other.unit;
+        }
+        for (exercise_id, volume) in other.exercises {
+            match self.exercises.get_mut(&exercise_id) {
+                Some(stored) => stored.totals.add(&volume.totals),
+                None => {
+                    self.exercises.insert(exercise_id, volume);
+                }
+            }
+        }
+    }
+}
+
+/// Weekly volume of every block, keyed by block name then week start date
+pub type VolumeHistory = BTreeMap<String, BTreeMap<NaiveDate, WeekVolume>>;
+
+/// Fold the records' volume into the stored weeks.
+///
+/// With `whole_weeks` (block mode), a re-normalized week is emitted whole, so its records
+/// replace what was stored for it. Otherwise (legacy raw_range mode) each record is seen once,
+/// as rows are appended to the sheet, so its volume is added to the stored week.
+pub fn record_volume(history: &mut VolumeHistory, records: &[WorkoutRecord], whole_weeks: bool) {
+    let mut weeks: HashMap<(&str, NaiveDate), WeekVolume> = HashMap::new();
+
+    for record in records {
+        let week = weeks.entry((&record.block_name, record.week_start_date)).or_insert_with(|| WeekVolume {
+            week_number: record.week_number,
+            phase: record.phase.clone(),
+            ..WeekVolume::default()
+        });
+        let Some(totals) = VolumeTotals::of_record(record) else {
+            continue;
+        };
+        if let Some(load) = &record.load {
+            week.unit = load.unit.to_string();
+        }
+        let exercise = week.exercises.entry(record.exercise_id.clone()).or_insert_with(|| ExerciseVolume {
+            name: record.exercise_canonical.clone(),
+            movement_pattern: record.taxonomy.movement_pattern.as_str().to_string(),
+            ..ExerciseVolume::default()
+        });
+        exercise.totals.add(&totals);
+    }
+
+    for ((block_name, week_start), week) in weeks {
+        let stored_weeks = history.entry(block_name.to_string()).or_default();
+        match stored_weeks.get_mut(&week_start) {
+            Some(stored) if !whole_weeks => stored.absorb(week),
+            _ => {
+                stored_weeks.insert(week_start, week);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SummaryPeriod {
+    Week,
+    Block,
+}
+
+impl SummaryPeriod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SummaryPeriod::Week => "week",
+            SummaryPeriod::Block => "block",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SummaryGroup {
+    Exercise,
+    MovementPattern,
+}
+
+impl SummaryGroup {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SummaryGroup::Exercise => "exercise",
+            SummaryGroup::MovementPattern => "movement_pattern",
+        }
+    }
+}
+
+/// Volume of one exercise or movement pattern over a week or a whole block
+#[derive(Debug, Clone, Serialize)]
+pub struct VolumeSummary {
+    pub period: SummaryPeriod,
+    pub block_name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub week_start_date: Option<NaiveDate>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub week_number: Option<u32>,
+    pub group_by: SummaryGroup,
+    /// Exercise ID or movement pattern
+    pub key: String,
+    pub name: String,
+    pub unit: String,
+    pub sets: u32,
+    pub reps: u32,
+    pub tonnage: f64,
+    pub hard_sets: u32,
+    pub average_load: Option<f64>,
+    pub average_rpe: Option<f64>,
+}
+
+impl VolumeSummary {
+    pub fn to_csv_headers() -> Vec<String> {
+        [
+            "period", "block_name", "week_start_date", "week_number", "group_by", "key", "name",
+            "unit", "sets", "reps", "tonnage", "hard_sets", "average_load", "average_rpe",
+        ]
+        .into_iter()
+        .map(str::to_string)
+        .collect()
+    }
+
+    pub fn to_csv_row(&self) -> Vec<String> {
+        vec![
+            self.period.as_str().to_string(),
+            self.block_name.clone(),
+            self.week_start_date.map(|d| d.to_string()).unwrap_or_default(),
+            self.week_number.map(|n| n.to_string()).unwrap_or_default(),
+            self.group_by.as_str().to_string(),
+            self.key.clone(),
+            self.name.clone(),
+            self.unit.clone(),
+            self.sets.to_string(),
+            self.reps.to_string(),
+            self.tonnage.to_string(),
+            self.hard_sets.to_string(),
+            self.average_load.map(|v| v.to_string()).unwrap_or_default(),
+            self.average_rpe.map(|v| v.to_string()).unwrap_or_default(),
+        ]
+    }
+}
+
+/// Weekly and per-block summaries by exercise and by movement pattern
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct VolumeReport {
+    pub weekly: Vec<VolumeSummary>,
+    pub blocks: Vec<VolumeSummary>,
+}
+
+/// The week or block a set of summaries covers
+struct SummaryScope<'a> {
+    period: SummaryPeriod,
+    block_name: &'a str,
+    week: Option<(NaiveDate, u32)>,
+    unit: &'a str,
+}
+
+impl SummaryScope<'_> {
+    fn summary(&self, group_by: SummaryGroup, key: &str, name: &str, totals: &VolumeTotals) -> VolumeSummary {
+        VolumeSummary {
+            period: self.period,
+            block_name: self.block_name.to_string(),
+            week_start_date: self.week.map(|(start, _)| start),
+            week_number: self.week.map(|(_, number)| number),
+            group_by,
+            key: key.to_string(),
+            name: name.to_string(),
+            unit: self.unit.to_string(),
+            sets: totals.sets,
+            reps: totals.reps,
+            tonnage: round_to(totals.tonnage, 0.01),
+            hard_sets: totals.hard_sets,
+            average_load: totals.average_load(),
+            average_rpe: totals.average_rpe(),
+        }
+    }
+
+    /// One summary per exercise, then one per movement pattern
+    fn summaries<'e>(&self, exercises: impl Iterator<Item = (&'e str, &'e ExerciseVolume)>) -> Vec<VolumeSummary> {
+        let mut patterns: BTreeMap<&str, VolumeTotals> = BTreeMap::new();
+        let mut summaries: Vec<VolumeSummary> = exercises
+            .map(|(id, exercise)| {
+                patterns.entry(&exercise.movement_pattern).or_default().add(&exercise.totals);
+                self.summary(SummaryGroup::Exercise, id, &exercise.name, &exercise.totals)
+            })
+            .collect();
+        summaries.extend(
+            patterns
+                .iter()
+                .map(|(pattern, totals)| self.summary(SummaryGroup::MovementPattern, pattern, pattern, totals)),
+        );
+        summaries
+    }
+}
+
+/// Build the report from the stored weekly volume, in block and week order
+pub fn summarize(history: &VolumeHistory) -> VolumeReport {
+    let mut report = VolumeReport::default();
+
+    for (block_name, weeks) in history {
+        let mut block_exercises: BTreeMap<&str, ExerciseVolume> = BTreeMap::new();
+
+        for (week_start, week) in weeks {
+            let scope = SummaryScope {
+                period: SummaryPeriod::Week,
+                block_name,
+                week: Some((*week_start, week.week_number)),
+                unit: &week.unit,
+            };
+            report.weekly.extend(scope.summaries(week.exercises.iter().map(|(id, e)| (id.as_str(), e))));
+
+            for (id, exercise) in &week.exercises {
+                block_exercises
+                    .entry(id)
+                    .or_insert_with(|| ExerciseVolume { totals: VolumeTotals::default(), ..exercise.clone() })
+                    .totals
+                    .add(&exercise.totals);
+            }
+        }
+
+        // Loads are all converted to one unit, so any week's unit stands for the block
+        let unit = weeks.values().map(|week| week.unit.as_str()).find(|unit| !unit.is_empty()).unwrap_or_default();
+        let scope = SummaryScope { period: SummaryPeriod::Block, block_name, week: None, unit };
+        report.blocks.extend(scope.summaries(block_exercises.iter().map(|(id, e)| (*id, e))));
+    }
+
+    report
+}
+
+
+/// Write the report to `volume_weekly.csv`, `volume_blocks.csv` and `volume.json` in `dir`
+pub fn write_report(report: &VolumeReport, dir: &str, ensure_directories: bool) -> Result<()> {
+    let dir = Path::new(dir);
+    let headers = VolumeSummary::to_csv_headers();
+    for (file, summaries) in [("volume_weekly.csv", &report.weekly), ("volume_blocks.csv", &report.blocks)] {
+        let rows: Vec<Vec<String>> = summaries.iter().map(VolumeSummary::to_csv_row).collect();
+        replace_rows(&dir.join(file).to_string_lossy(), &headers, &rows, ensure_directories)?;
+    }
+
+    let json_path = dir.join("volume.json");
+    fs::write(&json_path, serde_json::to_string_pretty(report)?)?;
+    info!("Wrote volume report to {}", json_path.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sheets::SheetRow;
+    use crate::transform::{normalize_block_data, ParseOptions};
+
+    fn row(row_number: usize, cells: &[&str]) -> SheetRow {
+        SheetRow { row_number, cells: cells.iter().map(|cell| cell.to_string()).collect() }
+    }
+
+    /// A week of squats at RPE 8 and per-side bench presses at RPE 6
+    fn records() -> Vec<WorkoutRecord> {
+        let rows = vec![
+            row(1, &["", "", "5/19/2025"]),
+            row(2, &["", "", "Week 1"]),
+            row(6, &["", "DAY 1"]),
+            row(7, &["", "Squat", "", "3", "5", "300", "8", "", "300", "3", "5", "8"]),
+            row(8, &["", "Bench", "", "3", "8", "", "6", "", "50/side", "3", "8", "6"]),
+        ];
+        normalize_block_data(rows, "Block 1", &ParseOptions::default()).unwrap().records
+    }
+
+    fn actual<'a>(records: &'a [WorkoutRecord], exercise: &str) -> &'a WorkoutRecord {
+        records.iter().find(|r| r.exercise_canonical == exercise && r.record_type == "actual").unwrap()
+    }
+
+    #[test]
+    fn totals_count_tonnage_both_sides_and_hard_sets() {
+        let records = records();
+        let squat = VolumeTotals::of_record(actual(&records, "Squat")).unwrap();
+        assert_eq!((squat.sets, squat.reps, squat.tonnage, squat.hard_sets), (3, 15, 4500.0, 3));
+        assert_eq!(squat.average_load(), Some(300.0));
+
+        let bench = VolumeTotals::of_record(actual(&records, "Bench")).unwrap();
+        assert_eq!((bench.sets, bench.reps, bench.tonnage, bench.hard_sets), (3, 24, 2400.0, 0));
+        assert_eq!(bench.average_rpe(), Some(6.0));
+
+        let prescribed = records.iter().find(|r| r.record_type == "prescribed").unwrap();
+        assert_eq!(VolumeTotals::of_record(prescribed), None);
+    }
+
+    #[test]
+    fn whole_weeks_replace_and_appended_rows_add_up() {
+        let records = records();
+        let week_start = NaiveDate::from_ymd_opt(2025, 5, 19).unwrap();
+        let squat_sets = |history: &VolumeHistory| {
+            let week = &history["Block 1"][&week_start];
+            week.exercises.values().find(|e| e.name == "Squat").unwrap().totals.sets
+        };
+
+        let mut history = VolumeHistory::new();
+        record_volume(&mut history, &records, true);
+        record_volume(&mut history, &records, true);
+        assert_eq!(squat_sets(&history), 3);
+
+        record_volume(&mut history, &records, false);
+        assert_eq!(squat_sets(&history), 6);
+    }
+}
@@ -0,0 +1,181 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use crate::hashing::stable_hash;
+use crate::load::Load;
+use crate::transform::{group_by_row, WorkoutRecord};
+
+/// One exercise of one session: a prescription joined with the actuals logged on its row
+#[derive(Debug, Clone)]
+pub struct WideRecord {
+    /// The row's record ID without the record type
+    pub id: String,
+    pub block_name: String,
+    pub week_start_date: NaiveDate,
+    pub week_number: u32,
+    pub phase: Option<String>,
+    pub day_number: u32,
+    pub workout_date: NaiveDate,
+    pub exercise_name: String,
+    pub exercise_id: String,
+    pub exercise_canonical: String,
+    pub modifiers: Vec<String>,
+    pub cue: Option<String>,
+    pub prescribed_sets: Option<u32>,
+    /// The prescribed reps cell: "5", "8-10", "3x5", "AMRAP"
+    pub prescribed_reps: Option<String>,
+    pub prescribed_load: Option<String>,
+    pub prescribed_rpe: Option<String>,
+    pub load_instruction: Option<String>,
+    /// The instruction worked out against the maxes table; not part of the content hash
+    pub resolved_load: Option<f64>,
+    // Per-set values are joined ("300/310/320", "5,5,4"); a value every set shares is written once
+    pub actual_load: Option<String>,
+    pub actual_sets: Option<u32>,
+    pub actual_reps: Option<String>,
+    pub actual_rpe: Option<String>,
+    /// Unit of both loads
+    pub load_unit: Option<String>,
+    /// The actual notes, else the prescribed ones
+    pub notes: Option<String>,
+    pub processed_at: DateTime<Utc>,
+}
+
+impl WideRecord {
+    pub fn to_csv_headers() -> Vec<String> {
+        [
+            "id", "block_name", "week_start_date", "week_number", "phase", "day_number", "workout_date",
+            "exercise_name", "exercise_id", "exercise_canonical", "modifiers", "cue",
+            "prescribed_sets", "prescribed_reps", "prescribed_load", "prescribed_rpe", "load_instruction",
+            "actual_load", "actual_sets", "actual_reps", "actual_rpe", "load_unit", "notes",
+            "resolved_load", "content_hash", "processed_at",
+        ]
+        .into_iter()
+        .map(str::to_string)
+        .collect()
+    }
+
+    pub fn to_csv_row(&self) -> Vec<String> {
+        let mut row = vec![self.id.clone()];
+        row.extend(self.content_fields());
+        row.push(self.resolved_load.map(|l| l.to_string()).unwrap_or_default());
+        row.push(self.content_hash());
+        row.push(self.processed_at.to_rfc3339());
+        row
+    }
+
+    /// Hash of the record's content, excluding `id` and `processed_at`
+    pub fn content_hash(&self) -> String {
+        stable_hash(&self.content_fields())
+    }
+
+    /// CSV cells from `block_name` through `notes`
+    fn content_fields(&self) -> Vec<String> {
+        let text = |value: &Option<String>| value.clone().unwrap_or_default();
+        let number = |value: Option<u32>| value.map(|n| n.to_string()).unwrap_or_default();
+        vec![
+            self.block_name.clone(),
+            self.week_start_date.to_string(),
+            self.week_number.to_string(),
+            text(&self.phase),
+            self.day_number.to_string(),
+            self.workout_date.to_string(),
+            self.exercise_name.clone(),
+            self.exercise_id.clone(),
+            self.exercise_canonical.clone(),
+            self.modifiers.join(";"),
+            text(&self.cue),
+            number(self.prescribed_sets),
+            text(&self.prescribed_reps),
+            text(&self.prescribed_load),
+            text(&self.prescribed_rpe),
+            text(&self.load_instruction),
+            text(&self.actual_load),
+            number(self.actual_sets),
+            text(&self.actual_reps),
+            text(&self.actual_rpe),
+            text(&self.load_unit),
+            text(&self.notes),
+        ]
+    }
+}
+
+/// Join each sheet row's prescription and actuals into one wide record, in sheet order
+pub fn join_records(records: &[WorkoutRecord]) -> Vec<WideRecord> {
+    group_by_row(records)
+        .into_iter()
+        .map(|row| {
+            let first = row.first();
+            let prescribed = row.prescribed;
+            let actuals = &row.actuals;
+
+            WideRecord {
+                id: row.key.to_string(),
+                block_name: first.block_name.clone(),
+                week_start_date: first.week_start_date,
+                week_number: first.week_number,
+                phase: first.phase.clone(),
+                day_number: first.day_number,
+                workout_date: actuals.first().map_or(first.workout_date, |a| a.workout_date),
+                exercise_name: first.exercise_name.clone(),
+                exercise_id: first.exercise_id.clone(),
+                exercise_canonical: first.exercise_canonical.clone(),
+                modifiers: first.modifiers.clone(),
+                cue: first.cue.clone(),
+                prescribed_sets: prescribed.and_then(WorkoutRecord::set_count),
+                prescribed_reps: prescribed.and_then(|p| p.reps.as_ref()).map(|r| r.raw.clone()),
+                prescribed_load: prescribed.and_then(|p| p.load.as_ref()).map(load_label),
+                prescribed_rpe: prescribed.and_then(|p| p.rpe.as_ref()).map(|r| r.values_label()).filter(|v| !v.is_empty()),
+                load_instruction: prescribed.and_then(|p| p.load_instruction.clone()),
+                resolved_load: prescribed.and_then(|p| p.resolved_load),
+                actual_load: sets_label(actuals.iter().filter_map(|a| a.load.as_ref()).map(load_label), "/"),
+                actual_sets: (!actuals.is_empty()).then(|| actuals.iter().map(|a| a.set_count().unwrap_or(1)).sum()),
+                actual_reps: sets_label(
+                    actuals.iter().filter_map(|a| a.reps.as_ref()).flat_map(|r| {
+                        if r.per_set.is_empty() {
+                            vec![r.raw.clone()]
+                        } else {
+                            r.per_set.iter().map(|n| n.to_string()).collect()
+                        }
+                    }),
+                    ",",
+                ),
+                actual_rpe: sets_label(
+                    actuals.iter().filter_map(|a| a.rpe.as_ref()).flat_map(|r| r.per_set.iter().map(|v| v.to_string())),
+                    ",",
+                ),
+                load_unit: prescribed
+                    .into_iter()
+                    .chain(actuals.iter().copied())
+                    .find_map(|r| r.load.as_ref())
+                    .map(|l| l.unit.to_string()),
+                notes: actuals.iter().find_map(|a| a.notes.clone()).or_else(|| prescribed.and_then(|p| p.notes.clone())),
+                processed_at: first.processed_at,
+            }
+        })
+        .collect()
+}
+
+/// A load in the configured unit: the per-set weights when listed, "BW+25" for loads
+/// relative to bodyweight
+fn load_label(load: &Load) -> String {
+    let weight = if load.per_set.is_empty() { load.value.to_string() } else { load.per_set_label() };
+    if !load.bodyweight_relative {
+        weight
+    } else if load.value == 0.0 {
+        "BW".to_string()
+    } else if load.value < 0.0 {
+        format!("BW{}", weight)
+    } else {
+        format!("BW+{}", weight)
+    }
+}
+
+/// Per-set values joined with `separator`, or the single value when every set shares it
+fn sets_label(values: impl Iterator<Item = String>, separator: &str) -> Option<String> {
+    let values: Vec<String> = values.filter(|value| !value.is_empty()).collect();
+    let first = values.first()?;
+    if values.iter().all(|value| value == first) {
+        Some(first.clone())
+    } else {
+        Some(values.join(separator))
+    }
+}